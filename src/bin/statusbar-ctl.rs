@@ -0,0 +1,52 @@
+//! Sends commands to a running statusbar-rs
+//!
+//! Usage: `statusbar-ctl [--socket <path>] job start|done <name>`
+//!
+//! The socket path may contain `%u` and `%h` for the name and home directory of the user.
+
+use statusbar_rs::blocks::expand_path;
+use std::io::Write as _;
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    let mut args = std::env::args().skip(1).collect::<Vec<String>>();
+    let socket = if args.first().is_some_and(|arg| arg == "--socket") && args.len() > 1 {
+        let template = args.drain(..2).nth(1).unwrap_or_default();
+        let Some(socket) = expand_path(&template) else {
+            eprintln!("Invalid socket path {template}");
+            return ExitCode::FAILURE;
+        };
+        Some(socket)
+    } else {
+        None
+    };
+    let (command, name) = match args.as_slice() {
+        [block, command, name] if block == "job" && (command == "start" || command == "done") => {
+            (command, name)
+        }
+        _ => {
+            eprintln!("Usage: statusbar-ctl [--socket <path>] job start|done <name>");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let socket = if let Some(socket) = socket {
+        socket
+    } else {
+        let Some(runtime_dir) = std::env::var_os("XDG_RUNTIME_DIR") else {
+            eprintln!("XDG_RUNTIME_DIR is not set");
+            return ExitCode::FAILURE;
+        };
+        PathBuf::from(runtime_dir).join("statusbar-rs/jobs")
+    };
+
+    let sent = UnixStream::connect(&socket)
+        .and_then(|mut stream| stream.write_all(format!("{command} {name}\n").as_bytes()));
+    if let Err(e) = sent {
+        eprintln!("Failed to talk to {}: {e}", socket.display());
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
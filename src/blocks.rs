@@ -1,18 +1,88 @@
+pub mod accessibility;
+pub mod ambient_light_block;
 pub mod battery_block;
+mod battery_history;
 mod bluetooth_battery;
+pub mod break_block;
+pub mod bus;
+pub mod call_block;
+pub mod clipboard_sync_block;
+pub mod color;
+pub mod confirm;
+pub mod connectivity_block;
+pub mod container_block;
+pub mod cpu_block;
 pub mod date_block;
 pub mod default_route_block;
+pub mod diagnostics_block;
+pub mod disk_block;
+pub mod download_block;
 pub mod dunst_block;
+pub mod energy_block;
+pub mod fan_block;
 mod hidpp;
+pub mod hotspot_block;
+mod http;
+pub mod http_json_block;
+mod i3_ipc;
+pub mod input_method_block;
+pub mod job_block;
+pub mod launcher_block;
 pub mod load_block;
+pub mod location_block;
+pub mod lsm_block;
+pub mod mail_queue_block;
+pub mod matrix_block;
+pub mod memory_block;
+pub mod modem_block;
+mod netlink;
+pub mod network_mount_block;
+pub mod nix_block;
+mod nl80211;
+pub mod notification_counter_block;
+pub mod notify;
+pub mod printer_block;
+pub mod restarts_block;
+pub mod screen_time_block;
+pub mod screenshot_block;
+pub mod script_block;
+pub mod simple_poll_block;
+pub mod steady_clock;
+pub mod switch_block;
 pub mod temperature_block;
+pub mod text;
+pub mod theme;
+pub mod threshold;
+pub mod throttle_block;
+pub mod thunderbolt_block;
+mod tickets;
+pub mod timer_block;
+pub mod timewarrior_block;
+pub mod torrent_block;
+pub mod traffic_quota_block;
+pub mod units;
+mod upower;
+pub mod vmstat_block;
 pub mod volume_block;
+pub mod wifi_block;
+pub mod wireguard_block;
+pub mod workday_block;
+pub mod xrun_block;
 
 use std::fmt::{Display, Formatter};
+use std::os::unix::fs::DirBuilderExt as _;
+use std::os::unix::process::CommandExt as _;
+use std::panic::Location;
+use std::path::PathBuf;
+use std::process::Command;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::Arc;
 
 use super::I3Event;
+use bus::{Bus, BusEvent};
 
 #[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Markup {
     Pango,
 }
@@ -25,19 +95,304 @@ impl Display for Markup {
     }
 }
 
+/// How i3bar aligns the text when the block is wider than it
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// The minimum width of a block
+#[derive(Debug, serde::Serialize)]
+#[serde(untagged)]
+pub enum MinWidth {
+    Pixels(u32),
+    /// As wide as this text
+    Text(String),
+}
+
 #[derive(Debug, Default, serde::Serialize)]
 pub struct I3Block {
     pub full_text: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub short_text: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub color: Option<String>,
+    pub color: Option<color::Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background: Option<color::Color>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border: Option<color::Color>,
+    /// Border widths in pixels, i3bar defaults to 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_top: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_right: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_bottom: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub border_left: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_width: Option<MinWidth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub align: Option<Align>,
+    /// Lets i3bar highlight the block
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub urgent: bool,
+    /// Whether to draw a separator after the block, i3bar defaults to true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub separator: Option<bool>,
+    /// Gap after the block in pixels
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub separator_block_width: Option<u32>,
+    /// Set by the bar to route clicks back to the block, blocks leave this empty
     pub name: String,
+    /// Tells apart multiple blocks of the same kind, passed back in [`I3Event::instance`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub markup: Option<Markup>,
+    /// Additional information that i3bar has no way to display. The first line is the summary.
+    #[serde(skip)]
+    pub tooltip: Option<String>,
+    /// Whether everything but the first line of the tooltip is pango markup instead of plain text
+    #[serde(skip)]
+    pub tooltip_markup: bool,
 }
 
+impl I3Block {
+    /// Reserves the width of `template` and aligns the text to the right, so numbers changing
+    /// their width don't shift the rest of the bar
+    #[must_use]
+    pub fn fixed_width(self, template: &str) -> Self {
+        Self {
+            min_width: Some(MinWidth::Text(template.to_owned())),
+            align: Some(Align::Right),
+            ..self
+        }
+    }
+
+    /// Marks blocks with the critical or good color with a symbol and an underline if the
+    /// palette asks for it
+    pub fn add_markers(&mut self) {
+        let palette = color::Palette::current();
+        if !palette.markers {
+            return;
+        }
+        let symbol = match self.color {
+            Some(color) if color == palette.critical => "⚠",
+            Some(color) if color == palette.good => "✓",
+            _ => return,
+        };
+        self.full_text = format!("{symbol} {}", self.full_text);
+        if let Some(short_text) = &mut self.short_text {
+            *short_text = format!("{symbol} {short_text}");
+        }
+        self.border = self.color;
+        self.border_top = Some(0);
+        self.border_right = Some(0);
+        self.border_bottom = Some(2);
+        self.border_left = Some(0);
+    }
+}
+
+/// Escapes text so it can be embedded into pango markup
+#[must_use]
+pub fn escape_markup(text: &str) -> String {
+    let mut ret = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => ret.push_str("&amp;"),
+            '<' => ret.push_str("&lt;"),
+            '>' => ret.push_str("&gt;"),
+            '\'' => ret.push_str("&apos;"),
+            '"' => ret.push_str("&quot;"),
+            c => ret.push(c),
+        }
+    }
+    ret
+}
+
+/// A block of the bar.
+///
+/// The bar calls [`Block::render`] whenever it redraws and [`Block::click`] for every click on
+/// the block. Blocks that change in the background call [`BlockContext::request_update`] so the
+/// bar doesn't have to wait for its next regular redraw.
 pub trait Block {
+    /// Returns what to display, or `None` to hide the block
     fn render(&self) -> Option<I3Block>;
+    /// Handles a click on the block
     fn click(&self, event: &I3Event);
+    /// Called after the machine resumed from suspend, so rates can be reset and stale values
+    /// refreshed
+    fn resume(&self) {}
+    /// Called when the wall clock jumped or the timezone changed, so values derived from it can
+    /// be recomputed
+    fn clock_changed(&self) {}
+    /// Called before the bar exits, so files like sockets can be removed
+    fn shutdown(&self) {}
+    /// Returns the name of the block for diagnostics
+    fn name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// What blocks get from the bar they are embedded in
+#[derive(Clone)]
+pub struct BlockContext {
+    /// Gets where each update was requested from
+    update: Sender<&'static Location<'static>>,
+    bus: Arc<Bus>,
+}
+
+impl BlockContext {
+    /// Creates a context that sends on `update` whenever a block wants to be redrawn, with the
+    /// location of the request so wakeups can be attributed to blocks
+    #[must_use]
+    pub fn new(update: Sender<&'static Location<'static>>) -> Self {
+        Self {
+            update,
+            bus: Arc::default(),
+        }
+    }
+
+    /// Asks the bar to redraw soon
+    #[track_caller]
+    pub fn request_update(&self) {
+        // The bar is gone if this fails, so nobody is waiting for an update anymore
+        let _idc = self.update.send(Location::caller());
+    }
+
+    /// Tells all blocks subscribed to the bus about an event
+    pub fn publish(&self, event: BusEvent) {
+        self.bus.publish(event);
+    }
+
+    /// Returns a receiver for events published by other blocks, starting with the last event of
+    /// every kind
+    #[must_use]
+    pub fn subscribe(&self) -> Receiver<BusEvent> {
+        self.bus.subscribe()
+    }
+}
+
+/// Returns the path of a file in our state directory, creating the directory if needed
+#[must_use]
+pub fn state_file(name: &str) -> Option<PathBuf> {
+    let mut path = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state"))
+        })?;
+    path.push("statusbar-rs");
+    std::fs::create_dir_all(&path).ok()?;
+    path.push(name);
+    Some(path)
+}
+
+/// Returns the path of a file in our runtime directory, creating the directory if needed
+#[must_use]
+pub fn runtime_file(name: &str) -> Option<PathBuf> {
+    let mut path = PathBuf::from(std::env::var_os("XDG_RUNTIME_DIR")?);
+    path.push("statusbar-rs");
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&path)
+        .ok()?;
+    path.push(name);
+    Some(path)
+}
+
+/// Returns the path of a file in our config directory
+#[must_use]
+pub fn config_file(name: &str) -> Option<PathBuf> {
+    let mut path = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    path.push("statusbar-rs");
+    path.push(name);
+    Some(path)
+}
+
+/// Makes the program start with no signals blocked, whatever the spawning thread blocks, so it
+/// can be stopped with a signal
+pub(crate) fn unblock_signals(command: &mut Command) -> &mut Command {
+    // SAFETY: Only async-signal-safe functions are called between fork() and exec(), and the set
+    // is initialized before it's used
+    unsafe {
+        command.pre_exec(|| {
+            let mut set = std::mem::zeroed::<libc::sigset_t>();
+            libc::sigemptyset(&raw mut set);
+            if libc::sigprocmask(libc::SIG_SETMASK, &raw const set, std::ptr::null_mut()) < 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Looks up the name and home directory of the current user in the password database
+fn current_user() -> Option<(String, String)> {
+    let mut passwd = std::mem::MaybeUninit::<libc::passwd>::uninit();
+    let mut result = std::ptr::null_mut();
+    let mut buf = vec![0; 1024];
+    loop {
+        // SAFETY: All pointers are valid, and the strings are written to the buffer of the given
+        // length instead of static storage, which makes this safe to call from any thread
+        let ret = unsafe {
+            libc::getpwuid_r(
+                libc::geteuid(),
+                passwd.as_mut_ptr(),
+                buf.as_mut_ptr(),
+                buf.len(),
+                &raw mut result,
+            )
+        };
+        match ret {
+            0 if !result.is_null() => break,
+            libc::ERANGE if buf.len() < 1 << 20 => buf.resize(buf.len() * 2, 0),
+            _ => return None,
+        }
+    }
+    // SAFETY: The entry was found, so it's initialized and its strings point into the buffer
+    unsafe {
+        let passwd = passwd.assume_init_ref();
+        Some((
+            std::ffi::CStr::from_ptr(passwd.pw_name)
+                .to_string_lossy()
+                .into_owned(),
+            std::ffi::CStr::from_ptr(passwd.pw_dir)
+                .to_string_lossy()
+                .into_owned(),
+        ))
+    }
+}
+
+/// Expands `%u` to the name and `%h` to the home directory of the current user, and `%%` to `%`
+#[must_use]
+pub fn expand_path(template: &str) -> Option<PathBuf> {
+    let (user, passwd_home) = current_user()?;
+    // Like the shell, prefer $HOME over the password database
+    let home = std::env::var("HOME")
+        .ok()
+        .filter(|home| !home.is_empty())
+        .unwrap_or(passwd_home);
+
+    let mut ret = String::with_capacity(template.len());
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            ret.push(c);
+            continue;
+        }
+        match chars.next()? {
+            'u' => ret.push_str(&user),
+            'h' => ret.push_str(&home),
+            '%' => ret.push('%'),
+            _ => return None,
+        }
+    }
+    Some(PathBuf::from(ret))
 }
@@ -0,0 +1,154 @@
+//! Makes the bar easier to read and click for everyone who needs it
+
+use super::color::{Color, Palette};
+use super::{Align, I3Block, MinWidth};
+use std::sync::RwLock;
+
+/// The profile in use, if any
+static CURRENT: RwLock<Option<Accessibility>> = RwLock::new(None);
+
+/// Text labels replacing the emoji of the blocks
+const LABELS: [(&str, &str); 55] = [
+    ("🔋", "BAT"),
+    ("🪫", "BAT LOW"),
+    ("⚡", "PWR"),
+    ("🎧", "HEADSET"),
+    ("⏱", "TIME"),
+    ("⏲", "TIMER"),
+    ("⏸", "PAUSE"),
+    ("⏹", "STOP"),
+    ("⏺", "REC"),
+    ("⏯", "MEDIA"),
+    ("💻", "LAPTOP"),
+    ("⚙", "CPU"),
+    ("📲", "HOTSPOT"),
+    ("📶", "WIFI"),
+    ("📡", "WWAN"),
+    ("⌨", "KBD"),
+    ("🖱", "MOUSE"),
+    ("🖲", "TRACKBALL"),
+    ("🎮", "GAMEPAD"),
+    ("🎹", "KEYS"),
+    ("🌀", "FAN"),
+    ("📱", "PHONE"),
+    ("📷", "CAM"),
+    ("📹", "VIDEO"),
+    ("📽", "PRESENTER"),
+    ("📼", "DISPLAY"),
+    ("📻", "RECEIVER"),
+    ("📋", "CLIP"),
+    ("📞", "CALL"),
+    ("🔔", "NOTIF"),
+    ("📢", "DND"),
+    ("✉", "MAIL"),
+    ("🌡", "TEMP"),
+    ("☀", "LIGHT"),
+    ("🎚", "AUDIO"),
+    ("💾", "DISK"),
+    ("🎉", "HOLIDAY"),
+    ("📦", "CONT"),
+    ("📍", "LOCATION"),
+    ("☕", "BREAK"),
+    ("🖨", "PRINTER"),
+    ("✍", "TABLET"),
+    ("🛜", "NET"),
+    ("🎵", "AUDIO"),
+    ("❓", "?"),
+    ("🖥", "SCREEN"),
+    ("🐢", "SLOW"),
+    ("❄", "NIX"),
+    ("🔒", "VPN"),
+    ("🔓", "VPN OFF"),
+    ("🌐", "INTERNET"),
+    ("🧠", "MEM"),
+    ("🧲", "TORRENT"),
+    ("⬇", "DL"),
+    ("⏳", "WORK"),
+];
+
+/// Wider separators, high contrast colors, text instead of emoji and larger click targets
+#[derive(Clone, Copy)]
+pub struct Accessibility {
+    /// Space between blocks in pixels
+    pub separator_width: u32,
+    /// Minimum width of every block in pixels
+    pub min_width: u32,
+    /// Replace emoji with text labels
+    pub text_labels: bool,
+}
+
+impl Accessibility {
+    /// Palette for bars with a dark background
+    pub const DARK: Palette = Palette {
+        critical: Color::rgb(0xff, 0xff, 0x00),
+        good: Color::rgb(0x00, 0xff, 0xff),
+        markers: true,
+    };
+    /// Palette for bars with a light background
+    pub const LIGHT: Palette = Palette {
+        critical: Color::rgb(0x80, 0x00, 0x00),
+        good: Color::rgb(0x00, 0x00, 0x80),
+        markers: true,
+    };
+
+    /// Applies the profile to all blocks from now on and switches to the high contrast palettes
+    pub fn enable(self) {
+        *CURRENT.write().unwrap() = Some(self);
+        Palette::configure(Self::DARK, Self::LIGHT);
+    }
+
+    /// Returns the profile in use, if any
+    #[must_use]
+    pub fn current() -> Option<Self> {
+        *CURRENT.read().unwrap()
+    }
+
+    /// Adapts a rendered block to the profile
+    pub fn apply(self, block: &mut I3Block) {
+        block.separator_block_width = Some(
+            block
+                .separator_block_width
+                .unwrap_or_default()
+                .max(self.separator_width),
+        );
+        if block.min_width.is_none() {
+            block.min_width = Some(MinWidth::Pixels(self.min_width));
+            block.align = Some(Align::Center);
+        }
+        if self.text_labels {
+            block.full_text = text_labels(&block.full_text);
+            if let Some(short_text) = &mut block.short_text {
+                *short_text = text_labels(short_text);
+            }
+        }
+    }
+}
+
+impl Default for Accessibility {
+    fn default() -> Self {
+        Self {
+            separator_width: 25,
+            min_width: 60,
+            text_labels: true,
+        }
+    }
+}
+
+/// Replaces all known emoji with their labels, separated from the following text by a space
+fn text_labels(text: &str) -> String {
+    // Some emoji come with a variation selector
+    let mut text = text.replace('\u{fe0f}', "");
+    for (emoji, label) in LABELS {
+        while let Some(index) = text.find(emoji) {
+            let end = index + emoji.len();
+            let rest = &text[end..];
+            let space = if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+                ""
+            } else {
+                " "
+            };
+            text.replace_range(index..end, &format!("{label}{space}"));
+        }
+    }
+    text
+}
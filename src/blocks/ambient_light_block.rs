@@ -0,0 +1,189 @@
+//! Shows the illuminance of the ambient light sensor and optionally follows it with the backlight
+
+use super::diagnostics_block::report;
+use super::{Block, I3Block, I3Event};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Backlight changes smaller than this many percent are skipped to avoid flickering
+const MIN_STEP: u8 = 5;
+
+pub struct AmbientLightBlock {
+    /// Backlight percentage by illuminance in lux, ordered by illuminance
+    curve: Vec<(f64, u8)>,
+    /// Whether the backlight follows the sensor
+    auto_brightness: AtomicBool,
+    /// Backlight percentage that was last set
+    last_brightness: Mutex<Option<u8>>,
+}
+
+impl Block for AmbientLightBlock {
+    fn render(&self) -> Option<I3Block> {
+        let lux = read_illuminance()?;
+        let auto_brightness = self.auto_brightness.load(Ordering::Relaxed);
+        if auto_brightness {
+            self.follow(lux);
+        }
+
+        Some(I3Block {
+            full_text: format!("☀{lux:.0}lx{}", if auto_brightness { " A" } else { "" }),
+            tooltip: Some(if auto_brightness {
+                "Brightness follows the ambient light".to_owned()
+            } else {
+                "Click to let the brightness follow the ambient light".to_owned()
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button == 1 {
+            self.auto_brightness.fetch_xor(true, Ordering::Relaxed);
+            *self.last_brightness.lock().unwrap() = None;
+        }
+    }
+}
+
+impl AmbientLightBlock {
+    /// Creates the block. When auto brightness is enabled by clicking, the backlight is set
+    /// according to `curve`, which maps illuminance in lux to brightness in percent and is
+    /// interpolated linearly.
+    #[must_use]
+    pub fn new(mut curve: Vec<(f64, u8)>) -> Self {
+        curve.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            curve,
+            auto_brightness: AtomicBool::new(false),
+            last_brightness: Mutex::new(None),
+        }
+    }
+
+    /// Sets the backlight to follow the illuminance
+    fn follow(&self, lux: f64) {
+        let Some(brightness) = interpolate(&self.curve, lux) else {
+            return;
+        };
+        let mut last = self.last_brightness.lock().unwrap();
+        if last.is_some_and(|last| last.abs_diff(brightness) < MIN_STEP) {
+            return;
+        }
+        *last = Some(brightness);
+        std::thread::spawn(move || {
+            if let Err(e) = set_brightness(brightness) {
+                report(format!("Failed to set the backlight: {e}"));
+            }
+        });
+    }
+}
+
+impl Default for AmbientLightBlock {
+    fn default() -> Self {
+        Self::new(vec![(0.0, 10), (50.0, 30), (300.0, 60), (1000.0, 100)])
+    }
+}
+
+/// Reads the illuminance of the first ambient light sensor in lux
+fn read_illuminance() -> Option<f64> {
+    let read = |path: &Path| {
+        std::fs::read_to_string(path)
+            .ok()?
+            .trim()
+            .parse::<f64>()
+            .ok()
+    };
+    for device in std::fs::read_dir("/sys/bus/iio/devices").ok()?.flatten() {
+        let path = device.path();
+        // Some drivers report lux directly, others a raw value that needs to be scaled
+        if let Some(lux) = read(&path.join("in_illuminance_input")) {
+            return Some(lux);
+        }
+        if let Some(raw) = read(&path.join("in_illuminance_raw")) {
+            let offset = read(&path.join("in_illuminance_offset")).unwrap_or(0.0);
+            let scale = read(&path.join("in_illuminance_scale")).unwrap_or(1.0);
+            return Some((raw + offset) * scale);
+        }
+    }
+    None
+}
+
+/// Finds the brightness in percent for the illuminance
+fn interpolate(curve: &[(f64, u8)], lux: f64) -> Option<u8> {
+    let first = curve.first()?;
+    if lux <= first.0 {
+        return Some(first.1);
+    }
+    for pair in curve.windows(2) {
+        let ((lux_a, percent_a), (lux_b, percent_b)) = (pair[0], pair[1]);
+        // Steps in the curve have no width to interpolate over
+        if lux <= lux_b && lux_b > lux_a {
+            let percent_a = f64::from(percent_a);
+            let percent_b = f64::from(percent_b);
+            let percent = percent_a + (percent_b - percent_a) * (lux - lux_a) / (lux_b - lux_a);
+            #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Between 0 and 100
+            let percent = percent.round().clamp(0.0, 100.0) as u8;
+            return Some(percent);
+        }
+    }
+    curve.last().map(|last| last.1)
+}
+
+/// Finds the first backlight device
+fn backlight() -> Option<PathBuf> {
+    std::fs::read_dir("/sys/class/backlight")
+        .ok()?
+        .flatten()
+        .map(|device| device.path())
+        .min()
+}
+
+/// Sets the backlight through logind, which doesn't require root
+fn set_brightness(percent: u8) -> zbus::Result<()> {
+    let backlight = backlight().ok_or_else(|| zbus::Error::Failure("No backlight".to_owned()))?;
+    let name = backlight
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    let max = std::fs::read_to_string(backlight.join("max_brightness"))
+        .ok()
+        .and_then(|max| max.trim().parse::<u32>().ok())
+        .ok_or_else(|| zbus::Error::Failure("Unknown maximum brightness".to_owned()))?;
+
+    let dbus_conn = zbus::blocking::Connection::system()?;
+    let proxy = zbus::blocking::Proxy::new(
+        &dbus_conn,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1/session/auto",
+        "org.freedesktop.login1.Session",
+    )?;
+    proxy.call::<_, _, ()>(
+        "SetBrightness",
+        &("backlight", name, max * u32::from(percent) / 100),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolate_between_points() {
+        let curve = [(0.0, 10), (100.0, 50), (1000.0, 100)];
+        assert_eq!(interpolate(&curve, -5.0), Some(10));
+        assert_eq!(interpolate(&curve, 50.0), Some(30));
+        assert_eq!(interpolate(&curve, 100.0), Some(50));
+        assert_eq!(interpolate(&curve, 5000.0), Some(100));
+        assert_eq!(interpolate(&[], 50.0), None);
+    }
+
+    #[test]
+    fn interpolate_steps() {
+        let curve = [(0.0, 10), (100.0, 20), (100.0, 80), (200.0, 100)];
+        assert_eq!(interpolate(&curve, 50.0), Some(15));
+        assert_eq!(interpolate(&curve, 100.0), Some(20));
+        assert_eq!(interpolate(&curve, 150.0), Some(90));
+        let curve = [(100.0, 20), (100.0, 80)];
+        assert_eq!(interpolate(&curve, 100.0), Some(20));
+        assert_eq!(interpolate(&curve, 150.0), Some(80));
+    }
+}
@@ -1,79 +1,131 @@
-use super::bluetooth_battery;
+use super::battery_history::BatteryHistory;
+use super::bluetooth_battery::BluetoothBattery;
+use super::color::{Color, Palette};
+use super::diagnostics_block::report;
 use super::hidpp::{BatteryStatus, Hidpp};
-use super::{Block, I3Block, I3Event};
-use std::sync::{mpsc::Sender, RwLock};
-use std::time::Instant;
+use super::notify::{Notification, Urgency};
+use super::threshold::ThresholdNotifier;
+use super::upower::UPower;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Below which percentage to offer suspending
+const CRITICAL_PERCENT: u8 = 5;
+
+/// Where the laptop batteries are read from
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BatteryBackend {
+    /// Read sysfs on every render
+    Sysfs,
+    /// Follow the changes reported by `UPower`, falling back to sysfs when it's not running
+    UPower,
+}
+
+/// How multiple laptop batteries are displayed
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BatteryAggregation {
+    /// Every battery on its own, ordered by name
+    Separate,
+    /// A single percentage weighted by capacity, together with the combined power
+    Combined,
+}
 
 pub struct BatteryBlock {
-    bluetooth: Option<bluetooth_battery::BluetoothBattery>,
+    aggregation: BatteryAggregation,
+    upower: Option<UPower>,
+    bluetooth: Option<Arc<BluetoothBattery>>,
     hidpp: Option<Hidpp>,
     last_bluetooth_poll: RwLock<Instant>,
     last_hidpp_recv_poll: RwLock<Instant>,
     last_hidpp_dev_poll: RwLock<Instant>,
+    /// Percentages of the laptop batteries over time
+    history: RwLock<BatteryHistory>,
+    /// Whether suspending was already offered since the battery became critical
+    critical_warned: AtomicBool,
+    /// Warns about low percentages
+    warnings: ThresholdNotifier,
 }
 
 impl Block for BatteryBlock {
     #[allow(clippy::too_many_lines)]
     fn render(&self) -> Option<I3Block> {
         // Find power supply batteries
+        let mut tooltip = None;
         let power_batteries = {
-            if let Ok(dir) = std::fs::read_dir("/sys/class/power_supply") {
-                let mut batteries = vec![];
-                let mut charging = false;
-
-                for supply in dir.flatten() {
-                    if supply
-                        .file_name()
-                        .into_string()
-                        .map(|x| x.starts_with("BAT"))
-                        .unwrap_or(false)
-                    {
-                        let mut path = supply.path();
-                        path.push("capacity");
-                        if let Ok(contents) = std::fs::read_to_string(path) {
-                            let contents = contents.trim();
-                            if let Ok(percent) = contents.parse::<u8>() {
-                                batteries.push(percent);
-                            }
-                        } else {
-                            continue;
-                        }
-                    } else if supply
-                        .file_name()
-                        .into_string()
-                        .map(|x| x.starts_with("AC"))
-                        .unwrap_or(false)
-                    {
-                        let mut path = supply.path();
-                        path.push("online");
-                        if let Ok(contents) = std::fs::read_to_string(path) {
-                            let contents = contents.trim();
-                            if contents == "1" {
-                                charging = true;
-                            }
-                        } else {
-                            continue;
-                        }
-                    }
+            let (batteries, charging) = if let Some(upower) = &self.upower {
+                upower_batteries(upower)
+            } else {
+                read_power_batteries()
+            };
+
+            // Keep track of the combined percentage
+            let combined = combine(&batteries);
+            if let Some(combined) = &combined {
+                let mut history = self.history.write().unwrap();
+                history.record(combined.percent, charging);
+                tooltip = history.tooltip();
+
+                if charging {
+                    self.warnings.reset();
+                } else {
+                    self.warnings.update(
+                        f64::from(combined.percent),
+                        &format!("{}%", combined.percent),
+                    );
+                }
+                if charging || combined.percent > CRITICAL_PERCENT {
+                    self.critical_warned.store(false, Ordering::Relaxed);
+                } else if !self.critical_warned.swap(true, Ordering::Relaxed) {
+                    offer_suspend(combined.percent);
                 }
+            }
 
-                // Calculate the resulting string
-                let ret = batteries
-                    .iter()
-                    .map(|bat| {
-                        if charging {
-                            format!(" 🔋<span foreground='#02ff02'>{bat}%</span>")
-                        } else if bat <= &15u8 {
-                            format!(" 🪫<span foreground='#ff0202'>{bat}%</span>")
-                        } else {
-                            format!(" 🔋{bat}%")
+            let shown = match self.aggregation {
+                BatteryAggregation::Separate => batteries,
+                BatteryAggregation::Combined => combined.into_iter().collect(),
+            };
+
+            // Calculate the resulting string
+            let ret = shown
+                .iter()
+                .map(|bat| {
+                    let percent = bat.percent;
+                    let power = match (self.aggregation, bat.power) {
+                        (BatteryAggregation::Combined, Some(power)) if power > 0.0 => {
+                            format!(" {power:.1}W")
                         }
-                    })
-                    .collect::<String>();
-                ret.trim().to_owned()
-            } else {
-                String::new()
-            }
+                        _ => String::new(),
+                    };
+                    let time_left = bat.time_left.map_or_else(String::new, |time_left| {
+                        let minutes = time_left.as_secs() / 60;
+                        format!(" {}:{:02}", minutes / 60, minutes % 60)
+                    });
+                    let power = format!("{power}{time_left}");
+                    if charging {
+                        // The color is the only difference otherwise
+                        let icon = if Palette::current().markers {
+                            "⚡"
+                        } else {
+                            "🔋"
+                        };
+                        format!(
+                            " {icon}<span foreground='{}'>{percent}%</span>{power}",
+                            Color::good()
+                        )
+                    } else if percent <= 15 {
+                        format!(
+                            " 🪫<span foreground='{}'>{percent}%</span>{power}",
+                            Color::critical()
+                        )
+                    } else {
+                        format!(" 🔋{percent}%{power}")
+                    }
+                })
+                .collect::<String>();
+            ret.trim().to_owned()
         };
 
         // Render bluetooth devices
@@ -128,8 +180,9 @@ impl Block for BatteryBlock {
                     BatteryStatus::Discharging | BatteryStatus::Full => {
                         if dev.charge <= 20 {
                             devices.push(format!(
-                                "{}<span foreground='#ff0202'>{}%</span>",
+                                "{}<span foreground='{}'>{}%</span>",
                                 dev.kind.emoji(),
+                                Color::critical(),
                                 dev.charge
                             ));
                         } else {
@@ -139,14 +192,16 @@ impl Block for BatteryBlock {
                     BatteryStatus::Recharging
                     | BatteryStatus::AlmostFull
                     | BatteryStatus::SlowRecharge => devices.push(format!(
-                        "{}<span foreground='#02ff02'>{}%</span>",
+                        "{}<span foreground='{}'>{}%</span>",
                         dev.kind.emoji(),
+                        Color::good(),
                         dev.charge
                     )),
                     BatteryStatus::InvalidBattery | BatteryStatus::ThermalError => {
                         devices.push(format!(
-                            "{}<span foreground='#ff0202'>(!) {}%</span>",
+                            "{}<span foreground='{}'>(!) {}%</span>",
                             dev.kind.emoji(),
+                            Color::critical(),
                             dev.charge
                         ));
                     }
@@ -191,21 +246,200 @@ impl Block for BatteryBlock {
         Some(I3Block {
             full_text: format!("{power_batteries}{bluetooth}{hidpp}"),
             markup: Some(super::Markup::Pango),
+            tooltip,
             ..Default::default()
         })
     }
 
     fn click(&self, _: &I3Event) {}
+
+    fn resume(&self) {
+        // Devices may have been connected, disconnected or charged in the meantime
+        if let Some(bluetooth) = &self.bluetooth {
+            bluetooth.update();
+            *self.last_bluetooth_poll.write().unwrap() = Instant::now();
+        }
+        if let Some(hidpp) = &self.hidpp {
+            let hidpp = hidpp.clone();
+            std::thread::spawn(move || hidpp.enumerate_receivers(true));
+            *self.last_hidpp_recv_poll.write().unwrap() = Instant::now();
+            *self.last_hidpp_dev_poll.write().unwrap() = Instant::now();
+        }
+    }
 }
 
 impl BatteryBlock {
-    pub fn new(timer_cancel: &Sender<()>) -> Self {
+    /// Creates the block. The battery history is persisted to `history_file` if given.
+    /// `warnings` are sent once per discharge when the battery drops to their percentages.
+    #[must_use]
+    pub fn new(
+        ctx: &BlockContext,
+        history_file: Option<PathBuf>,
+        backend: BatteryBackend,
+        aggregation: BatteryAggregation,
+        warnings: ThresholdNotifier,
+    ) -> Self {
         Self {
+            aggregation,
+            upower: match backend {
+                BatteryBackend::Sysfs => None,
+                BatteryBackend::UPower => UPower::new(ctx),
+            },
             hidpp: Hidpp::new(),
-            bluetooth: bluetooth_battery::BluetoothBattery::new(timer_cancel),
+            bluetooth: BluetoothBattery::shared(ctx),
             last_bluetooth_poll: RwLock::new(Instant::now()),
             last_hidpp_recv_poll: RwLock::new(Instant::now()),
             last_hidpp_dev_poll: RwLock::new(Instant::now()),
+            history: RwLock::new(BatteryHistory::new(history_file)),
+            critical_warned: AtomicBool::new(false),
+            // A wobbling percentage must not warn again before the next charge
+            warnings: warnings.until_reset(),
         }
     }
 }
+
+/// Asks whether to suspend the machine
+fn offer_suspend(percent: u8) {
+    Notification {
+        summary: "Battery critical".to_owned(),
+        body: format!("{percent}% left"),
+        urgency: Urgency::Critical,
+        actions: vec![
+            ("suspend".to_owned(), "Suspend".to_owned()),
+            ("ignore".to_owned(), "Ignore".to_owned()),
+        ],
+        ..Default::default()
+    }
+    .show_with_actions(|action| {
+        if action == Some("suspend") {
+            if let Err(e) = suspend() {
+                report(format!("Failed to suspend: {e}"));
+            }
+        }
+    });
+}
+
+/// Suspends the machine through logind
+fn suspend() -> zbus::Result<()> {
+    let dbus_conn = zbus::blocking::Connection::system()?;
+    let proxy = zbus::blocking::Proxy::new(
+        &dbus_conn,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+    // Interactive so polkit may ask for authorization
+    proxy.call::<_, _, ()>("Suspend", &(true,))
+}
+
+/// A battery of the machine itself
+struct PowerBattery {
+    name: String,
+    percent: u8,
+    /// Remaining energy or charge, whichever the battery reports
+    now: Option<f64>,
+    /// Energy or charge when full
+    full: Option<f64>,
+    /// Current power draw in W
+    power: Option<f64>,
+    /// Time until empty or full, if known
+    time_left: Option<Duration>,
+}
+
+/// Converts the batteries reported by `UPower`
+fn upower_batteries(upower: &UPower) -> (Vec<PowerBattery>, bool) {
+    let state = upower.state();
+    let batteries = state
+        .batteries
+        .into_iter()
+        .map(|bat| PowerBattery {
+            name: bat.name,
+            percent: bat.percent,
+            now: Some(bat.energy),
+            full: Some(bat.energy_full),
+            power: Some(bat.power),
+            time_left: bat.time_left,
+        })
+        .collect();
+    (batteries, state.charging)
+}
+
+/// Reads all batteries of the machine, ordered by name, and whether they are charging
+fn read_power_batteries() -> (Vec<PowerBattery>, bool) {
+    let mut batteries = vec![];
+    let mut charging = false;
+    let Ok(dir) = std::fs::read_dir("/sys/class/power_supply") else {
+        return (batteries, charging);
+    };
+
+    for supply in dir.flatten() {
+        let path = supply.path();
+        let read = |name| {
+            std::fs::read_to_string(path.join(name))
+                .map(|contents| contents.trim().to_owned())
+                .unwrap_or_default()
+        };
+        let read_num = |name| read(name).parse::<f64>().ok();
+        let Ok(name) = supply.file_name().into_string() else {
+            continue;
+        };
+        if name.starts_with("BAT") {
+            if let Ok(percent) = read("capacity").parse::<u8>() {
+                batteries.push(PowerBattery {
+                    name,
+                    percent,
+                    now: read_num("energy_now").or_else(|| read_num("charge_now")),
+                    full: read_num("energy_full").or_else(|| read_num("charge_full")),
+                    // Some batteries only report current and voltage
+                    power: read_num("power_now")
+                        .or_else(|| Some(read_num("current_now")? * read_num("voltage_now")? / 1e6))
+                        .map(|microwatts| microwatts / 1e6),
+                    time_left: None,
+                });
+            }
+            if read("status") == "Charging" {
+                charging = true;
+            }
+        } else if matches!(read("type").as_str(), "Mains" | "USB") && read("online") == "1" {
+            // Chargers are not always called AC, e.g. ADP1, USB-PD or docks
+            charging = true;
+        }
+    }
+
+    // read_dir() has no particular order
+    batteries.sort_by(|a, b| a.name.cmp(&b.name));
+    (batteries, charging)
+}
+
+/// Combines all batteries into one with the percentage weighted by their capacity
+fn combine(batteries: &[PowerBattery]) -> Option<PowerBattery> {
+    if batteries.is_empty() {
+        return None;
+    }
+    let now = batteries.iter().map(|bat| bat.now).sum::<Option<f64>>();
+    let full = batteries.iter().map(|bat| bat.full).sum::<Option<f64>>();
+    let percent = match (now, full) {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Between 0 and 100
+        (Some(now), Some(full)) if full > 0.0 => (now / full * 100.0).round().min(100.0) as u8,
+        // Without capacities, every battery counts the same
+        _ => {
+            let sum = batteries
+                .iter()
+                .map(|bat| usize::from(bat.percent))
+                .sum::<usize>();
+            u8::try_from(sum / batteries.len()).unwrap_or(u8::MAX)
+        }
+    };
+    Some(PowerBattery {
+        name: "combined".to_owned(),
+        percent,
+        now,
+        full,
+        power: batteries.iter().map(|bat| bat.power).sum::<Option<f64>>(),
+        // Batteries are usually drained and charged one after another
+        time_left: batteries
+            .iter()
+            .filter_map(|bat| bat.time_left)
+            .reduce(|a, b| a + b),
+    })
+}
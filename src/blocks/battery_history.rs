@@ -0,0 +1,174 @@
+//! Keeps track of the laptop battery percentage over time
+
+use super::diagnostics_block::report;
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// How often to record the battery percentage in seconds
+const SAMPLE_INTERVAL: u64 = 60;
+
+/// Samples further apart than this many seconds are not used for the discharge rate
+const MAX_GAP: i64 = 5 * 60;
+
+/// How long to keep samples in seconds
+const MAX_AGE: i64 = 24 * 60 * 60;
+
+/// How many characters the sparkline has
+const SPARKLINE_WIDTH: usize = 24;
+
+/// Characters used to draw the sparkline, from empty to full
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// A single recorded percentage
+struct Sample {
+    /// Unix timestamp of the sample
+    time: i64,
+    percent: u8,
+    charging: bool,
+}
+
+impl Sample {
+    /// Parses a line of the state file
+    fn parse(line: &str) -> Option<Self> {
+        let mut split = line.split(' ');
+        Some(Self {
+            time: split.next()?.parse().ok()?,
+            percent: split.next()?.parse().ok()?,
+            charging: split.next()? == "1",
+        })
+    }
+
+    /// Formats the sample as a line of the state file
+    fn to_line(&self) -> String {
+        format!(
+            "{} {} {}\n",
+            self.time,
+            self.percent,
+            u8::from(self.charging)
+        )
+    }
+}
+
+pub struct BatteryHistory {
+    samples: VecDeque<Sample>,
+    /// Where the samples are persisted to
+    state_file: Option<PathBuf>,
+    last_sample: Option<Instant>,
+}
+
+impl BatteryHistory {
+    /// Loads the history from the state file, if any
+    pub fn new(state_file: Option<PathBuf>) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        let samples = state_file
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|contents| {
+                contents
+                    .lines()
+                    .filter_map(Sample::parse)
+                    .filter(|sample| now - sample.time < MAX_AGE)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let ret = Self {
+            samples,
+            state_file,
+            last_sample: None,
+        };
+        // Drop old samples from the file
+        ret.rewrite();
+        ret
+    }
+
+    /// Records the current percentage, if it's time for a new sample
+    pub fn record(&mut self, percent: u8, charging: bool) {
+        if self
+            .last_sample
+            .is_some_and(|last| last.elapsed().as_secs() < SAMPLE_INTERVAL)
+        {
+            return;
+        }
+        self.last_sample = Some(Instant::now());
+
+        let sample = Sample {
+            time: chrono::Utc::now().timestamp(),
+            percent,
+            charging,
+        };
+        if let Some(path) = &self.state_file {
+            let written = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut f| f.write_all(sample.to_line().as_bytes()));
+            if let Err(e) = written {
+                report(format!("Failed to write battery history: {e}"));
+            }
+        }
+        self.samples.push_back(sample);
+        while self
+            .samples
+            .front()
+            .is_some_and(|first| self.samples.back().unwrap().time - first.time >= MAX_AGE)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Renders the sparkline and discharge rate
+    pub fn tooltip(&self) -> Option<String> {
+        if self.samples.len() < 2 {
+            return None;
+        }
+
+        // Use the latest sample of each bucket
+        let per_bucket = self.samples.len().div_ceil(SPARKLINE_WIDTH);
+        let sparkline = self
+            .samples
+            .iter()
+            .skip((self.samples.len() - 1) % per_bucket)
+            .step_by(per_bucket)
+            .map(|sample| SPARKS[usize::from(sample.percent.min(100)) * (SPARKS.len() - 1) / 100])
+            .collect::<String>();
+
+        let mut ret = format!("Battery history\n{sparkline}");
+
+        // Only count time spent discharging, ignoring gaps where the machine was off
+        let mut discharged = 0i64;
+        let mut duration = 0i64;
+        for (prev, next) in self.samples.iter().zip(self.samples.iter().skip(1)) {
+            // The clock may also have been set back
+            if prev.charging
+                || next.charging
+                || next.percent > prev.percent
+                || next.time <= prev.time
+                || next.time - prev.time > MAX_GAP
+            {
+                continue;
+            }
+            discharged += i64::from(prev.percent) - i64::from(next.percent);
+            duration += next.time - prev.time;
+        }
+        if duration > 0 {
+            #[allow(clippy::cast_precision_loss)] // Never that large
+            let rate = discharged as f64 / (duration as f64 / 3600.0);
+            let _idc = write!(ret, "\n−{rate:.1}%/h average");
+        }
+        Some(ret)
+    }
+
+    /// Writes all samples to the state file
+    fn rewrite(&self) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        let contents = self.samples.iter().map(Sample::to_line).collect::<String>();
+        if let Err(e) = std::fs::write(path, contents) {
+            report(format!("Failed to write battery history: {e}"));
+        }
+    }
+}
@@ -1,10 +1,14 @@
 //! Lists bluetooth devices with batteries and their state
 
+use super::BlockContext;
 use std::collections::HashMap;
-use std::sync::{mpsc::Sender, Arc, RwLock};
+use std::sync::{Arc, OnceLock, RwLock};
 use zbus::blocking::{Connection, Proxy};
 use zbus::zvariant;
 
+/// The instance shared by all blocks
+static SHARED: OnceLock<Option<Arc<BluetoothBattery>>> = OnceLock::new();
+
 pub struct BluetoothBattery {
     dbus_conn: Arc<Connection>,
     devices: Arc<RwLock<HashMap<zvariant::OwnedObjectPath, Device>>>,
@@ -17,7 +21,12 @@ struct Device {
 }
 
 impl BluetoothBattery {
-    pub fn new(timer_cancel: &Sender<()>) -> Option<Self> {
+    /// Returns the instance shared by all blocks, creating it if needed
+    pub fn shared(ctx: &BlockContext) -> Option<Arc<Self>> {
+        SHARED.get_or_init(|| Self::new(ctx).map(Arc::new)).clone()
+    }
+
+    fn new(ctx: &BlockContext) -> Option<Self> {
         // Connect
         let Ok(dbus_conn) = Connection::system() else {
             return None;
@@ -42,7 +51,7 @@ impl BluetoothBattery {
         let stream = object_manager.receive_signal("InterfacesAdded").ok()?;
         let conn = Arc::clone(&dbus_conn);
         let devs = Arc::clone(&devices);
-        let sender = timer_cancel.clone();
+        let ctx2 = ctx.clone();
         std::thread::spawn(move || {
             for item in stream {
                 // Deconstruct the body of the signal
@@ -80,14 +89,14 @@ impl BluetoothBattery {
                 // Insert the device
                 let dev = Device { percentage, icon };
                 devs.write().unwrap().insert(path.into_owned().into(), dev);
-                let _idc = sender.send(());
+                ctx2.request_update();
             }
         });
 
         // Handler for removed devices
         let stream = object_manager.receive_signal("InterfacesRemoved").ok()?;
         let devs = Arc::clone(&devices);
-        let sender = timer_cancel.clone();
+        let ctx2 = ctx.clone();
         std::thread::spawn(move || {
             for item in stream {
                 let body = item.body();
@@ -99,7 +108,7 @@ impl BluetoothBattery {
                     continue;
                 };
                 devs.write().unwrap().remove(&path.clone().into());
-                let _idc = sender.send(());
+                ctx2.request_update();
             }
         });
 
@@ -160,4 +169,15 @@ impl BluetoothBattery {
             .map(|dev| (dev.icon.clone(), dev.percentage))
             .collect()
     }
+
+    /// Returns the percentage of the device with the given address like `00_1B_66_AB_CD_EF`
+    pub fn percentage_of(&self, address: &str) -> Option<u8> {
+        let suffix = format!("/dev_{address}");
+        self.devices
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(path, _)| path.as_str().ends_with(&suffix))
+            .map(|(_, dev)| dev.percentage)
+    }
 }
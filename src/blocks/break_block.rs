@@ -0,0 +1,93 @@
+//! Shows how long the user has been active since the last break
+
+use super::color::Color;
+use super::diagnostics_block::report;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use zbus::blocking::{Connection, Proxy};
+
+/// How often to ask logind for the idle state in seconds
+const POLL_INTERVAL: u64 = 30;
+
+pub struct BreakBlock {
+    /// When the last break ended
+    active_since: Arc<RwLock<Instant>>,
+    /// How long to be active before the block is colored
+    max_active: Duration,
+}
+
+impl Block for BreakBlock {
+    fn render(&self) -> Option<I3Block> {
+        let active = self.active_since.read().unwrap().elapsed();
+        let mins = active.as_secs() / 60;
+        Some(I3Block {
+            full_text: format!("☕{}h{:02}", mins / 60, mins % 60),
+            color: if active >= self.max_active {
+                Some(Color::critical())
+            } else {
+                None
+            },
+            tooltip: Some(format!("{}h{:02} since last break", mins / 60, mins % 60)),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+
+    fn resume(&self) {
+        // Being suspended is a break as well
+        *self.active_since.write().unwrap() = Instant::now();
+    }
+}
+
+impl BreakBlock {
+    /// Creates the block. Being idle for `break_length` counts as a break.
+    #[must_use]
+    pub fn new(ctx: &BlockContext, max_active: Duration, break_length: Duration) -> Self {
+        let active_since = Arc::new(RwLock::new(Instant::now()));
+
+        let active_since2 = Arc::clone(&active_since);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = watch_idle(&active_since2, break_length, &ctx) {
+                report(format!("Failed to query idle state: {e}"));
+            }
+        });
+
+        Self {
+            active_since,
+            max_active,
+        }
+    }
+}
+
+/// Polls the idle hint of logind
+fn watch_idle(
+    active_since: &RwLock<Instant>,
+    break_length: Duration,
+    ctx: &BlockContext,
+) -> zbus::Result<()> {
+    let dbus_conn = Connection::system()?;
+    let proxy = Proxy::new(
+        &dbus_conn,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+
+    let mut idle_since = None;
+    loop {
+        let idle = proxy.get_property::<bool>("IdleHint")?;
+        if idle {
+            idle_since.get_or_insert_with(Instant::now);
+        } else if let Some(since) = idle_since.take() {
+            // Back from a break
+            if since.elapsed() >= break_length {
+                *active_since.write().unwrap() = Instant::now();
+                ctx.request_update();
+            }
+        }
+        std::thread::sleep(Duration::from_secs(POLL_INTERVAL));
+    }
+}
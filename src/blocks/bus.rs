@@ -0,0 +1,64 @@
+//! Lets blocks react to each other
+
+use std::mem::discriminant;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Something that happened in one block that others may be interested in
+#[derive(Debug, Clone, PartialEq)]
+pub enum BusEvent {
+    /// Applications that are recording from any source and whether the default source is muted.
+    /// Published by the volume block.
+    Recording { apps: Vec<String>, muted: bool },
+    /// Time tracked today, in whole minutes. Published by the timewarrior block.
+    TrackedToday(Duration),
+}
+
+/// Delivers events published by one block to all subscribed blocks
+#[derive(Default)]
+pub struct Bus {
+    subscribers: Mutex<Vec<Sender<BusEvent>>>,
+    /// Last published event of every kind
+    last: Mutex<Vec<BusEvent>>,
+}
+
+impl Bus {
+    /// Returns a receiver for the last event of every kind, followed by all events published
+    /// from now on, so late subscribers start with the current state
+    pub fn subscribe(&self) -> Receiver<BusEvent> {
+        let (send, receive) = mpsc::channel();
+        // Holding the lock keeps events from being published in between
+        let last = self.last.lock().unwrap();
+        for event in last.iter() {
+            let _idc = send.send(event.clone());
+        }
+        self.subscribers.lock().unwrap().push(send);
+        receive
+    }
+
+    /// Sends an event to all subscribers
+    pub fn publish(&self, event: BusEvent) {
+        // Events that don't change anything are dropped, so blocks reacting to each other can't
+        // keep each other busy forever
+        let mut last = self.last.lock().unwrap();
+        let previous = last
+            .iter()
+            .position(|previous| discriminant(previous) == discriminant(&event));
+        if previous.is_some_and(|index| last[index] == event) {
+            return;
+        }
+
+        // Forget subscribers that went away
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+
+        if let Some(index) = previous {
+            last[index] = event;
+        } else {
+            last.push(event);
+        }
+    }
+}
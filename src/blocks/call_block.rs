@@ -0,0 +1,87 @@
+//! Shows whether a voice call is active and whether the microphone is muted
+
+use super::bus::BusEvent;
+use super::color::Color;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::sync::{Arc, RwLock};
+
+/// Parts of the application names of known call applications, in lowercase.
+/// Discord and Chromium based apps record as "WEBRTC `VoiceEngine`".
+const CALL_APPS: [&str; 8] = [
+    "mumble", "discord", "webrtc", "teams", "zoom", "skype", "jitsi", "signal",
+];
+
+pub struct CallBlock {
+    call: Arc<RwLock<Option<Call>>>,
+}
+
+struct Call {
+    /// Call applications that are recording
+    apps: Vec<String>,
+    muted: bool,
+}
+
+impl Block for CallBlock {
+    fn render(&self) -> Option<I3Block> {
+        let call = self.call.read().unwrap();
+        let call = call.as_ref()?;
+        Some(if call.muted {
+            I3Block {
+                full_text: "📞 muted".to_owned(),
+                color: Some(Color::critical()),
+                tooltip: Some(call.apps.join("\n")),
+                ..Default::default()
+            }
+        } else {
+            I3Block {
+                full_text: "📞 on call".to_owned(),
+                color: Some(Color::good()),
+                tooltip: Some(call.apps.join("\n")),
+                ..Default::default()
+            }
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button == 1 {
+            std::thread::spawn(|| {
+                std::process::Command::new("pactl")
+                    .args(["set-source-mute", "@DEFAULT_SOURCE@", "toggle"])
+                    .status()
+            });
+        }
+    }
+}
+
+impl CallBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        let call = Arc::new(RwLock::new(None));
+
+        let call2 = Arc::clone(&call);
+        let events = ctx.subscribe();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            for event in events {
+                let BusEvent::Recording { apps, muted } = event else {
+                    continue;
+                };
+                let apps: Vec<_> = apps
+                    .into_iter()
+                    .filter(|app| {
+                        let app = app.to_lowercase();
+                        CALL_APPS.iter().any(|known| app.contains(known))
+                    })
+                    .collect();
+                *call2.write().unwrap() = if apps.is_empty() {
+                    None
+                } else {
+                    Some(Call { apps, muted })
+                };
+                ctx.request_update();
+            }
+        });
+
+        Self { call }
+    }
+}
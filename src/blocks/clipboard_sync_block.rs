@@ -0,0 +1,144 @@
+//! Shows whether the Wayland primary selection is synced to the clipboard and toggles the syncing
+//!
+//! Syncs started by the block are stopped along with everything they started. Syncs started
+//! elsewhere are only found when their command line is exactly the configured command, which is
+//! the case when `sh` executes it directly but not for `sh -c` itself or other wrappers.
+
+use super::color::Color;
+use super::diagnostics_block::report;
+use super::{unblock_signals, Block, BlockContext, I3Block, I3Event};
+use std::os::unix::process::CommandExt as _;
+use std::process::Command;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// How often to look for the sync process in seconds
+const POLL_INTERVAL: u64 = 10;
+
+pub struct ClipboardSyncBlock {
+    /// Command that keeps the selections in sync, run through `sh -c`
+    command: String,
+    /// Process group of the sync started by the block, while it's running
+    spawned: Arc<Mutex<Option<libc::pid_t>>>,
+    /// PIDs of running sync processes started elsewhere
+    pids: Arc<RwLock<Vec<libc::pid_t>>>,
+    /// Looks for the processes again right away
+    poll_channel: Mutex<Sender<()>>,
+}
+
+impl Block for ClipboardSyncBlock {
+    fn render(&self) -> Option<I3Block> {
+        // Only Wayland has this problem
+        std::env::var_os("WAYLAND_DISPLAY")?;
+
+        let running =
+            self.spawned.lock().unwrap().is_some() || !self.pids.read().unwrap().is_empty();
+        Some(I3Block {
+            full_text: if running { "📋sync" } else { "📋split" }.to_owned(),
+            color: if running { Some(Color::good()) } else { None },
+            tooltip: Some(if running {
+                "The primary selection is copied to the clipboard".to_owned()
+            } else {
+                "The primary selection and the clipboard are separate".to_owned()
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button != 1 {
+            return;
+        }
+        let poll = self.poll_channel.lock().unwrap().clone();
+        let mut spawned = self.spawned.lock().unwrap();
+        let pids = self.pids.read().unwrap().clone();
+        if spawned.is_none() && pids.is_empty() {
+            // In its own group so the shell and everything it started can be stopped together
+            match unblock_signals(
+                Command::new("sh")
+                    .arg("-c")
+                    .arg(&self.command)
+                    .process_group(0),
+            )
+            .spawn()
+            {
+                Ok(mut child) => {
+                    *spawned = libc::pid_t::try_from(child.id()).ok();
+                    // Reap the process when it exits
+                    let spawned = Arc::clone(&self.spawned);
+                    let poll = poll.clone();
+                    std::thread::spawn(move || {
+                        let _idc = child.wait();
+                        *spawned.lock().unwrap() = None;
+                        let _idc = poll.send(());
+                    });
+                }
+                Err(e) => report(format!("Failed to start `{}`: {e}", self.command)),
+            }
+        } else {
+            // The group ID is the PID of its leader
+            let targets = spawned.iter().map(|group| -group).chain(pids);
+            for pid in targets {
+                // SAFETY: kill() has no memory safety preconditions
+                unsafe {
+                    libc::kill(pid, libc::SIGTERM);
+                }
+            }
+        }
+        let _idc = poll.send(());
+    }
+}
+
+impl ClipboardSyncBlock {
+    /// Creates the block. `command` is started to sync the selections, and looked for in the
+    /// running processes in case it was started elsewhere.
+    #[must_use]
+    pub fn new(ctx: &BlockContext, command: &str) -> Self {
+        let pids = Arc::new(RwLock::new(vec![]));
+        let (send, receive) = mpsc::channel::<()>();
+
+        let pids2 = Arc::clone(&pids);
+        let ctx = ctx.clone();
+        let command2 = command.to_owned();
+        std::thread::spawn(move || loop {
+            let current = find_processes(&command2);
+            if *pids2.read().unwrap() != current {
+                *pids2.write().unwrap() = current;
+                ctx.request_update();
+            }
+
+            match receive.recv_timeout(Duration::from_secs(POLL_INTERVAL)) {
+                Ok(()) => {
+                    // Give the process a moment to start or exit
+                    std::thread::sleep(Duration::from_millis(200));
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        });
+
+        Self {
+            command: command.to_owned(),
+            spawned: Arc::new(Mutex::new(None)),
+            pids,
+            poll_channel: Mutex::new(send),
+        }
+    }
+}
+
+/// Finds the processes whose command line is exactly the command
+fn find_processes(command: &str) -> Vec<libc::pid_t> {
+    let Ok(dir) = std::fs::read_dir("/proc") else {
+        return vec![];
+    };
+    dir.flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse::<libc::pid_t>().ok()?;
+            // Arguments are separated by NUL bytes
+            let cmdline = std::fs::read(entry.path().join("cmdline")).ok()?;
+            let cmdline = String::from_utf8_lossy(&cmdline).replace('\0', " ");
+            (cmdline.trim_end() == command).then_some(pid)
+        })
+        .collect()
+}
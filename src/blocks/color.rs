@@ -0,0 +1,142 @@
+//! Colors of blocks
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+/// Whether the light palette is used instead of the dark one
+static LIGHT: AtomicBool = AtomicBool::new(false);
+
+/// The dark and the light palette
+static PALETTES: RwLock<[Palette; 2]> = RwLock::new([Palette::DARK, Palette::LIGHT]);
+
+/// A color with optional transparency
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+    /// 255 is opaque
+    pub alpha: u8,
+}
+
+/// A color string that could not be parsed
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid color {0:?}, expected #rgb, #rrggbb or #rrggbbaa")]
+pub struct InvalidColor(String);
+
+/// The colors blocks use, for a dark or a light bar
+#[derive(Clone, Copy)]
+pub struct Palette {
+    /// Something needs attention
+    pub critical: Color,
+    /// Something is active or fine
+    pub good: Color,
+    /// Also mark colored blocks with a symbol and an underline, so they can be told apart without
+    /// seeing the colors
+    pub markers: bool,
+}
+
+impl Palette {
+    /// For bars with a dark background
+    pub const DARK: Self = Self {
+        critical: Color::rgb(0xff, 0x02, 0x02),
+        good: Color::rgb(0x02, 0xff, 0x02),
+        markers: false,
+    };
+    /// For bars with a light background
+    pub const LIGHT: Self = Self {
+        critical: Color::rgb(0xc0, 0x00, 0x00),
+        good: Color::rgb(0x00, 0x80, 0x00),
+        markers: false,
+    };
+
+    /// Returns the palette that is currently used
+    #[must_use]
+    pub fn current() -> Self {
+        PALETTES.read().unwrap()[usize::from(LIGHT.load(Ordering::Relaxed))]
+    }
+
+    /// Replaces the dark and the light palette
+    pub fn configure(dark: Self, light: Self) {
+        *PALETTES.write().unwrap() = [dark, light];
+    }
+
+    /// Switches to the light or dark palette and returns whether anything changed
+    pub fn set_light(light: bool) -> bool {
+        LIGHT.swap(light, Ordering::Relaxed) != light
+    }
+}
+
+impl Color {
+    /// Something needs attention
+    #[must_use]
+    pub fn critical() -> Self {
+        Palette::current().critical
+    }
+
+    /// Something is active or fine
+    #[must_use]
+    pub fn good() -> Self {
+        Palette::current().good
+    }
+
+    /// Creates an opaque color
+    #[must_use]
+    pub const fn rgb(red: u8, green: u8, blue: u8) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            alpha: 0xff,
+        }
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)?;
+        // Not every consumer understands alpha, so only add it when needed
+        if self.alpha != 0xff {
+            write!(f, "{:02x}", self.alpha)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Color {
+    type Err = InvalidColor;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidColor(s.to_owned());
+        let hex = s.strip_prefix('#').ok_or_else(invalid)?;
+        if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let channel = |i: usize, len: usize| {
+            let value = u8::from_str_radix(&hex[i * len..(i + 1) * len], 16).unwrap_or_default();
+            // #rgb is short for #rrggbb
+            if len == 1 {
+                value * 0x11
+            } else {
+                value
+            }
+        };
+        match hex.len() {
+            3 => Ok(Self::rgb(channel(0, 1), channel(1, 1), channel(2, 1))),
+            6 => Ok(Self::rgb(channel(0, 2), channel(1, 2), channel(2, 2))),
+            8 => Ok(Self {
+                alpha: channel(3, 2),
+                ..Self::rgb(channel(0, 2), channel(1, 2), channel(2, 2))
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl serde::Serialize for Color {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
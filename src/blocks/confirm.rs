@@ -0,0 +1,57 @@
+//! Asks for a second click before doing something destructive
+
+use super::BlockContext;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long the second click may take in seconds
+const CONFIRM_WINDOW: u64 = 2;
+
+/// Confirms an action by clicking twice within [`CONFIRM_WINDOW`]
+pub struct Confirmation {
+    /// When the first click happened
+    armed: Mutex<Option<Instant>>,
+    ctx: BlockContext,
+}
+
+impl Confirmation {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        Self {
+            armed: Mutex::new(None),
+            ctx: ctx.clone(),
+        }
+    }
+
+    /// Returns whether this click confirms the action. Otherwise it's the first click, and the
+    /// block is redrawn so it can show that a second click is awaited.
+    #[track_caller]
+    pub fn confirm(&self) -> bool {
+        let mut armed = self.armed.lock().unwrap();
+        if armed
+            .take()
+            .is_some_and(|since| since.elapsed() < Duration::from_secs(CONFIRM_WINDOW))
+        {
+            return true;
+        }
+        *armed = Some(Instant::now());
+        self.ctx.request_update();
+
+        // Redraw again once the hint is gone
+        let ctx = self.ctx.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_secs(CONFIRM_WINDOW));
+            ctx.request_update();
+        });
+        false
+    }
+
+    /// Whether a second click is awaited
+    #[must_use]
+    pub fn pending(&self) -> bool {
+        self.armed
+            .lock()
+            .unwrap()
+            .is_some_and(|since| since.elapsed() < Duration::from_secs(CONFIRM_WINDOW))
+    }
+}
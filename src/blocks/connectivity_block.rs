@@ -0,0 +1,118 @@
+//! Shows when the machine is offline or behind a captive portal and opens the portal on click
+
+use super::color::Color;
+use super::diagnostics_block::report;
+use super::http;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::process::Command;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// How often to check the connectivity in seconds
+const POLL_INTERVAL: u64 = 60;
+
+#[derive(Clone, PartialEq, Eq)]
+enum Connectivity {
+    Online,
+    /// Requests are redirected to the login page of the portal
+    Portal(String),
+    Offline,
+}
+
+pub struct ConnectivityBlock {
+    /// Result of the last check, unknown until the first one finished
+    state: Arc<RwLock<Option<Connectivity>>>,
+    /// Checks again right away
+    check_channel: Mutex<Sender<()>>,
+}
+
+impl Block for ConnectivityBlock {
+    fn render(&self) -> Option<I3Block> {
+        let (full_text, tooltip) = match self.state.read().unwrap().as_ref()? {
+            Connectivity::Online => return None,
+            Connectivity::Portal(_) => ("🌐portal", "Behind a captive portal, click to log in"),
+            Connectivity::Offline => ("🌐offline", "Not connected to the internet"),
+        };
+        Some(I3Block {
+            full_text: full_text.to_owned(),
+            color: Some(Color::critical()),
+            tooltip: Some(tooltip.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button != 1 {
+            return;
+        }
+        if let Some(Connectivity::Portal(url)) = &*self.state.read().unwrap() {
+            match Command::new("xdg-open").arg(url).spawn() {
+                // Reap the process when it exits
+                Ok(mut child) => {
+                    std::thread::spawn(move || child.wait());
+                }
+                Err(e) => report(format!("Failed to open the captive portal: {e}")),
+            }
+        }
+        let _idc = self.check_channel.lock().unwrap().send(());
+    }
+
+    fn resume(&self) {
+        // Probably on a different network now
+        let _idc = self.check_channel.lock().unwrap().send(());
+    }
+}
+
+impl ConnectivityBlock {
+    /// Creates the block checking `url`, which has to answer with an empty response and status
+    /// 204 over plain HTTP, like `http://connectivitycheck.gstatic.com/generate_204`. Without
+    /// it, nothing is checked and the block stays hidden.
+    #[must_use]
+    pub fn new(ctx: &BlockContext, url: Option<&str>) -> Self {
+        let state = Arc::new(RwLock::new(None));
+        let (send, receive) = mpsc::channel::<()>();
+        let Some(url) = url.map(ToOwned::to_owned) else {
+            return Self {
+                state,
+                check_channel: Mutex::new(send),
+            };
+        };
+
+        let state2 = Arc::clone(&state);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || loop {
+            let current = Some(check(&url));
+            if *state2.read().unwrap() != current {
+                *state2.write().unwrap() = current;
+                ctx.request_update();
+            }
+
+            match receive.recv_timeout(Duration::from_secs(POLL_INTERVAL)) {
+                Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        });
+
+        Self {
+            state,
+            check_channel: Mutex::new(send),
+        }
+    }
+}
+
+/// Requests the check endpoint over plain HTTP, which portals intercept
+fn check(url: &str) -> Connectivity {
+    // Portals usually answer DNS, so no response at all is really offline
+    let Ok((status, location)) = http::status(url) else {
+        return Connectivity::Offline;
+    };
+    if status == 204 {
+        return Connectivity::Online;
+    }
+    // Portals either redirect to their login page or show it right away. The location comes from
+    // whoever intercepted the request, so nothing but web pages is opened.
+    let location = location
+        .filter(|location| location.starts_with("http://") || location.starts_with("https://"));
+    Connectivity::Portal(location.unwrap_or_else(|| url.to_owned()))
+}
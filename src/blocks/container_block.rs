@@ -0,0 +1,50 @@
+//! Shows when the bar is running inside a container
+
+use super::color::Color;
+use super::{Block, I3Block, I3Event};
+
+pub struct ContainerBlock {
+    /// Name of the container, if we are running in one
+    container: Option<String>,
+}
+
+impl Block for ContainerBlock {
+    fn render(&self) -> Option<I3Block> {
+        let container = self.container.as_ref()?;
+        Some(I3Block {
+            full_text: format!("📦{container}"),
+            color: Some(Color::critical()),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+}
+
+impl Default for ContainerBlock {
+    fn default() -> Self {
+        Self {
+            container: detect_container(),
+        }
+    }
+}
+
+/// Finds out whether we are in a container and what it's called
+fn detect_container() -> Option<String> {
+    // Podman, toolbox and distrobox
+    if let Ok(contents) = std::fs::read_to_string("/run/.containerenv") {
+        let name = contents.lines().find_map(|line| {
+            line.strip_prefix("name=")
+                .map(|name| name.trim_matches('"').to_owned())
+        });
+        return Some(name.unwrap_or_else(|| "podman".to_owned()));
+    }
+    // Docker
+    if std::path::Path::new("/.dockerenv").exists() {
+        return Some(std::env::var("HOSTNAME").unwrap_or_else(|_| "docker".to_owned()));
+    }
+    // systemd-nspawn and others following the container interface
+    std::env::var("container")
+        .ok()
+        .filter(|name| !name.is_empty())
+}
@@ -0,0 +1,121 @@
+//! Shows the CPU utilization since the last render
+
+use super::color::Color;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::sync::Mutex;
+
+/// Above this utilization in percent, the block is colored
+const HIGH_USAGE: u64 = 90;
+
+/// Characters used to draw the per-core usage, from idle to busy
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+pub struct CpuBlock {
+    state: Mutex<CpuState>,
+    ctx: BlockContext,
+}
+
+#[derive(Default)]
+struct CpuState {
+    /// Busy and total jiffies of the previous render, all CPUs first and then every core
+    last: Vec<(u64, u64)>,
+    /// Whether the usage of every core is shown
+    per_core: bool,
+}
+
+impl Block for CpuBlock {
+    fn render(&self) -> Option<I3Block> {
+        let counters = read_counters()?;
+        let mut state = self.state.lock().unwrap();
+
+        let usage = if state.last.len() == counters.len() {
+            counters
+                .iter()
+                .zip(&state.last)
+                .map(|((busy, total), (last_busy, last_total))| {
+                    (busy.saturating_sub(*last_busy) * 100)
+                        .checked_div(total.saturating_sub(*last_total))
+                        .unwrap_or(0)
+                        .min(100)
+                })
+                .collect::<Vec<_>>()
+        } else {
+            vec![0; counters.len()]
+        };
+        state.last = counters;
+
+        let (&total, cores) = usage.split_first()?;
+        let mut full_text = format!("⚙{total}%");
+        if state.per_core {
+            full_text.push(' ');
+            full_text.extend(
+                cores.iter().map(|&usage| {
+                    BARS[usize::try_from(usage).unwrap_or(0) * (BARS.len() - 1) / 100]
+                }),
+            );
+        }
+        let block = I3Block {
+            full_text,
+            color: if total >= HIGH_USAGE {
+                Some(Color::critical())
+            } else {
+                None
+            },
+            ..Default::default()
+        };
+        if state.per_core {
+            Some(block)
+        } else {
+            Some(block.fixed_width("⚙100%"))
+        }
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button == 1 {
+            let mut state = self.state.lock().unwrap();
+            state.per_core = !state.per_core;
+            self.ctx.request_update();
+        }
+    }
+
+    fn resume(&self) {
+        self.state.lock().unwrap().last.clear();
+    }
+}
+
+impl CpuBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        Self {
+            state: Mutex::default(),
+            ctx: ctx.clone(),
+        }
+    }
+}
+
+/// Reads the busy and total jiffies of all CPUs and of every core from `/proc/stat`
+fn read_counters() -> Option<Vec<(u64, u64)>> {
+    let contents = std::fs::read_to_string("/proc/stat").ok()?;
+    let counters = contents
+        .lines()
+        .filter(|line| line.starts_with("cpu"))
+        .map(|line| {
+            // user nice system idle iowait irq softirq steal, guest time is already part of user
+            let values = line
+                .split_whitespace()
+                .skip(1)
+                .take(8)
+                .map(|value| value.parse::<u64>().unwrap_or_default())
+                .collect::<Vec<_>>();
+            let total = values.iter().sum::<u64>();
+            let idle = values.get(3).copied().unwrap_or_default()
+                + values.get(4).copied().unwrap_or_default();
+            (total - idle, total)
+        })
+        .collect::<Vec<_>>();
+    if counters.is_empty() {
+        None
+    } else {
+        Some(counters)
+    }
+}
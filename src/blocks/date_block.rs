@@ -1,18 +1,438 @@
 use super::{Block, I3Block, I3Event};
-use chrono::Local;
+use chrono::{DateTime, Datelike as _, Days, Local, LocalResult, Months, NaiveDate};
+use chrono::{NaiveDateTime, NaiveTime, Offset as _, TimeDelta, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How often to look for upcoming clock changes in seconds
+const DST_POLL_INTERVAL: u64 = 60;
+
+/// How far ahead recurring holidays without an end are expanded
+const RECURRENCE_YEARS: i32 = 5;
 
 #[derive(Default)]
-pub struct DateBlock {}
+pub struct DateBlock {
+    /// Days that are marked as holidays
+    holidays: HashSet<NaiveDate>,
+    /// Render in this timezone instead of the system one
+    timezone: Option<Tz>,
+    /// Last description of an upcoming clock change and when it was looked for
+    dst: Mutex<Option<(Instant, Option<String>)>>,
+}
 
 impl Block for DateBlock {
     fn render(&self) -> Option<I3Block> {
-        let now = Local::now();
-        Some(I3Block {
-            full_text: now.format("(KW%V) %a, %d.%m. (%b) %H:%M").to_string(),
-            short_text: Some(now.format("%H:%M").to_string()),
-            ..Default::default()
+        Some(match self.timezone {
+            Some(timezone) => self.render_in(&timezone),
+            None => self.render_in(&Local),
         })
     }
 
     fn click(&self, _: &I3Event) {}
+
+    fn clock_changed(&self) {
+        // The next change is different in the new timezone
+        *self.dst.lock().unwrap() = None;
+    }
+}
+
+impl DateBlock {
+    /// Creates the block, marking the days of all events in the ICS file as holidays, see
+    /// [`parse_holidays`] for what is understood of recurring events. The time is
+    /// rendered in `timezone` if given, and in the system timezone otherwise.
+    #[must_use]
+    pub fn new(holidays_file: &Path, timezone: Option<Tz>) -> Self {
+        Self {
+            holidays: std::fs::read_to_string(holidays_file)
+                .map(|contents| parse_holidays(&contents, Local::now().date_naive()))
+                .unwrap_or_default(),
+            timezone,
+            dst: Mutex::new(None),
+        }
+    }
+
+    fn render_in<T: TimeZone>(&self, timezone: &T) -> I3Block
+    where
+        T::Offset: Display,
+    {
+        let now = Utc::now();
+        let local = now.with_timezone(timezone);
+        let holiday = if self.holidays.contains(&local.date_naive()) {
+            " 🎉"
+        } else {
+            ""
+        };
+        let time = local.format(time_format(timezone, &local.naive_local()));
+
+        I3Block {
+            full_text: format!("{}{time}{holiday}", local.format("(KW%V) %a, %d.%m. (%b) ")),
+            short_text: Some(time.to_string()),
+            tooltip: self.dst_change(timezone, now),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the cached description of an upcoming clock change, looking again if it's too old
+    fn dst_change<T: TimeZone>(&self, timezone: &T, now: DateTime<Utc>) -> Option<String>
+    where
+        T::Offset: Display,
+    {
+        let mut dst = self.dst.lock().unwrap();
+        if let Some((time, change)) = &*dst {
+            if time.elapsed() < Duration::from_secs(DST_POLL_INTERVAL) {
+                return change.clone();
+            }
+        }
+        let change = dst_change(timezone, now);
+        *dst = Some((Instant::now(), change.clone()));
+        change
+    }
+}
+
+/// The hour after the clocks went back happens twice, so tell both apart by their offset
+fn time_format<T: TimeZone>(timezone: &T, local: &NaiveDateTime) -> &'static str {
+    if matches!(
+        timezone.from_local_datetime(local),
+        LocalResult::Ambiguous(..)
+    ) {
+        "%H:%M (UTC%:z)"
+    } else {
+        "%H:%M"
+    }
+}
+
+/// Describes when the clocks change, if that happens within the day after `now`
+fn dst_change<T: TimeZone>(timezone: &T, now: DateTime<Utc>) -> Option<String>
+where
+    T::Offset: Display,
+{
+    let offset = |time: DateTime<Utc>| {
+        time.with_timezone(timezone)
+            .offset()
+            .fix()
+            .local_minus_utc()
+    };
+    let current = offset(now);
+    if offset(now + TimeDelta::days(1)) == current {
+        return None;
+    }
+
+    // Find the minute of the change
+    let (mut before, mut after) = (now, now + TimeDelta::days(1));
+    while after - before > TimeDelta::minutes(1) {
+        let middle = before + (after - before) / 2;
+        if offset(middle) == current {
+            before = middle;
+        } else {
+            after = middle;
+        }
+    }
+    let change = offset(after) - current;
+    Some(format!(
+        "Clocks change\n{} {} by {} min",
+        after.with_timezone(timezone).format("%a %H:%M"),
+        if change > 0 { "forward" } else { "back" },
+        change.abs() / 60
+    ))
+}
+
+/// Finds the days of all events in an ICS calendar. Events may span several days and repeat
+/// daily, weekly, monthly or yearly, with an interval, a count or an end. Rules with other parts,
+/// like the weekdays of `BYDAY`, only mark the first occurrence. Recurring events without an end
+/// are expanded up to [`RECURRENCE_YEARS`] after `today`.
+fn parse_holidays(ics: &str, today: NaiveDate) -> HashSet<NaiveDate> {
+    // Long lines are folded by starting the continuation with whitespace
+    let mut lines: Vec<String> = vec![];
+    for line in ics.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_owned()),
+        }
+    }
+
+    let limit = today
+        .with_year(today.year() + RECURRENCE_YEARS)
+        .unwrap_or(NaiveDate::MAX);
+    let mut holidays = HashSet::new();
+    let mut event: Option<Event> = None;
+    for line in &lines {
+        // Properties look like DTSTART;VALUE=DATE:20241225, the parameters don't matter
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let name = name.split(';').next().unwrap_or_default();
+        match (name, value, &mut event) {
+            ("BEGIN", "VEVENT", _) => event = Some(Event::default()),
+            ("END", "VEVENT", Some(current)) => {
+                holidays.extend(current.days(limit));
+                event = None;
+            }
+            ("DTSTART", _, Some(current)) => current.start = parse_date(value),
+            ("DTEND", _, Some(current)) => current.end = parse_date(value),
+            ("DURATION", _, Some(current)) => current.duration = parse_duration(value),
+            ("RRULE", _, Some(current)) => current.rule = Some(value.to_owned()),
+            _ => {}
+        }
+    }
+    holidays
+}
+
+/// The parts of a calendar event that decide which days it covers
+#[derive(Default)]
+struct Event {
+    start: Option<(NaiveDate, Option<NaiveTime>)>,
+    end: Option<(NaiveDate, Option<NaiveTime>)>,
+    /// Length in whole days
+    duration: Option<u64>,
+    rule: Option<String>,
+}
+
+impl Event {
+    /// Returns all days covered by any occurrence up to `limit`
+    fn days(&self, limit: NaiveDate) -> Vec<NaiveDate> {
+        let Some((start, _)) = self.start else {
+            return vec![];
+        };
+        // The end of all-day events is exclusive, so does the end at midnight
+        let length = match (self.end, self.duration) {
+            (Some((end, time)), _) => u64::try_from(
+                (end - start).num_days()
+                    + i64::from(time.is_some_and(|time| time != NaiveTime::MIN)),
+            )
+            .unwrap_or_default(),
+            (None, Some(days)) => days,
+            (None, None) => 1,
+        }
+        .max(1);
+        occurrences(start, self.rule.as_deref(), limit)
+            .into_iter()
+            .flat_map(|first| {
+                (0..length).filter_map(move |day| first.checked_add_days(Days::new(day)))
+            })
+            .collect()
+    }
+}
+
+/// Returns the start dates of the occurrences of a recurring event, or just `start` if the rule
+/// is missing or not understood
+fn occurrences(start: NaiveDate, rule: Option<&str>, limit: NaiveDate) -> Vec<NaiveDate> {
+    let Some(rule) = rule else {
+        return vec![start];
+    };
+    let mut freq = None;
+    let mut interval = 1;
+    let mut count = None;
+    let mut until = limit;
+    for part in rule.split(';') {
+        let (key, value) = part.split_once('=').unwrap_or((part, ""));
+        match key {
+            "FREQ" => freq = Some(value),
+            "INTERVAL" => interval = value.parse().unwrap_or(1).max(1),
+            "COUNT" => count = value.parse::<usize>().ok(),
+            "UNTIL" => {
+                until = value
+                    .get(..8)
+                    .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok())
+                    .map_or(limit, |date| date.min(limit));
+            }
+            "WKST" => {}
+            _ => return vec![start],
+        }
+    }
+
+    if !matches!(freq, Some("DAILY" | "WEEKLY" | "MONTHLY" | "YEARLY")) {
+        return vec![start];
+    }
+
+    // Occurrences on days that don't exist, like February 30th, are skipped and not counted. The
+    // first value is the first day of the period, to know when to stop.
+    let nth = |n: u32| -> (Option<NaiveDate>, Option<NaiveDate>) {
+        let steps = n.saturating_mul(interval);
+        match freq {
+            Some("DAILY") => {
+                let date = start.checked_add_days(Days::new(steps.into()));
+                (date, date)
+            }
+            Some("WEEKLY") => {
+                let date = start.checked_add_days(Days::new(u64::from(steps) * 7));
+                (date, date)
+            }
+            Some("MONTHLY") => {
+                let month = start
+                    .with_day(1)
+                    .and_then(|first| first.checked_add_months(Months::new(steps)));
+                (month, month.and_then(|month| month.with_day(start.day())))
+            }
+            Some("YEARLY") => {
+                let year = i32::try_from(steps)
+                    .ok()
+                    .and_then(|steps| start.year().checked_add(steps));
+                let base = year.and_then(|year| start.with_day(1)?.with_year(year));
+                (base, year.and_then(|year| start.with_year(year)))
+            }
+            _ => (None, None),
+        }
+    };
+
+    let mut dates = vec![];
+    for n in 0.. {
+        let (Some(base), date) = nth(n) else {
+            break;
+        };
+        if base > until || count.is_some_and(|count| dates.len() >= count) {
+            break;
+        }
+        dates.extend(date.filter(|date| *date <= until));
+    }
+    dates
+}
+
+/// Parses a value like `20241225` or `20241225T100000Z`
+fn parse_date(value: &str) -> Option<(NaiveDate, Option<NaiveTime>)> {
+    let date = NaiveDate::parse_from_str(value.get(..8)?, "%Y%m%d").ok()?;
+    let time = value
+        .get(9..15)
+        .filter(|_| value.as_bytes().get(8) == Some(&b'T'))
+        .and_then(|time| NaiveTime::parse_from_str(time, "%H%M%S").ok());
+    Some((date, time))
+}
+
+/// Parses the days of a duration like `P3D` or `P1W`, shorter ones cover their first day
+fn parse_duration(value: &str) -> Option<u64> {
+    let value = value.strip_prefix('P')?;
+    let value = value.split('T').next()?;
+    if let Some(weeks) = value.strip_suffix('W') {
+        return Some(weeks.parse::<u64>().ok()? * 7);
+    }
+    match value.strip_suffix('D') {
+        Some(days) => days.parse().ok(),
+        None => Some(1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Europe::Berlin;
+
+    fn utc(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    fn naive(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn date(text: &str) -> NaiveDate {
+        text.parse().unwrap()
+    }
+
+    fn holidays(events: &str) -> Vec<NaiveDate> {
+        let ics = format!("BEGIN:VCALENDAR\r\n{events}END:VCALENDAR\r\n");
+        let mut days: Vec<_> = parse_holidays(&ics, date("2024-06-01"))
+            .into_iter()
+            .collect();
+        days.sort();
+        days
+    }
+
+    #[test]
+    fn holidays_span_days() {
+        let events = "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20241224\r\nDTEND;VALUE=DATE:20241227\r\n\
+            END:VEVENT\r\nBEGIN:VEVENT\r\nDTSTART:20241231T000000Z\r\nDTEND:20250101T000000Z\r\n\
+            END:VEVENT\r\n";
+        assert_eq!(
+            holidays(events),
+            [
+                date("2024-12-24"),
+                date("2024-12-25"),
+                date("2024-12-26"),
+                date("2024-12-31")
+            ]
+        );
+    }
+
+    #[test]
+    fn holidays_recur() {
+        let yearly =
+            "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20240229\r\nRRULE:FREQ=YEARLY;UNTIL=20330101\r\n\
+            END:VEVENT\r\n";
+        // Only leap years have the day, and open rules end five years from today
+        assert_eq!(holidays(yearly), [date("2024-02-29"), date("2028-02-29")]);
+
+        let weekly = "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20240603\r\nDURATION:P2D\r\n\
+            RRULE:FREQ=WEEKLY;INTERVAL=2;\r\n COUNT=2\r\nEND:VEVENT\r\n";
+        assert_eq!(
+            holidays(weekly),
+            [
+                date("2024-06-03"),
+                date("2024-06-04"),
+                date("2024-06-17"),
+                date("2024-06-18")
+            ]
+        );
+    }
+
+    #[test]
+    fn holidays_with_unknown_rules_only_start() {
+        let events =
+            "BEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20240603\r\nRRULE:FREQ=WEEKLY;BYDAY=MO,FR\r\n\
+            END:VEVENT\r\n";
+        assert_eq!(holidays(events), [date("2024-06-03")]);
+    }
+
+    #[test]
+    fn dst_change_spring_forward() {
+        assert_eq!(
+            dst_change(&Berlin, utc("2024-03-30T12:00:00Z")).as_deref(),
+            Some("Clocks change\nSun 03:00 forward by 60 min")
+        );
+    }
+
+    #[test]
+    fn dst_change_fall_back() {
+        assert_eq!(
+            dst_change(&Berlin, utc("2024-10-26T12:00:00Z")).as_deref(),
+            Some("Clocks change\nSun 02:00 back by 60 min")
+        );
+    }
+
+    #[test]
+    fn dst_change_only_within_a_day() {
+        assert_eq!(dst_change(&Berlin, utc("2024-03-29T12:00:00Z")), None);
+        assert_eq!(dst_change(&Berlin, utc("2024-03-31T12:00:00Z")), None);
+        assert_eq!(dst_change(&Utc, utc("2024-10-26T12:00:00Z")), None);
+    }
+
+    #[test]
+    fn ambiguous_hour_shows_offset() {
+        let format = time_format(&Berlin, &naive("2024-10-27 02:30"));
+        assert_eq!(format, "%H:%M (UTC%:z)");
+        let first = Berlin
+            .from_local_datetime(&naive("2024-10-27 02:30"))
+            .earliest();
+        let second = Berlin
+            .from_local_datetime(&naive("2024-10-27 02:30"))
+            .latest();
+        assert_eq!(
+            first.unwrap().format(format).to_string(),
+            "02:30 (UTC+02:00)"
+        );
+        assert_eq!(
+            second.unwrap().format(format).to_string(),
+            "02:30 (UTC+01:00)"
+        );
+    }
+
+    #[test]
+    fn unambiguous_hours_show_no_offset() {
+        assert_eq!(time_format(&Berlin, &naive("2024-10-27 03:30")), "%H:%M");
+        // The skipped hour doesn't exist at all
+        assert_eq!(time_format(&Berlin, &naive("2024-03-31 02:30")), "%H:%M");
+        assert_eq!(time_format(&Berlin, &naive("2024-03-31 03:30")), "%H:%M");
+    }
 }
@@ -1,9 +1,31 @@
+use super::color::Color;
 use super::{Block, I3Block, I3Event};
 use std::fs::File;
 use std::io::{BufRead as _, BufReader};
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// How often to make the kernel check that the gateway is still there in seconds
+const PROBE_INTERVAL: u64 = 10;
+
+/// How long the gateway may stay unresolved before it's considered down in seconds. Resolving
+/// takes a few seconds, and entries that failed look the same as ones being resolved.
+const RESOLVE_TIMEOUT: u64 = 15;
+
+/// ARP entry flag of resolved entries
+const ATF_COM: u32 = 0x2;
+
+/// ARP entry flag of static entries
+const ATF_PERM: u32 = 0x4;
 
 #[derive(Default)]
-pub struct DefaultRouteBlock {}
+pub struct DefaultRouteBlock {
+    /// When the gateway was last probed
+    last_probe: Mutex<Option<Instant>>,
+    /// Since when the gateway's ARP entry is unresolved
+    unresolved_since: Mutex<Option<(Ipv4Addr, Instant)>>,
+}
 
 impl Block for DefaultRouteBlock {
     fn render(&self) -> Option<I3Block> {
@@ -14,19 +36,79 @@ impl Block for DefaultRouteBlock {
                 continue;
             };
             // Detect default route
-            if split.next() == Some("00000000") {
+            if split.next() != Some("00000000") {
+                continue;
+            }
+            // The kernel prints the address in network byte order as a native integer
+            let gateway = split
+                .next()
+                .and_then(|gateway| u32::from_str_radix(gateway, 16).ok())
+                .map(|gateway| Ipv4Addr::from(gateway.to_ne_bytes()))
+                .filter(|gateway| !gateway.is_unspecified());
+            // Point-to-point links have no gateway
+            if gateway.is_some_and(|gateway| !self.gateway_reachable(gateway)) {
                 return Some(I3Block {
-                    full_text: interface.to_owned(),
+                    full_text: format!("{interface} (gateway down)"),
+                    color: Some(Color::critical()),
                     ..Default::default()
                 });
             }
+            return Some(I3Block {
+                full_text: interface.to_owned(),
+                ..Default::default()
+            });
         }
         Some(I3Block {
             full_text: "No link".to_owned(),
-            color: Some("#ff0202".to_owned()),
+            color: Some(Color::critical()),
             ..Default::default()
         })
     }
 
     fn click(&self, _: &I3Event) {}
 }
+
+impl DefaultRouteBlock {
+    /// Returns whether the gateway answers ARP requests, or might still.
+    ///
+    /// Sending a datagram to the gateway makes the kernel confirm its neighbor entry, which fails
+    /// after a few seconds when the gateway doesn't answer anymore. Gateways without an entry,
+    /// like on links without ARP, are assumed to be reachable.
+    fn gateway_reachable(&self, gateway: Ipv4Addr) -> bool {
+        let mut last_probe = self.last_probe.lock().unwrap();
+        if last_probe.is_none_or(|last| last.elapsed().as_secs() >= PROBE_INTERVAL) {
+            *last_probe = Some(Instant::now());
+            if let Ok(socket) = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)) {
+                // Discard protocol
+                let _idc = socket.send_to(&[], (gateway, 9));
+            }
+        }
+        drop(last_probe);
+
+        let Ok(arp) = std::fs::read_to_string("/proc/net/arp") else {
+            return true;
+        };
+        let address = gateway.to_string();
+        let unresolved = arp
+            .lines()
+            .skip(1)
+            .map(|line| line.split_whitespace().collect::<Vec<_>>())
+            .find(|fields| fields.first() == Some(&address.as_str()))
+            .and_then(|fields| {
+                let flags = fields.get(2)?.strip_prefix("0x")?;
+                u32::from_str_radix(flags, 16).ok()
+            })
+            .is_some_and(|flags| flags & (ATF_COM | ATF_PERM) == 0);
+
+        let mut unresolved_since = self.unresolved_since.lock().unwrap();
+        if !unresolved {
+            *unresolved_since = None;
+            return true;
+        }
+        let since = unresolved_since
+            .filter(|(last, _)| *last == gateway)
+            .map_or_else(Instant::now, |(_, since)| since);
+        *unresolved_since = Some((gateway, since));
+        since.elapsed().as_secs() < RESOLVE_TIMEOUT
+    }
+}
@@ -0,0 +1,115 @@
+//! Collects errors from all blocks so they don't vanish into the i3 log
+
+use super::color::Color;
+use super::{escape_markup, Block, BlockContext, I3Block, I3Event};
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, OnceLock, RwLock};
+
+/// How many errors to keep around
+const MAX_ERRORS: usize = 20;
+
+/// How many of the latest errors to show in the tooltip
+const TOOLTIP_ERRORS: usize = 5;
+
+/// Where errors are sent to, once the block is created
+static ERROR_CHANNEL: OnceLock<Sender<String>> = OnceLock::new();
+
+/// Reports an error to the diagnostics block and to stderr
+pub fn report(message: String) {
+    eprintln!("{message}");
+    if let Some(channel) = ERROR_CHANNEL.get() {
+        let _idc = channel.send(message);
+    }
+}
+
+/// An error that was reported by some block
+struct ReportedError {
+    time: DateTime<Local>,
+    message: String,
+}
+
+pub struct DiagnosticsBlock {
+    /// Number of errors since the last clear
+    count: Arc<RwLock<usize>>,
+    /// The latest errors, newest last
+    errors: Arc<RwLock<VecDeque<ReportedError>>>,
+}
+
+impl Block for DiagnosticsBlock {
+    fn render(&self) -> Option<I3Block> {
+        let count = *self.count.read().unwrap();
+        if count == 0 {
+            return None;
+        }
+
+        let tooltip = self
+            .errors
+            .read()
+            .unwrap()
+            .iter()
+            .rev()
+            .take(TOOLTIP_ERRORS)
+            .map(|err| {
+                format!(
+                    "<b>{}</b> {}",
+                    err.time.format("%H:%M:%S"),
+                    escape_markup(&err.message)
+                )
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        Some(I3Block {
+            full_text: format!("⚠ {count}"),
+            color: Some(Color::critical()),
+            tooltip: Some(format!("{count} errors\n{tooltip}")),
+            tooltip_markup: true,
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button == 3 {
+            *self.count.write().unwrap() = 0;
+            self.errors.write().unwrap().clear();
+        }
+    }
+}
+
+impl DiagnosticsBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        let ret = Self {
+            count: Arc::new(RwLock::new(0)),
+            errors: Arc::new(RwLock::new(VecDeque::with_capacity(MAX_ERRORS))),
+        };
+
+        let (send, receive) = mpsc::channel::<String>();
+        if ERROR_CHANNEL.set(send).is_err() {
+            eprintln!("Only one diagnostics block is supported");
+            return ret;
+        }
+
+        // Collect errors
+        let count = Arc::clone(&ret.count);
+        let errors = Arc::clone(&ret.errors);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            for message in receive {
+                let mut errors = errors.write().unwrap();
+                if errors.len() == MAX_ERRORS {
+                    errors.pop_front();
+                }
+                errors.push_back(ReportedError {
+                    time: Local::now(),
+                    message,
+                });
+                *count.write().unwrap() += 1;
+                ctx.request_update();
+            }
+        });
+
+        ret
+    }
+}
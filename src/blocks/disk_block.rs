@@ -0,0 +1,317 @@
+//! Shows the free space of filesystems and when they will be full
+
+use super::color::Color;
+use super::notify::Urgency;
+use super::threshold::{Threshold, ThresholdNotifier};
+use super::units::format_bytes;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::collections::{HashSet, VecDeque};
+use std::ffi::CString;
+use std::fmt::Write as _;
+use std::os::unix::ffi::OsStrExt as _;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+/// How often to check the free space in seconds
+const POLL_INTERVAL: u64 = 10;
+
+/// How long a filesystem may take to respond in seconds, network shares can hang and disks may
+/// have to spin up
+const TIMEOUT: u64 = 5;
+
+/// How often to remember the free space in seconds
+const SAMPLE_INTERVAL: u64 = 60;
+
+/// How many samples the forecast is based on
+const MAX_SAMPLES: usize = 30;
+
+/// How many samples are needed for a forecast
+const MIN_SAMPLES: usize = 5;
+
+/// Only warn when the filesystem is full within this many seconds
+const FORECAST_HORIZON: u64 = 24 * 60 * 60;
+
+/// Below this many seconds until the filesystem is full, the block is colored
+const FORECAST_CRITICAL: u64 = 60 * 60;
+
+/// A filesystem to show
+pub struct Mount {
+    pub path: PathBuf,
+    /// Shown in front of the free space, may be empty
+    pub label: String,
+    /// Warns about little free space in percent
+    pub low_space: Option<ThresholdNotifier>,
+}
+
+impl Mount {
+    /// The root filesystem, warning when there is little space left
+    #[must_use]
+    pub fn root() -> Self {
+        Self {
+            path: PathBuf::from("/"),
+            label: String::new(),
+            low_space: Some(
+                ThresholdNotifier::below(vec![
+                    Threshold::new(10.0, Urgency::Normal, "Disk almost full", "{value} left"),
+                    Threshold::new(5.0, Urgency::Critical, "Disk almost full", "{value} left"),
+                ])
+                .with_hysteresis(1.0),
+            ),
+        }
+    }
+}
+
+pub struct DiskBlock {
+    mounts: Arc<Vec<Mount>>,
+    /// Latest usage of each mount, `None` if it's not mounted or didn't respond
+    usage: Arc<RwLock<Vec<Option<Usage>>>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Usage {
+    free: u64,
+    total: u64,
+    /// Seconds until the filesystem is full, if it's filling up
+    full_in: Option<u64>,
+}
+
+impl Block for DiskBlock {
+    fn render(&self) -> Option<I3Block> {
+        let usage = self.usage.read().unwrap();
+        let mut parts = vec![];
+        let mut tooltip = vec![];
+        let mut critical = false;
+        for (mount, usage) in self.mounts.iter().zip(usage.iter()) {
+            let Some(Usage {
+                free,
+                total,
+                full_in,
+            }) = *usage
+            else {
+                continue;
+            };
+
+            let low = free.saturating_mul(20) < total;
+            critical |= low || full_in.is_some_and(|secs| secs < FORECAST_CRITICAL);
+
+            let mut part = if mount.label.is_empty() {
+                format_bytes(free)
+            } else {
+                format!("{} {}", mount.label, format_bytes(free))
+            };
+            if let Some(full_in) = full_in {
+                let _idc = write!(part, " full in ~{}", format_secs(full_in));
+            }
+            parts.push(part);
+            tooltip.push(format!(
+                "{}: {} free of {}",
+                mount.path.display(),
+                format_bytes(free),
+                format_bytes(total)
+            ));
+        }
+        if parts.is_empty() {
+            return None;
+        }
+
+        Some(I3Block {
+            full_text: format!("💾{}", parts.join(" ")),
+            color: if critical {
+                Some(Color::critical())
+            } else {
+                None
+            },
+            tooltip: Some(tooltip.join("\n")),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+}
+
+impl DiskBlock {
+    /// Creates the block showing the mounts in the given order. Mounts that aren't mounted or
+    /// don't respond are hidden.
+    #[must_use]
+    pub fn new(ctx: &BlockContext, mounts: Vec<Mount>) -> Self {
+        let mounts = Arc::new(mounts);
+        let usage = Arc::new(RwLock::new(vec![None; mounts.len()]));
+
+        let mounts2 = Arc::clone(&mounts);
+        let usage2 = Arc::clone(&usage);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            // Free bytes over time, by mount
+            let mut samples = vec![VecDeque::new(); mounts2.len()];
+            // Mounts that are still stuck in `statvfs()`
+            let in_flight = Arc::new(Mutex::new(HashSet::new()));
+            loop {
+                let mounted = mount_points();
+                let current: Vec<_> = mounts2
+                    .iter()
+                    .zip(&mut samples)
+                    .map(|(mount, samples)| {
+                        // Removable media and network shares come and go
+                        if !mounted.contains(&mount.path) {
+                            return None;
+                        }
+                        let (free, total) = disk_space_in_time(&mount.path, &in_flight)?;
+                        if let Some(low_space) = &mount.low_space {
+                            #[allow(clippy::cast_precision_loss)] // Who cares
+                            let percent = free as f64 / total.max(1) as f64 * 100.0;
+                            low_space.update(
+                                percent,
+                                &format!("{} on {}", format_bytes(free), mount.path.display()),
+                            );
+                        }
+                        Some(Usage {
+                            free,
+                            total,
+                            full_in: record(samples, free),
+                        })
+                    })
+                    .collect();
+
+                if *usage2.read().unwrap() != current {
+                    *usage2.write().unwrap() = current;
+                    ctx.request_update();
+                }
+                std::thread::sleep(Duration::from_secs(POLL_INTERVAL));
+            }
+        });
+
+        Self { mounts, usage }
+    }
+}
+
+/// Like [`disk_space`], but gives up on filesystems that take too long to respond
+fn disk_space_in_time(path: &Path, in_flight: &Arc<Mutex<HashSet<PathBuf>>>) -> Option<(u64, u64)> {
+    // Don't pile up threads for mounts that are still stuck
+    if !in_flight.lock().unwrap().insert(path.to_owned()) {
+        return None;
+    }
+    let (send, receive) = mpsc::channel();
+    let in_flight = Arc::clone(in_flight);
+    let path = path.to_owned();
+    std::thread::spawn(move || {
+        let space = disk_space(&path);
+        in_flight.lock().unwrap().remove(&path);
+        let _idc = send.send(space);
+    });
+    receive
+        .recv_timeout(Duration::from_secs(TIMEOUT))
+        .ok()
+        .flatten()
+}
+
+/// Remembers the free space from time to time and returns the forecast
+fn record(samples: &mut VecDeque<(Instant, u64)>, free: u64) -> Option<u64> {
+    if samples
+        .back()
+        .is_none_or(|(time, _)| time.elapsed().as_secs() >= SAMPLE_INTERVAL)
+    {
+        if samples.len() == MAX_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back((Instant::now(), free));
+    }
+    forecast(samples, free)
+}
+
+/// Returns the paths everything is mounted at
+fn mount_points() -> Vec<PathBuf> {
+    // Lines like "/dev/sda1 /run/media/usb\040stick vfat rw 0 0", with octal escapes
+    let Ok(mounts) = std::fs::read_to_string("/proc/self/mounts") else {
+        return vec![];
+    };
+    mounts
+        .lines()
+        .filter_map(|line| line.split(' ').nth(1))
+        .map(|path| {
+            PathBuf::from(
+                path.replace("\\040", " ")
+                    .replace("\\011", "\t")
+                    .replace("\\012", "\n")
+                    .replace("\\134", "\\"),
+            )
+        })
+        .collect()
+}
+
+/// Returns the free and total bytes of the filesystem at the path
+#[must_use]
+pub fn disk_space(path: &Path) -> Option<(u64, u64)> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: The path is a valid C string and buf is a valid statvfs struct
+    let buf = unsafe {
+        let mut buf = std::mem::zeroed::<libc::statvfs>();
+        if libc::statvfs(path.as_ptr(), &raw mut buf) != 0 {
+            return None;
+        }
+        buf
+    };
+    Some((buf.f_bavail * buf.f_frsize, buf.f_blocks * buf.f_frsize))
+}
+
+/// Estimates in how many seconds there is no free space left, if the trend is clearly downward
+fn forecast(samples: &VecDeque<(Instant, u64)>, free: u64) -> Option<u64> {
+    if samples.len() < MIN_SAMPLES {
+        return None;
+    }
+    let (first, _) = samples.front()?;
+
+    // Least squares fit of the free space over time
+    #[allow(clippy::cast_precision_loss)] // Who cares
+    let points = samples
+        .iter()
+        .map(|(time, free)| ((*time - *first).as_secs_f64(), *free as f64))
+        .collect::<Vec<_>>();
+    #[allow(clippy::cast_precision_loss)]
+    let len = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / len;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / len;
+    let covariance = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum::<f64>();
+    let variance = points
+        .iter()
+        .map(|(x, _)| (x - mean_x).powi(2))
+        .sum::<f64>();
+    if variance == 0.0 {
+        return None;
+    }
+    let slope = covariance / variance;
+
+    // Only warn when most samples went down, a single big deletion shouldn't cause a warning
+    let decreasing = samples
+        .iter()
+        .zip(samples.iter().skip(1))
+        .filter(|((_, a), (_, b))| b < a)
+        .count();
+    if slope >= 0.0 || decreasing * 2 < samples.len() - 1 {
+        return None;
+    }
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    let secs = (free as f64 / -slope) as u64;
+    if secs > FORECAST_HORIZON {
+        None
+    } else {
+        Some(secs)
+    }
+}
+
+/// Formats a rough duration
+fn format_secs(secs: u64) -> String {
+    if secs >= 60 * 60 {
+        format!("{}h", secs / 60 / 60)
+    } else {
+        format!("{}m", secs / 60)
+    }
+}
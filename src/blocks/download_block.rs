@@ -0,0 +1,322 @@
+//! Shows the downloads in progress in a directory, like the `.part` files of yt-dlp and Firefox
+
+use super::diagnostics_block::report;
+use super::http;
+use super::text::truncate_display;
+use super::units::{format_bytes, format_rate};
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CString;
+use std::fmt::Write as _;
+use std::os::fd::{AsRawFd as _, FromRawFd as _, OwnedFd};
+use std::os::unix::ffi::OsStrExt as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// How often to measure the growth of running downloads
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// File name suffixes of unfinished downloads
+const SUFFIXES: [&str; 3] = [".part", ".crdownload", ".download"];
+
+/// How many columns the name of the shown download may take up
+const MAX_NAME_WIDTH: usize = 20;
+
+pub struct DownloadBlock {
+    downloads: Arc<RwLock<Vec<Download>>>,
+}
+
+#[derive(Clone, PartialEq)]
+struct Download {
+    /// File name without the suffix
+    name: String,
+    size: u64,
+    /// Bytes per second between the last two samples
+    rate: Option<f64>,
+    /// Expected size once finished, if known
+    total: Option<u64>,
+}
+
+impl Download {
+    /// Time until the download is finished at the current rate
+    fn eta(&self) -> Option<Duration> {
+        let rate = self.rate.filter(|rate| *rate > 0.0)?;
+        let left = self.total?.checked_sub(self.size)?;
+        #[allow(clippy::cast_precision_loss)] // Who cares
+        Duration::try_from_secs_f64(left as f64 / rate).ok()
+    }
+}
+
+/// What is known about a download from previous scans
+struct Sample {
+    time: Instant,
+    size: u64,
+    rate: Option<f64>,
+    /// Content-Length of where the download comes from, only looked up once
+    length: Option<u64>,
+}
+
+impl Block for DownloadBlock {
+    fn render(&self) -> Option<I3Block> {
+        let downloads = self.downloads.read().unwrap();
+        let first = downloads.first()?;
+
+        let mut full_text = format!(
+            "⬇{} {}",
+            truncate_display(&first.name, MAX_NAME_WIDTH),
+            format_bytes(first.size)
+        );
+        if let Some(rate) = first.rate {
+            let _idc = write!(full_text, " {}", format_rate(rate));
+        }
+        if let Some(eta) = first.eta() {
+            let minutes = eta.as_secs() / 60;
+            let _idc = write!(full_text, " {}:{:02}", minutes / 60, minutes % 60);
+        }
+        if downloads.len() > 1 {
+            let _idc = write!(full_text, " +{}", downloads.len() - 1);
+        }
+        let tooltip = downloads
+            .iter()
+            .map(|download| {
+                let mut line = format!("{} {}", download.name, format_bytes(download.size));
+                if let Some(total) = download.total {
+                    let _idc = write!(line, " of {}", format_bytes(total));
+                }
+                if let Some(rate) = download.rate {
+                    let _idc = write!(line, " {}", format_rate(rate));
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        Some(I3Block {
+            full_text,
+            tooltip: Some(tooltip),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+}
+
+impl DownloadBlock {
+    /// Creates the block watching `dir`, the block stays hidden without one
+    #[must_use]
+    pub fn new(ctx: &BlockContext, dir: Option<PathBuf>) -> Self {
+        let downloads = Arc::new(RwLock::new(vec![]));
+
+        if let Some(dir) = dir {
+            let downloads2 = Arc::clone(&downloads);
+            let ctx = ctx.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = watch(&dir, &downloads2, &ctx) {
+                    report(format!("Failed to watch {}: {e}", dir.display()));
+                }
+            });
+        }
+
+        Self { downloads }
+    }
+}
+
+/// Rescans the directory whenever unfinished downloads appear or disappear, and measures their
+/// growth regularly while there are any
+fn watch(dir: &Path, downloads: &RwLock<Vec<Download>>, ctx: &BlockContext) -> std::io::Result<()> {
+    // SAFETY: inotify_init1() has no memory safety preconditions
+    let fd = unsafe { libc::inotify_init1(libc::IN_CLOEXEC) };
+    if fd < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: The fd was just opened and is owned by nobody else
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    let path = CString::new(dir.as_os_str().as_bytes()).map_err(std::io::Error::other)?;
+    // SAFETY: The path is a valid C string
+    let watch = unsafe {
+        libc::inotify_add_watch(
+            fd.as_raw_fd(),
+            path.as_ptr(),
+            libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_FROM | libc::IN_MOVED_TO,
+        )
+    };
+    if watch < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let mut samples = HashMap::new();
+    let mut next_sample = Instant::now();
+    loop {
+        // Rates are only measured on the timer so they always span the same interval
+        let sample = Instant::now() >= next_sample;
+        if sample {
+            next_sample = Instant::now() + SAMPLE_INTERVAL;
+        }
+        let current = scan(dir, &mut samples, sample);
+        let running = !current.is_empty();
+        if *downloads.read().unwrap() != current {
+            *downloads.write().unwrap() = current;
+            ctx.request_update();
+        }
+
+        // Only wake up regularly while there is something to measure
+        wait(&fd, running.then_some(next_sample))?;
+    }
+}
+
+/// Waits until `deadline` or until an unfinished download appears or disappears
+fn wait(fd: &OwnedFd, deadline: Option<Instant>) -> std::io::Result<()> {
+    let mut buf = [0u8; 4096];
+    loop {
+        let timeout = deadline.map_or(-1, |deadline| {
+            let left = deadline.saturating_duration_since(Instant::now());
+            i32::try_from(left.as_millis()).unwrap_or(i32::MAX)
+        });
+        let mut pollfd = libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: The pollfd is valid for the duration of the call
+        let ready = unsafe { libc::poll(&raw mut pollfd, 1, timeout) };
+        if ready <= 0 {
+            // Interrupted or timed out, either way scanning again doesn't hurt
+            return Ok(());
+        }
+        // SAFETY: The buffer is valid for its length
+        let len = unsafe { libc::read(fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+        let Ok(len) = usize::try_from(len) else {
+            return Err(std::io::Error::last_os_error());
+        };
+        if changes_downloads(&buf[..len]) {
+            return Ok(());
+        }
+    }
+}
+
+/// Whether any of the inotify events is about an unfinished download
+fn changes_downloads(mut events: &[u8]) -> bool {
+    const HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+    while events.len() >= HEADER {
+        let field = |offset: usize| {
+            u32::from_ne_bytes(events[offset..offset + 4].try_into().unwrap_or_default())
+        };
+        // The fields are wd, mask, cookie and len
+        if field(4) & libc::IN_Q_OVERFLOW != 0 {
+            return true;
+        }
+        let len = usize::try_from(field(12)).unwrap_or(usize::MAX);
+        let Some(name) = events.get(HEADER..HEADER.saturating_add(len)) else {
+            break;
+        };
+        // The name is padded with NULs
+        let name = name.split(|b| *b == 0).next().unwrap_or_default();
+        if SUFFIXES
+            .iter()
+            .any(|suffix| name.ends_with(suffix.as_bytes()))
+        {
+            return true;
+        }
+        events = &events[HEADER + len..];
+    }
+    false
+}
+
+/// Lists the unfinished downloads. Their rates are only measured again if `sample` is set.
+fn scan(dir: &Path, samples: &mut HashMap<String, Sample>, sample: bool) -> Vec<Download> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        samples.clear();
+        return vec![];
+    };
+    let now = Instant::now();
+    let mut seen = HashSet::new();
+    let mut downloads = vec![];
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let Some(name) = SUFFIXES
+            .iter()
+            .find_map(|suffix| file_name.strip_suffix(suffix))
+        else {
+            continue;
+        };
+        let name = name.to_owned();
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        let size = metadata.len();
+
+        let last = match samples.entry(file_name.clone()) {
+            Entry::Occupied(last) => {
+                let last = last.into_mut();
+                if sample {
+                    #[allow(clippy::cast_precision_loss)] // Who cares
+                    let rate =
+                        size.saturating_sub(last.size) as f64 / (now - last.time).as_secs_f64();
+                    *last = Sample {
+                        time: now,
+                        size,
+                        rate: Some(rate),
+                        length: last.length,
+                    };
+                }
+                last
+            }
+            Entry::Vacant(entry_) => entry_.insert(Sample {
+                time: now,
+                size,
+                rate: None,
+                length: content_length(&entry.path()),
+            }),
+        };
+        let total = ytdl_total(&dir.join(format!("{name}.ytdl")), size).or(last.length);
+        downloads.push(Download {
+            name,
+            size,
+            rate: last.rate,
+            total,
+        });
+        seen.insert(file_name);
+    }
+    samples.retain(|file_name, _| seen.contains(file_name));
+    // The fastest download is the most interesting one
+    downloads.sort_by(|a, b| {
+        b.rate
+            .unwrap_or_default()
+            .total_cmp(&a.rate.unwrap_or_default())
+    });
+    downloads
+}
+
+/// Estimates the final size of a fragmented yt-dlp download from the fragments done so far,
+/// which yt-dlp keeps track of in a `.ytdl` file next to the download
+fn ytdl_total(path: &Path, size: u64) -> Option<u64> {
+    let state: serde_json::Value = serde_json::from_slice(&std::fs::read(path).ok()?).ok()?;
+    let downloader = &state["downloader"];
+    let done = downloader["current_fragment"]["index"]
+        .as_u64()
+        .filter(|done| *done > 0)?;
+    let count = downloader["fragment_count"].as_u64()?;
+    Some(size / done * count)
+}
+
+/// Looks up the size of the download at the URL it comes from, which wget and some browsers
+/// store in the extended attributes of the file
+fn content_length(path: &Path) -> Option<u64> {
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut buf = [0u8; 4096];
+    // SAFETY: The path is a valid C string and the buffer is valid for its length
+    let len = unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            c"user.xdg.origin.url".as_ptr(),
+            buf.as_mut_ptr().cast(),
+            buf.len(),
+        )
+    };
+    let url = std::str::from_utf8(buf.get(..usize::try_from(len).ok()?)?).ok()?;
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return None;
+    }
+    http::content_length(url).ok()?
+}
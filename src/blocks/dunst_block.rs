@@ -1,4 +1,5 @@
-use super::{Block, I3Block, I3Event};
+use super::color::Color;
+use super::{Block, BlockContext, I3Block, I3Event};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{
     mpsc::{self, Sender},
@@ -18,7 +19,7 @@ impl Block for DunstBlock {
         if paused_state.load(Ordering::Relaxed) {
             Some(I3Block {
                 full_text: "paused".to_owned(),
-                color: Some("#ff0202".to_owned()),
+                color: Some(Color::critical()),
                 ..Default::default()
             })
         } else {
@@ -39,7 +40,8 @@ impl Block for DunstBlock {
 }
 
 impl DunstBlock {
-    pub fn new(timer_cancel: Sender<()>) -> Self {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
         // Connect
         let Ok(dbus_conn) = Connection::session() else {
             return Self {
@@ -73,11 +75,12 @@ impl DunstBlock {
         // Query future signals
         let stream = proxy.receive_property_changed::<bool>("paused");
         let value2 = Arc::clone(&value);
+        let ctx = ctx.clone();
         std::thread::spawn(move || {
             for item in stream {
                 if let Ok(value) = item.get() {
                     value2.store(value, Ordering::Relaxed);
-                    let _idc = timer_cancel.send(());
+                    ctx.request_update();
                 }
             }
         });
@@ -0,0 +1,220 @@
+//! Integrates the power draw over the day
+//!
+//! The energy is either measured by the package energy counter of the CPU, which usually needs
+//! root, or as what is drawn from the battery. The two can't be compared, so one is picked at
+//! startup and shown in the block.
+
+use super::diagnostics_block::report;
+use super::{Block, I3Block, I3Event};
+use chrono::{Local, NaiveDate};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Package energy counter of the CPU
+const RAPL_DIR: &str = "/sys/class/powercap/intel-rapl:0";
+
+/// How often to persist the consumption in seconds
+const SAVE_INTERVAL: u64 = 60;
+
+/// What the energy is measured by
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Source {
+    /// The CPU package only, but also on AC
+    Rapl,
+    /// The whole machine, but only on battery
+    Battery,
+}
+
+impl Source {
+    /// Name in the state file
+    fn key(self) -> &'static str {
+        match self {
+            Self::Rapl => "rapl",
+            Self::Battery => "battery",
+        }
+    }
+}
+
+pub struct EnergyBlock {
+    /// Price of a kWh in Euro
+    price_per_kwh: Option<f64>,
+    source: Source,
+    state: Mutex<EnergyState>,
+}
+
+struct EnergyState {
+    /// Day the consumption is counted for
+    day: NaiveDate,
+    /// Consumed energy today in Wh
+    consumed: f64,
+    /// When the consumption was last updated
+    last_update: Instant,
+    /// Last value of the RAPL counter in µJ
+    last_rapl: Option<u64>,
+    /// Where the consumption is persisted
+    state_file: Option<PathBuf>,
+    last_save: Instant,
+}
+
+impl Block for EnergyBlock {
+    fn render(&self) -> Option<I3Block> {
+        let mut state = self.state.lock().unwrap();
+
+        // Start over every day
+        let today = Local::now().date_naive();
+        if state.day != today {
+            state.day = today;
+            state.consumed = 0.0;
+            state.save(self.source);
+        }
+
+        let elapsed = state.last_update.elapsed().as_secs_f64();
+        state.last_update = Instant::now();
+
+        match self.source {
+            Source::Rapl => {
+                let energy = read_u64(&PathBuf::from(RAPL_DIR).join("energy_uj"))?;
+                if let Some(last) = state.last_rapl {
+                    let delta = energy.checked_sub(last).or_else(|| {
+                        // The counter wrapped around, the sample is dropped if the range is bogus
+                        read_u64(&PathBuf::from(RAPL_DIR).join("max_energy_range_uj"))
+                            .and_then(|max| max.checked_sub(last))
+                            .and_then(|rest| rest.checked_add(energy))
+                    });
+                    if let Some(delta) = delta {
+                        #[allow(clippy::cast_precision_loss)] // Who cares
+                        let wh = delta as f64 / 3_600_000_000.0;
+                        state.consumed += wh;
+                    }
+                }
+                state.last_rapl = Some(energy);
+            }
+            Source::Battery => {
+                if let Some(watts) = battery_discharge() {
+                    state.consumed += watts * elapsed / 3600.0;
+                } else if state.consumed == 0.0 {
+                    return None;
+                }
+            }
+        }
+        if state.last_save.elapsed().as_secs() >= SAVE_INTERVAL {
+            state.save(self.source);
+        }
+
+        let (label, tooltip) = match self.source {
+            Source::Rapl => ("CPU", "Energy used by the CPU package today"),
+            Source::Battery => ("🔋", "Energy drawn from the battery today"),
+        };
+        let full_text = if let Some(price) = self.price_per_kwh {
+            format!(
+                "⚡{label} {:.1} Wh ({:.2} €)",
+                state.consumed,
+                state.consumed / 1000.0 * price
+            )
+        } else {
+            format!("⚡{label} {:.1} Wh", state.consumed)
+        };
+        Some(I3Block {
+            full_text,
+            tooltip: Some(tooltip.to_owned()),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+
+    fn resume(&self) {
+        // The RAPL counter may have been reset
+        let mut state = self.state.lock().unwrap();
+        state.last_rapl = None;
+        state.last_update = Instant::now();
+    }
+
+    fn shutdown(&self) {
+        self.state.lock().unwrap().save(self.source);
+    }
+}
+
+impl EnergyBlock {
+    /// Creates the block. The cost is only shown when `price_per_kwh` is given. The consumption
+    /// is persisted to `state_file` if given.
+    #[must_use]
+    pub fn new(price_per_kwh: Option<f64>, state_file: Option<PathBuf>) -> Self {
+        // Prefer RAPL since it also works on AC, if we may read it
+        let source = if read_u64(&PathBuf::from(RAPL_DIR).join("energy_uj")).is_some() {
+            Source::Rapl
+        } else {
+            Source::Battery
+        };
+        let today = Local::now().date_naive();
+        Self {
+            price_per_kwh,
+            source,
+            state: Mutex::new(EnergyState {
+                day: today,
+                consumed: state_file
+                    .as_deref()
+                    .and_then(|path| load(path, today, source))
+                    .unwrap_or_default(),
+                last_update: Instant::now(),
+                last_rapl: None,
+                state_file,
+                last_save: Instant::now(),
+            }),
+        }
+    }
+}
+
+impl EnergyState {
+    /// Writes the consumption to the state file
+    fn save(&mut self, source: Source) {
+        self.last_save = Instant::now();
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        let contents = format!("{}\t{}\t{}\n", self.day, source.key(), self.consumed);
+        if let Err(e) = std::fs::write(path, contents) {
+            report(format!("Failed to write energy consumption: {e}"));
+        }
+    }
+}
+
+/// Loads the consumption from the state file if it's from today and measured the same way
+fn load(path: &Path, today: NaiveDate, source: Source) -> Option<f64> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let mut fields = contents.trim_end().split('\t');
+    if fields.next()?.parse::<NaiveDate>().ok()? != today || fields.next()? != source.key() {
+        return None;
+    }
+    fields.next()?.parse().ok()
+}
+
+/// Reads a number from a sysfs file
+fn read_u64(path: &Path) -> Option<u64> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Returns the power the batteries are discharging with in W
+fn battery_discharge() -> Option<f64> {
+    let mut total = None;
+    for supply in std::fs::read_dir("/sys/class/power_supply").ok()?.flatten() {
+        let path = supply.path();
+        if !std::fs::read_to_string(path.join("status")).is_ok_and(|s| s.trim() == "Discharging") {
+            continue;
+        }
+        let microwatts = read_u64(&path.join("power_now")).or_else(|| {
+            // Some batteries only report current and voltage
+            Some(
+                read_u64(&path.join("current_now"))? * read_u64(&path.join("voltage_now"))?
+                    / 1_000_000,
+            )
+        });
+        if let Some(microwatts) = microwatts {
+            #[allow(clippy::cast_precision_loss)] // Who cares
+            let watts = microwatts as f64 / 1_000_000.0;
+            *total.get_or_insert(0.0) += watts;
+        }
+    }
+    total
+}
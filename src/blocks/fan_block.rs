@@ -0,0 +1,214 @@
+//! Shows the fan profile and toggles between automatic control and full speed
+
+use super::diagnostics_block::report;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::fmt::Write as _;
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Fan control of `thinkpad_acpi`, which needs the `fan_control=1` module option to be written
+const THINKPAD_FAN: &str = "/proc/acpi/ibm/fan";
+
+/// How often to poll the fan state in seconds
+const POLL_INTERVAL: u64 = 10;
+
+#[derive(Clone, Copy)]
+enum Backend {
+    ThinkPad,
+    /// `nbfc`, which supports many other laptops
+    Nbfc,
+    /// ASUS laptops, where full speed is the performance profile
+    Asusctl,
+}
+
+#[derive(Clone, Copy)]
+struct FanState {
+    full_speed: bool,
+    /// Only known on `ThinkPads`
+    rpm: Option<u32>,
+}
+
+/// Last polled state and when it was polled
+type Polled = Mutex<Option<(Instant, Option<FanState>)>>;
+
+pub struct FanBlock {
+    backend: Option<Backend>,
+    /// Whether we may switch the fan, the `ThinkPad` fan can only be switched by root
+    switchable: bool,
+    last: Arc<Polled>,
+    ctx: BlockContext,
+}
+
+impl Block for FanBlock {
+    fn render(&self) -> Option<I3Block> {
+        let backend = self.backend?;
+        let mut last = self.last.lock().unwrap();
+        if last
+            .as_ref()
+            .is_none_or(|(time, _)| time.elapsed() >= Duration::from_secs(POLL_INTERVAL))
+        {
+            *last = Some((Instant::now(), read_state(backend)));
+        }
+        let state = last.as_ref()?.1?;
+
+        let mut full_text = if state.full_speed {
+            "🌀full".to_owned()
+        } else {
+            "🌀auto".to_owned()
+        };
+        if let Some(rpm) = state.rpm {
+            let _idc = write!(full_text, " {rpm}rpm");
+        }
+        Some(I3Block {
+            full_text,
+            tooltip: Some(if self.switchable {
+                "Click to toggle full speed".to_owned()
+            } else {
+                format!("Switching the fan needs write access to {THINKPAD_FAN}")
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button != 1 {
+            return;
+        }
+        let Some(backend) = self.backend.filter(|_| self.switchable) else {
+            return;
+        };
+        let Some((_, Some(state))) = *self.last.lock().unwrap() else {
+            return;
+        };
+
+        // The tools may take a while, so don't block the bar
+        let last = Arc::clone(&self.last);
+        let ctx = self.ctx.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = set_full_speed(backend, !state.full_speed) {
+                report(format!("Failed to set the fan profile: {e}"));
+            }
+            // Show the new state on the next render
+            *last.lock().unwrap() = None;
+            ctx.request_update();
+        });
+    }
+}
+
+impl FanBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        let backend = if Path::new(THINKPAD_FAN).exists() {
+            Some(Backend::ThinkPad)
+        } else if in_path("nbfc") {
+            Some(Backend::Nbfc)
+        } else if in_path("asusctl") {
+            Some(Backend::Asusctl)
+        } else {
+            None
+        };
+        let switchable = match backend {
+            Some(Backend::ThinkPad) => OpenOptions::new().write(true).open(THINKPAD_FAN).is_ok(),
+            _ => true,
+        };
+        Self {
+            backend,
+            switchable,
+            last: Arc::new(Mutex::new(None)),
+            ctx: ctx.clone(),
+        }
+    }
+}
+
+/// Checks whether a program can be found in `$PATH`, without running it
+fn in_path(program: &str) -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// Reads whether the fan runs at full speed
+fn read_state(backend: Backend) -> Option<FanState> {
+    match backend {
+        Backend::ThinkPad => {
+            // Lines like "level:\t\tauto"
+            let contents = std::fs::read_to_string(THINKPAD_FAN).ok()?;
+            let field = |name: &str| {
+                contents.lines().find_map(|line| {
+                    let (key, value) = line.split_once(':')?;
+                    (key == name).then(|| value.trim().to_owned())
+                })
+            };
+            Some(FanState {
+                full_speed: matches!(field("level")?.as_str(), "full-speed" | "disengaged" | "7"),
+                rpm: field("speed").and_then(|speed| speed.parse().ok()),
+            })
+        }
+        Backend::Nbfc => {
+            let output = Command::new("nbfc")
+                .args(["status", "--all"])
+                .output()
+                .ok()?;
+            let output = String::from_utf8_lossy(&output.stdout);
+            // Every fan has a line like "Auto Control Enabled : true"
+            let auto = output
+                .lines()
+                .filter(|line| line.to_lowercase().starts_with("auto control enabled"))
+                .map(|line| line.to_lowercase().ends_with("true"))
+                .collect::<Vec<_>>();
+            if auto.is_empty() {
+                return None;
+            }
+            Some(FanState {
+                full_speed: !auto.iter().any(|auto| *auto),
+                rpm: None,
+            })
+        }
+        Backend::Asusctl => {
+            let output = Command::new("asusctl")
+                .args(["profile", "-p"])
+                .output()
+                .ok()?;
+            Some(FanState {
+                full_speed: String::from_utf8_lossy(&output.stdout).contains("Performance"),
+                rpm: None,
+            })
+        }
+    }
+}
+
+/// Switches the fans to full speed or back to automatic control
+fn set_full_speed(backend: Backend, full_speed: bool) -> std::io::Result<()> {
+    let status = match backend {
+        Backend::ThinkPad => {
+            let level = if full_speed { "full-speed" } else { "auto" };
+            return std::fs::write(THINKPAD_FAN, format!("level {level}"));
+        }
+        Backend::Nbfc => {
+            if full_speed {
+                Command::new("nbfc")
+                    .args(["set", "--speed", "100"])
+                    .status()?
+            } else {
+                Command::new("nbfc").args(["set", "--auto"]).status()?
+            }
+        }
+        Backend::Asusctl => {
+            let profile = if full_speed {
+                "Performance"
+            } else {
+                "Balanced"
+            };
+            Command::new("asusctl")
+                .args(["profile", "-P", profile])
+                .status()?
+        }
+    };
+    if status.success() {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(format!("exited with {status}")))
+    }
+}
@@ -79,6 +79,8 @@ unsafe impl Sync for HidppInner {}
 impl HidppInner {
     /// Finds all relevant devices and dedup them
     fn enumerate_receivers(&mut self) {
+        // The device list is only read once otherwise, missing receivers plugged in later
+        let _idc = self.hid_api.refresh_devices();
         self.receivers = self
             .hid_api
             .device_list()
@@ -0,0 +1,88 @@
+//! Shows the clients and throughput of a hotspot running on this machine
+
+use super::nl80211::{Nl80211, NL80211_IFTYPE_AP};
+use super::units::format_rate;
+use super::{Block, I3Block, I3Event};
+use std::sync::Mutex;
+use std::time::Instant;
+
+#[derive(Default)]
+pub struct HotspotBlock {
+    state: Mutex<HotspotState>,
+}
+
+#[derive(Default)]
+struct HotspotState {
+    nl80211: Option<Nl80211>,
+    /// Bytes transferred by the access point interfaces at the previous render
+    last: Option<(Instant, u64)>,
+}
+
+impl Block for HotspotBlock {
+    fn render(&self) -> Option<I3Block> {
+        let mut state = self.state.lock().unwrap();
+        if state.nl80211.is_none() {
+            state.nl80211 = Nl80211::connect().ok();
+        }
+        let nl80211 = state.nl80211.as_mut()?;
+        let Ok(interfaces) = nl80211.interfaces() else {
+            // Reconnect on the next render
+            state.nl80211 = None;
+            return None;
+        };
+
+        let mut ssids = vec![];
+        let mut clients = 0;
+        let mut bytes = 0;
+        for interface in interfaces {
+            if interface.kind != NL80211_IFTYPE_AP {
+                continue;
+            }
+            clients += nl80211
+                .stations(&interface)
+                .map_or(0, |stations| stations.len());
+            bytes += read_bytes(&interface.name).unwrap_or_default();
+            ssids.push(interface.ssid.unwrap_or(interface.name));
+        }
+        if ssids.is_empty() {
+            state.last = None;
+            return None;
+        }
+
+        let rate = state.last.map(|(time, last)| {
+            #[allow(clippy::cast_precision_loss)] // Who cares
+            let rate = bytes.saturating_sub(last) as f64 / time.elapsed().as_secs_f64();
+            rate
+        });
+        state.last = Some((Instant::now(), bytes));
+
+        let full_text = if let Some(rate) = rate {
+            format!("📲{clients} {}", format_rate(rate))
+        } else {
+            format!("📲{clients}")
+        };
+        Some(I3Block {
+            full_text,
+            tooltip: Some(format!("Hotspot\n{}", ssids.join("\n"))),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+
+    fn resume(&self) {
+        self.state.lock().unwrap().last = None;
+    }
+}
+
+/// Reads the received and sent bytes of the interface
+fn read_bytes(interface: &str) -> Option<u64> {
+    let read = |name: &str| {
+        std::fs::read_to_string(format!("/sys/class/net/{interface}/statistics/{name}"))
+            .ok()?
+            .trim()
+            .parse::<u64>()
+            .ok()
+    };
+    Some(read("rx_bytes")? + read("tx_bytes")?)
+}
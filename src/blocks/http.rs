@@ -0,0 +1,233 @@
+//! Minimal HTTP client running curl, which takes care of TLS, redirects and proxies
+
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+
+/// How long a request may take in seconds
+const TIMEOUT: u64 = 10;
+
+/// Body of a request
+#[derive(Clone, Copy)]
+pub enum Body<'a> {
+    Json(&'a str),
+    /// URL encoded, like `hashes=all`
+    Form(&'a str),
+}
+
+/// Sends a request and returns the body of a successful response. Headers look like
+/// `X-Api-Key: secret`.
+pub fn request(
+    method: &str,
+    url: &str,
+    headers: &[String],
+    body: Option<Body>,
+) -> Result<Vec<u8>, String> {
+    let mut config = vec![
+        ("fail", None),
+        ("location", None),
+        ("request", Some(method)),
+    ];
+    for header in headers {
+        config.push(("header", Some(header)));
+    }
+    match body {
+        Some(Body::Json(body)) => {
+            config.push(("header", Some("Content-Type: application/json")));
+            config.push(("data", Some(body)));
+        }
+        Some(Body::Form(body)) => config.push(("data", Some(body))),
+        None => {}
+    }
+    config.push(("url", Some(url)));
+    curl(&config, TIMEOUT)
+}
+
+/// Sends a request like [`request`], but returns the status and body of any response, so APIs
+/// explaining their errors in the body can be handled. The request may take `timeout` seconds,
+/// for long polling.
+pub fn request_status(
+    method: &str,
+    url: &str,
+    headers: &[String],
+    body: Option<&str>,
+    timeout: u64,
+) -> Result<(u16, Vec<u8>), String> {
+    let mut config = vec![
+        ("location", None),
+        ("request", Some(method)),
+        ("write-out", Some("\\n%{http_code}")),
+    ];
+    for header in headers {
+        config.push(("header", Some(header)));
+    }
+    if let Some(body) = body {
+        config.push(("header", Some("Content-Type: application/json")));
+        config.push(("data", Some(body)));
+    }
+    config.push(("url", Some(url)));
+    let mut output = curl(&config, timeout)?;
+    let newline = output
+        .iter()
+        .rposition(|&b| b == b'\n')
+        .ok_or_else(|| "No status in the response".to_owned())?;
+    let status = String::from_utf8_lossy(&output[newline + 1..]);
+    let status = status
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid status {status}"))?;
+    output.truncate(newline);
+    Ok((status, output))
+}
+
+/// Fetches and parses a JSON document
+pub fn get_json(url: &str, headers: &[String]) -> Result<serde_json::Value, String> {
+    let body = request("GET", url, headers, None)?;
+    serde_json::from_slice(&body).map_err(|e| format!("Invalid JSON from {url}: {e}"))
+}
+
+/// Returns a header of the response to a GET request, whatever its status
+pub fn response_header(url: &str, name: &str) -> Result<Option<String>, String> {
+    let output = curl(
+        &[
+            ("output", Some("/dev/null")),
+            ("dump-header", Some("-")),
+            ("url", Some(url)),
+        ],
+        TIMEOUT,
+    )?;
+    Ok(String::from_utf8_lossy(&output).lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_owned())
+    }))
+}
+
+/// Returns the Content-Length of the resource at `url` from a HEAD request, following redirects
+pub fn content_length(url: &str) -> Result<Option<u64>, String> {
+    let output = curl(
+        &[("head", None), ("location", None), ("url", Some(url))],
+        TIMEOUT,
+    )?;
+    let output = String::from_utf8_lossy(&output);
+    // Every redirect comes with its own headers, the last ones are of the resource
+    let lines: Vec<_> = output.lines().collect();
+    let start = lines
+        .iter()
+        .rposition(|line| line.starts_with("HTTP/"))
+        .unwrap_or_default();
+    Ok(lines[start..].iter().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.eq_ignore_ascii_case("content-length")
+            .then(|| value.trim().parse().ok())?
+    }))
+}
+
+/// Sends a GET request without following redirects and returns the status of the response and
+/// where it redirects to, if anywhere. Only fails when there is no response at all.
+pub fn status(url: &str) -> Result<(u16, Option<String>), String> {
+    let output = curl(
+        &[
+            ("output", Some("/dev/null")),
+            ("write-out", Some("%{http_code}\\n%{redirect_url}")),
+            ("url", Some(url)),
+        ],
+        TIMEOUT,
+    )?;
+    let output = String::from_utf8_lossy(&output);
+    let (status, location) = output.split_once('\n').unwrap_or((&output, ""));
+    let status = status
+        .trim()
+        .parse()
+        .map_err(|_| format!("Invalid status {status}"))?;
+    Ok((status, (!location.is_empty()).then(|| location.to_owned())))
+}
+
+/// Runs curl with the given options, giving up after `timeout` seconds, and returns what it wrote
+/// to stdout. The options are passed
+/// as a config file on stdin, since the command line of every process is readable by all users
+/// and URLs, headers and bodies may contain secrets.
+fn curl(options: &[(&str, Option<&str>)], timeout: u64) -> Result<Vec<u8>, String> {
+    let mut config = format!("silent\nshow-error\nmax-time = {timeout}\n");
+    for (option, value) in options {
+        config.push_str(option);
+        if let Some(value) = value {
+            config.push_str(" = ");
+            config.push_str(&quote(value));
+        }
+        config.push('\n');
+    }
+
+    let mut child = Command::new("curl")
+        .args(["--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run curl: {e}"))?;
+    // curl reads the whole config before doing anything, so this can't block on its output
+    let written = child
+        .stdin
+        .take()
+        .map(|mut stdin| stdin.write_all(config.as_bytes()));
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run curl: {e}"))?;
+    if let Some(Err(e)) = written {
+        return Err(format!("Failed to configure curl: {e}"));
+    }
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_owned());
+    }
+    Ok(output.stdout)
+}
+
+/// Percent-encodes a value for use in a URL path segment or query
+pub fn encode(value: &str) -> String {
+    let mut encoded = String::new();
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+            encoded.push(char::from(byte));
+        } else {
+            let _idc = write!(encoded, "%{byte:02X}");
+        }
+    }
+    encoded
+}
+
+/// Quotes a value for curl's config file syntax
+fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quote_escapes() {
+        assert_eq!(quote("X-Api-Key: secret"), r#""X-Api-Key: secret""#);
+        assert_eq!(quote(r#"{"a": "b\c"}"#), r#""{\"a\": \"b\\c\"}""#);
+        assert_eq!(quote("a\nb\tc"), r#""a\nb\tc""#);
+    }
+
+    #[test]
+    fn encode_reserved() {
+        assert_eq!(encode("group/project"), "group%2Fproject");
+        assert_eq!(encode("a b=ü~"), "a%20b%3D%C3%BC~");
+    }
+}
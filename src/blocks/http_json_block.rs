@@ -0,0 +1,143 @@
+//! Shows fields of a JSON document fetched from any REST endpoint
+
+use super::color::Color;
+use super::http;
+use super::{Block, BlockContext, I3Block, I3Event};
+use serde_json::Value;
+use std::str::FromStr as _;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// What to fetch and which fields to show. Fields are selected by JSON pointers like
+/// `/result/status/print_stats/state`.
+pub struct HttpJsonConfig {
+    pub url: String,
+    /// Sent with every request, like `Authorization: Bearer secret`
+    pub headers: Vec<String>,
+    /// Seconds between requests, at least one
+    pub interval: u64,
+    /// Shown in front of the fields
+    pub label: String,
+    /// Pointer to the text
+    pub text: Option<String>,
+    /// Pointer to a number shown after the text
+    pub value: Option<String>,
+    /// Pointer to a color like `#ff0000`, or to a boolean making the block critical when true
+    pub color: Option<String>,
+}
+
+pub struct HttpJsonBlock {
+    label: String,
+    /// Last fetched document, or why fetching it failed
+    document: Arc<RwLock<Option<Result<Fields, String>>>>,
+    /// Fetches the document again right away
+    fetch_channel: Mutex<Sender<()>>,
+}
+
+/// The selected fields of a document
+#[derive(PartialEq)]
+struct Fields {
+    text: Option<String>,
+    value: Option<String>,
+    color: Option<Color>,
+}
+
+impl Block for HttpJsonBlock {
+    fn render(&self) -> Option<I3Block> {
+        let document = self.document.read().unwrap();
+        match document.as_ref()? {
+            Ok(fields) => {
+                let parts: Vec<_> = fields.text.iter().chain(&fields.value).cloned().collect();
+                Some(I3Block {
+                    full_text: format!("{}{}", self.label, parts.join(" ")),
+                    color: fields.color,
+                    ..Default::default()
+                })
+            }
+            Err(e) => Some(I3Block {
+                full_text: format!("{}?", self.label),
+                color: Some(Color::critical()),
+                tooltip: Some(e.clone()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button == 1 {
+            let _idc = self.fetch_channel.lock().unwrap().send(());
+        }
+    }
+}
+
+impl HttpJsonBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext, config: HttpJsonConfig) -> Self {
+        let document = Arc::new(RwLock::new(None));
+        let (send, receive) = mpsc::channel::<()>();
+        let label = config.label.clone();
+        if config.interval == 0 {
+            // Would request the document in a tight loop
+            *document.write().unwrap() = Some(Err("The interval must be at least 1 s".to_owned()));
+            return Self {
+                label,
+                document,
+                fetch_channel: Mutex::new(send),
+            };
+        }
+
+        let document2 = Arc::clone(&document);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || loop {
+            let fields = Some(
+                http::get_json(&config.url, &config.headers)
+                    .map(|document| select(&config, &document)),
+            );
+            if *document2.read().unwrap() != fields {
+                *document2.write().unwrap() = fields;
+                ctx.request_update();
+            }
+
+            match receive.recv_timeout(Duration::from_secs(config.interval)) {
+                Ok(()) | Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        });
+
+        Self {
+            label,
+            document,
+            fetch_channel: Mutex::new(send),
+        }
+    }
+}
+
+/// Picks the configured fields from the document
+fn select(config: &HttpJsonConfig, document: &Value) -> Fields {
+    let field = |pointer: &Option<String>| {
+        pointer
+            .as_ref()
+            .and_then(|pointer| document.pointer(pointer))
+    };
+    Fields {
+        text: field(&config.text).map(|text| match text {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        }),
+        value: field(&config.value).and_then(|value| {
+            let value = value.as_f64()?;
+            // Whole numbers without a fraction, everything else rounded
+            Some(if value.fract() == 0.0 {
+                format!("{value:.0}")
+            } else {
+                format!("{value:.1}")
+            })
+        }),
+        color: field(&config.color).and_then(|color| match color {
+            Value::String(color) => Color::from_str(color).ok(),
+            Value::Bool(true) => Some(Color::critical()),
+            _ => None,
+        }),
+    }
+}
@@ -0,0 +1,82 @@
+//! Minimal i3/sway IPC client
+
+use std::io::{Read as _, Write as _};
+use std::os::unix::net::UnixStream;
+
+/// Magic string every message starts with
+const MAGIC: &[u8; 6] = b"i3-ipc";
+
+/// Message type for subscribing to events
+const SUBSCRIBE: u32 = 2;
+
+/// An open IPC connection
+pub struct I3Ipc {
+    stream: UnixStream,
+}
+
+impl I3Ipc {
+    /// Connects to the running i3 or sway
+    pub fn connect() -> std::io::Result<Self> {
+        let path = if let Some(path) =
+            std::env::var_os("I3SOCK").or_else(|| std::env::var_os("SWAYSOCK"))
+        {
+            path.into_string().unwrap_or_default()
+        } else {
+            let output = std::process::Command::new("i3")
+                .arg("--get-socketpath")
+                .output()?;
+            String::from_utf8_lossy(&output.stdout).trim().to_owned()
+        };
+        Ok(Self {
+            stream: UnixStream::connect(path)?,
+        })
+    }
+
+    /// Subscribes to the given events, e.g. `window`
+    pub fn subscribe(&mut self, events: &[&str]) -> std::io::Result<()> {
+        let payload = serde_json::to_vec(events)?;
+        self.send(SUBSCRIBE, &payload)?;
+        let (_, reply) = self.receive()?;
+        if reply.get("success").and_then(serde_json::Value::as_bool) == Some(true) {
+            Ok(())
+        } else {
+            Err(std::io::Error::other("subscription failed"))
+        }
+    }
+
+    /// Waits for the next event and returns its type (without the event bit) and payload
+    pub fn next_event(&mut self) -> std::io::Result<(u32, serde_json::Value)> {
+        loop {
+            let (kind, payload) = self.receive()?;
+            // Highest bit is set for events
+            if kind & 0x8000_0000 != 0 {
+                return Ok((kind & 0x7fff_ffff, payload));
+            }
+        }
+    }
+
+    /// Sends a message
+    fn send(&mut self, kind: u32, payload: &[u8]) -> std::io::Result<()> {
+        let len = u32::try_from(payload.len()).map_err(std::io::Error::other)?;
+        let mut msg = Vec::with_capacity(MAGIC.len() + 8 + payload.len());
+        msg.extend_from_slice(MAGIC);
+        msg.extend_from_slice(&len.to_ne_bytes());
+        msg.extend_from_slice(&kind.to_ne_bytes());
+        msg.extend_from_slice(payload);
+        self.stream.write_all(&msg)
+    }
+
+    /// Receives a message and parses its payload
+    fn receive(&mut self) -> std::io::Result<(u32, serde_json::Value)> {
+        let mut header = [0u8; 14];
+        self.stream.read_exact(&mut header)?;
+        if &header[..6] != MAGIC {
+            return Err(std::io::Error::other("invalid magic"));
+        }
+        let len = u32::from_ne_bytes([header[6], header[7], header[8], header[9]]);
+        let kind = u32::from_ne_bytes([header[10], header[11], header[12], header[13]]);
+        let mut payload = vec![0u8; len as usize];
+        self.stream.read_exact(&mut payload)?;
+        Ok((kind, serde_json::from_slice(&payload)?))
+    }
+}
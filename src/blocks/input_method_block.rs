@@ -0,0 +1,216 @@
+//! Shows the active input method engine of fcitx5 or ibus and cycles through the engines
+
+use super::diagnostics_block::report;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::process::Command;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use zbus::blocking::fdo::DBusProxy;
+use zbus::blocking::{Connection, Proxy};
+use zbus::names::BusName;
+
+/// How often to check the active engine in seconds. Neither fcitx5 nor ibus announce engine
+/// switches on the session bus, so switches by shortcut show up late, but clicks right away.
+const POLL_INTERVAL: u64 = 30;
+
+/// Bus name of fcitx5, which must not be activated just to ask for its engine
+const FCITX_NAME: &str = "org.fcitx.Fcitx5";
+
+/// Labels of engines by a part of their name
+const LABELS: [(&str, &str); 8] = [
+    ("pinyin", "拼"),
+    ("rime", "中"),
+    ("chewing", "注"),
+    ("mozc", "あ"),
+    ("anthy", "あ"),
+    ("kkc", "あ"),
+    ("hangul", "한"),
+    ("unikey", "VI"),
+];
+
+pub struct InputMethodBlock {
+    /// Name of the active engine
+    engine: Arc<RwLock<Option<String>>>,
+    /// Switches to the next engine
+    cycle_channel: Mutex<Sender<()>>,
+}
+
+impl Block for InputMethodBlock {
+    fn render(&self) -> Option<I3Block> {
+        let engine = self.engine.read().unwrap();
+        let engine = engine.as_ref()?;
+        Some(I3Block {
+            full_text: format!("⌨{}", label(engine)),
+            tooltip: Some(engine.clone()),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button == 1 {
+            let _idc = self.cycle_channel.lock().unwrap().send(());
+        }
+    }
+}
+
+impl InputMethodBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        let engine = Arc::new(RwLock::new(None));
+        let (send, receive) = mpsc::channel::<()>();
+
+        let engine2 = Arc::clone(&engine);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let fcitx = Connection::session().ok();
+            loop {
+                // Prefer fcitx5 and fall back to ibus, whichever is running
+                let fcitx_conn = fcitx.as_ref().filter(|conn| fcitx_running(conn));
+                let current = if let Some(conn) = fcitx_conn {
+                    fcitx_engine(conn).ok()
+                } else if ibus_running() {
+                    ibus_engine()
+                } else {
+                    None
+                };
+                if *engine2.read().unwrap() != current {
+                    *engine2.write().unwrap() = current;
+                    ctx.request_update();
+                }
+
+                match receive.recv_timeout(Duration::from_secs(POLL_INTERVAL)) {
+                    Ok(()) => {
+                        let result = if let Some(conn) = fcitx_conn {
+                            fcitx_cycle(conn).map_err(|e| e.to_string())
+                        } else {
+                            ibus_cycle()
+                        };
+                        if let Err(e) = result {
+                            report(format!("Failed to switch the input method: {e}"));
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => return,
+                }
+            }
+        });
+
+        Self {
+            engine,
+            cycle_channel: Mutex::new(send),
+        }
+    }
+}
+
+/// Returns a short label for an engine, e.g. "EN" for `keyboard-us` or "拼" for `pinyin`
+fn label(engine: &str) -> String {
+    if let Some((_, label)) = LABELS.iter().find(|(part, _)| engine.contains(part)) {
+        return (*label).to_owned();
+    }
+    // Keyboard layouts are called keyboard-us (fcitx5) or xkb:us::eng (ibus)
+    let layout = engine
+        .strip_prefix("keyboard-")
+        .or_else(|| engine.strip_prefix("xkb:"))
+        .unwrap_or(engine);
+    let layout = layout.split([':', '-']).next().unwrap_or(layout);
+    let layout = if layout == "us" || layout == "gb" {
+        "en"
+    } else {
+        layout
+    };
+    layout.chars().take(2).collect::<String>().to_uppercase()
+}
+
+/// Checks whether fcitx5 is running, without starting it through D-Bus activation
+fn fcitx_running(dbus_conn: &Connection) -> bool {
+    let Ok(proxy) = DBusProxy::new(dbus_conn) else {
+        return false;
+    };
+    BusName::try_from(FCITX_NAME).is_ok_and(|name| proxy.name_has_owner(name).unwrap_or(false))
+}
+
+/// Connects to the fcitx5 controller
+fn fcitx_proxy(dbus_conn: &Connection) -> zbus::Result<Proxy<'_>> {
+    Proxy::new(
+        dbus_conn,
+        FCITX_NAME,
+        "/controller",
+        "org.fcitx.Fcitx.Controller1",
+    )
+}
+
+/// Returns the active engine of fcitx5
+fn fcitx_engine(dbus_conn: &Connection) -> zbus::Result<String> {
+    fcitx_proxy(dbus_conn)?.call("CurrentInputMethod", &())
+}
+
+/// Switches fcitx5 to the next engine of the current group
+fn fcitx_cycle(dbus_conn: &Connection) -> zbus::Result<()> {
+    let proxy = fcitx_proxy(dbus_conn)?;
+    let current: String = proxy.call("CurrentInputMethod", &())?;
+    let group: String = proxy.call("CurrentInputMethodGroup", &())?;
+    // Engines come with their layouts
+    let (_layout, engines): (String, Vec<(String, String)>) =
+        proxy.call("InputMethodGroupInfo", &(group,))?;
+    let engines: Vec<_> = engines.into_iter().map(|(engine, _)| engine).collect();
+    if let Some(next) = next_engine(&engines, &current) {
+        proxy.call::<_, _, ()>("SetCurrentIM", &(next,))?;
+    }
+    Ok(())
+}
+
+/// Checks whether the ibus daemon is running, so the ibus command isn't spawned for nothing
+fn ibus_running() -> bool {
+    let Ok(dir) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+    dir.flatten().any(|entry| {
+        std::fs::read_to_string(entry.path().join("comm"))
+            .is_ok_and(|comm| comm.trim_end() == "ibus-daemon")
+    })
+}
+
+/// Returns the active engine of ibus
+fn ibus_engine() -> Option<String> {
+    let output = Command::new("ibus").arg("engine").output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let engine = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    (!engine.is_empty()).then_some(engine)
+}
+
+/// Switches ibus to the next preloaded engine
+fn ibus_cycle() -> Result<(), String> {
+    let current = ibus_engine().ok_or("ibus is not running")?;
+    // Prints a list like ['xkb:us::eng', 'libpinyin']
+    let output = Command::new("gsettings")
+        .args(["get", "org.freedesktop.ibus.general", "preload-engines"])
+        .output()
+        .map_err(|e| e.to_string())?;
+    let engines: Vec<_> = String::from_utf8_lossy(&output.stdout)
+        .split('\'')
+        .skip(1)
+        .step_by(2)
+        .map(ToOwned::to_owned)
+        .collect();
+    if let Some(next) = next_engine(&engines, &current) {
+        Command::new("ibus")
+            .args(["engine", next])
+            .status()
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Returns the engine after the current one, wrapping around
+fn next_engine<'a>(engines: &'a [String], current: &str) -> Option<&'a str> {
+    let index = engines
+        .iter()
+        .position(|engine| engine == current)
+        .map_or(0, |index| index + 1);
+    engines
+        .get(index % engines.len().max(1))
+        .map(String::as_str)
+}
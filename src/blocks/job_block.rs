@@ -0,0 +1,166 @@
+//! Shows long-running jobs registered by external scripts
+//!
+//! Jobs are registered by writing `start <name>` and `done <name>` lines to
+//! the socket, e.g. using `statusbar-ctl job start backup`.
+
+use super::diagnostics_block::report;
+use super::notify::Notification;
+use super::text::truncate_display;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::io::{BufRead as _, BufReader};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Frames of the spinner animation
+const SPINNER: [char; 8] = ['⣾', '⣽', '⣻', '⢿', '⡿', '⣟', '⣯', '⣷'];
+
+/// How long a client may stay silent before it's disconnected, in seconds
+const CLIENT_TIMEOUT: u64 = 10;
+
+/// How many columns the name of the shown job may take up
+const MAX_NAME_WIDTH: usize = 30;
+
+pub struct JobBlock {
+    jobs: Arc<RwLock<Vec<Job>>>,
+    /// Current frame of the spinner
+    frame: AtomicUsize,
+    /// Socket to remove on shutdown, if listening
+    socket: Option<PathBuf>,
+}
+
+struct Job {
+    name: String,
+    started: Instant,
+}
+
+impl Block for JobBlock {
+    fn render(&self) -> Option<I3Block> {
+        let jobs = self.jobs.read().unwrap();
+        let job = jobs.first()?;
+
+        let frame = self.frame.fetch_add(1, Ordering::Relaxed) % SPINNER.len();
+        let others = if jobs.len() > 1 {
+            format!(" +{}", jobs.len() - 1)
+        } else {
+            String::new()
+        };
+        Some(I3Block {
+            full_text: format!(
+                "{} {} {}{others}",
+                SPINNER[frame],
+                truncate_display(&job.name, MAX_NAME_WIDTH),
+                format_duration(job.started.elapsed().as_secs())
+            ),
+            tooltip: Some(
+                jobs.iter()
+                    .map(|job| {
+                        format!(
+                            "{} {}",
+                            job.name,
+                            format_duration(job.started.elapsed().as_secs())
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+
+    fn shutdown(&self) {
+        if let Some(socket) = &self.socket {
+            let _idc = std::fs::remove_file(socket);
+        }
+    }
+}
+
+impl JobBlock {
+    /// Creates the block listening on `socket`. A notification is shown when a job is done if
+    /// `notify` is set.
+    #[must_use]
+    pub fn new(ctx: &BlockContext, socket: Option<PathBuf>, notify: bool) -> Self {
+        let mut ret = Self {
+            jobs: Arc::new(RwLock::new(vec![])),
+            frame: AtomicUsize::new(0),
+            socket: None,
+        };
+        let Some(socket) = socket else {
+            return ret;
+        };
+
+        // Remove stale socket from previous runs
+        let _idc = std::fs::remove_file(&socket);
+        let listener = match UnixListener::bind(&socket) {
+            Ok(listener) => listener,
+            Err(e) => {
+                report(format!("Failed to listen on {}: {e}", socket.display()));
+                return ret;
+            }
+        };
+        ret.socket = Some(socket);
+
+        let jobs = Arc::clone(&ret.jobs);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming().map_while(Result::ok) {
+                // A client that stalls must not hold up the others
+                let jobs = Arc::clone(&jobs);
+                let ctx = ctx.clone();
+                std::thread::spawn(move || handle_client(stream, &jobs, notify, &ctx));
+            }
+        });
+
+        ret
+    }
+}
+
+/// Applies the commands a client sends until it disconnects or goes quiet
+fn handle_client(stream: UnixStream, jobs: &RwLock<Vec<Job>>, notify: bool, ctx: &BlockContext) {
+    if let Err(e) = stream.set_read_timeout(Some(Duration::from_secs(CLIENT_TIMEOUT))) {
+        report(format!("Failed to set a timeout on a job client: {e}"));
+        return;
+    }
+    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+        let mut jobs = jobs.write().unwrap();
+        if let Some(name) = line.strip_prefix("start ") {
+            jobs.push(Job {
+                name: name.to_owned(),
+                started: Instant::now(),
+            });
+        } else if let Some(name) = line.strip_prefix("done ") {
+            let Some(index) = jobs.iter().position(|job| job.name == name) else {
+                continue;
+            };
+            let job = jobs.remove(index);
+            if notify {
+                Notification {
+                    summary: format!("{} done", job.name),
+                    body: format!(
+                        "Finished after {}",
+                        format_duration(job.started.elapsed().as_secs())
+                    ),
+                    ..Default::default()
+                }
+                .show();
+            }
+        } else {
+            report(format!("Invalid job command: {line}"));
+            continue;
+        }
+        ctx.request_update();
+    }
+}
+
+/// Formats seconds as hours and minutes, or minutes and seconds
+fn format_duration(secs: u64) -> String {
+    if secs >= 3600 {
+        format!("{}h{:02}", secs / 3600, secs / 60 % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
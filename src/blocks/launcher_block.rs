@@ -0,0 +1,42 @@
+//! Launches a command when clicked
+
+use super::diagnostics_block::report;
+use super::{Block, I3Block, I3Event};
+use std::process::Command;
+
+pub struct LauncherBlock {
+    /// What is displayed
+    icon: String,
+    /// Shell command that is run on left click
+    command: String,
+}
+
+impl Block for LauncherBlock {
+    fn render(&self) -> Option<I3Block> {
+        Some(I3Block {
+            full_text: self.icon.clone(),
+            instance: Some(self.icon.clone()),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button != 1 {
+            return;
+        }
+        let command = self.command.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = Command::new("sh").arg("-c").arg(&command).status() {
+                report(format!("Failed to launch {command}: {e}"));
+            }
+        });
+    }
+}
+
+impl LauncherBlock {
+    /// Creates a block showing `icon` that runs `command` using `sh`
+    #[must_use]
+    pub fn new(icon: String, command: String) -> Self {
+        Self { icon, command }
+    }
+}
@@ -1,3 +1,4 @@
+use super::color::Color;
 use super::{Block, I3Block, I3Event};
 use std::fs::File;
 use std::io::{Read as _, Seek as _, SeekFrom};
@@ -8,13 +9,27 @@ pub struct LoadBlock {
     load_file: Option<Mutex<File>>,
     /// Number of parallel threads
     num_threads: Option<usize>,
+    /// Display the load divided by the number of threads
+    per_core: bool,
 }
 
 impl LoadBlock {
+    /// Creates the block, optionally displaying the load per core
+    #[must_use]
+    pub fn new(per_core: bool) -> Self {
+        Self {
+            load_file: File::open("/proc/loadavg").ok().map(Mutex::new),
+            num_threads: std::thread::available_parallelism()
+                .map(std::num::NonZeroUsize::get)
+                .ok(),
+            per_core,
+        }
+    }
+
     fn err() -> I3Block {
         I3Block {
             full_text: "ERROR".to_owned(),
-            color: Some("#ff0202".to_owned()),
+            color: Some(Color::critical()),
             ..Default::default()
         }
     }
@@ -41,22 +56,30 @@ impl Block for LoadBlock {
                 return Some(Self::err());
             };
 
-            let color = if let Some(num_threads) = self.num_threads {
-                #[allow(clippy::cast_precision_loss)] // Who cares
-                if load1 / num_threads as f32 > 1.0 {
-                    Some("#ff0202".to_owned())
-                } else {
-                    None
-                }
+            #[allow(clippy::cast_precision_loss)] // Who cares
+            let per_core = self
+                .num_threads
+                .map(|num_threads| load1 / num_threads as f32);
+
+            let color = if per_core.is_some_and(|load| load > 1.0) {
+                Some(Color::critical())
             } else {
                 None
             };
 
-            Some(I3Block {
-                full_text: format!("{load1:.02}"),
-                color,
-                ..Default::default()
-            })
+            let (full_text, template) = match per_core {
+                Some(load) if self.per_core => (format!("{load:.02}/core"), "0.00/core"),
+                _ => (format!("{load1:.02}"), "00.00"),
+            };
+
+            Some(
+                I3Block {
+                    full_text,
+                    color,
+                    ..Default::default()
+                }
+                .fixed_width(template),
+            )
         } else {
             None
         }
@@ -67,11 +90,6 @@ impl Block for LoadBlock {
 
 impl Default for LoadBlock {
     fn default() -> Self {
-        Self {
-            load_file: File::open("/proc/loadavg").ok().map(Mutex::new),
-            num_threads: std::thread::available_parallelism()
-                .map(std::num::NonZeroUsize::get)
-                .ok(),
-        }
+        Self::new(false)
     }
 }
@@ -0,0 +1,102 @@
+//! Shows which applications are using the location through `GeoClue`
+
+use super::color::Color;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::sync::{Arc, RwLock};
+use zbus::blocking::{Connection, MessageIterator, Proxy};
+use zbus::message::Type;
+use zbus::MatchRule;
+
+/// Where `GeoClue` exports its clients
+const CLIENT_PATH: &str = "/org/freedesktop/GeoClue2/Client";
+
+pub struct LocationBlock {
+    /// Desktop IDs of the applications with an active location session
+    apps: Arc<RwLock<Vec<String>>>,
+}
+
+impl Block for LocationBlock {
+    fn render(&self) -> Option<I3Block> {
+        let apps = self.apps.read().unwrap();
+        if apps.is_empty() {
+            return None;
+        }
+        Some(I3Block {
+            full_text: "📍".to_owned(),
+            color: Some(Color::critical()),
+            tooltip: Some(format!("Location used by\n{}", apps.join("\n"))),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+}
+
+impl LocationBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        let apps = Arc::new(RwLock::new(vec![]));
+
+        let apps2 = Arc::clone(&apps);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let Ok(dbus_conn) = Connection::system() else {
+                return;
+            };
+            // Clients starting and stopping change their Active property
+            let Ok(rule) = MatchRule::builder()
+                .msg_type(Type::Signal)
+                .sender("org.freedesktop.GeoClue2")
+                .map(zbus::match_rule::Builder::build)
+            else {
+                return;
+            };
+            let Ok(messages) = MessageIterator::for_match_rule(rule, &dbus_conn, None) else {
+                return;
+            };
+
+            *apps2.write().unwrap() = active_clients(&dbus_conn).unwrap_or_default();
+            ctx.request_update();
+            for _ in messages {
+                *apps2.write().unwrap() = active_clients(&dbus_conn).unwrap_or_default();
+                ctx.request_update();
+            }
+        });
+
+        Self { apps }
+    }
+}
+
+/// Returns the desktop IDs of all clients with an active location session
+fn active_clients(dbus_conn: &Connection) -> zbus::Result<Vec<String>> {
+    // There is no method listing the clients, so they are found by introspecting
+    let introspectable = Proxy::new(
+        dbus_conn,
+        "org.freedesktop.GeoClue2",
+        CLIENT_PATH,
+        "org.freedesktop.DBus.Introspectable",
+    )?;
+    let xml: String = introspectable.call("Introspect", &())?;
+
+    let mut apps = vec![];
+    for node in xml.split("<node name=\"").skip(1) {
+        let Some((name, _)) = node.split_once('"') else {
+            continue;
+        };
+        let client = Proxy::new(
+            dbus_conn,
+            "org.freedesktop.GeoClue2",
+            format!("{CLIENT_PATH}/{name}"),
+            "org.freedesktop.GeoClue2.Client",
+        )?;
+        if client.get_property::<bool>("Active").unwrap_or_default() {
+            let app = client
+                .get_property::<String>("DesktopId")
+                .unwrap_or_default();
+            if !app.is_empty() && !apps.contains(&app) {
+                apps.push(app);
+            }
+        }
+    }
+    Ok(apps)
+}
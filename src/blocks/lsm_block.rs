@@ -0,0 +1,35 @@
+//! Warns when the Linux security modules are not enforcing
+
+use super::color::Color;
+use super::simple_poll_block::{read_value, SimplePollBlock};
+use super::I3Block;
+use std::time::Duration;
+
+/// How often to check the modules in seconds
+const POLL_INTERVAL: u64 = 30;
+
+pub type LsmBlock = SimplePollBlock<&'static str>;
+
+#[must_use]
+pub fn new() -> LsmBlock {
+    SimplePollBlock::new(Duration::from_secs(POLL_INTERVAL), poll, |problem| {
+        I3Block {
+            full_text: (*problem).to_owned(),
+            color: Some(Color::critical()),
+            ..Default::default()
+        }
+    })
+}
+
+/// Returns what is not enforcing, if anything
+fn poll() -> Option<&'static str> {
+    if read_value::<u8>("/sys/fs/selinux/enforce") == Some(0) {
+        Some("SELinux permissive")
+    } else if read_value::<String>("/sys/module/apparmor/parameters/enabled").as_deref()
+        == Some("N")
+    {
+        Some("AppArmor disabled")
+    } else {
+        None
+    }
+}
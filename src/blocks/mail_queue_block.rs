@@ -0,0 +1,60 @@
+//! Shows the number of mails stuck in the local postfix queue
+
+use super::color::Color;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// How often to check the queue in seconds
+const POLL_INTERVAL: u64 = 60;
+
+pub struct MailQueueBlock {
+    /// Number of queued messages
+    queued: Arc<AtomicUsize>,
+}
+
+impl Block for MailQueueBlock {
+    fn render(&self) -> Option<I3Block> {
+        let queued = self.queued.load(Ordering::Relaxed);
+        if queued == 0 {
+            return None;
+        }
+        Some(I3Block {
+            full_text: format!("✉{queued}"),
+            color: Some(Color::critical()),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+}
+
+impl MailQueueBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        let queued = Arc::new(AtomicUsize::new(0));
+
+        // Poll in the background since postqueue may take a while
+        let queued2 = Arc::clone(&queued);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || loop {
+            // postqueue prints one JSON object per queued message
+            let Ok(output) = std::process::Command::new("postqueue").arg("-j").output() else {
+                // No postfix installed
+                return;
+            };
+            let count = output
+                .stdout
+                .split(|c| *c == b'\n')
+                .filter(|line| !line.is_empty())
+                .count();
+            if queued2.swap(count, Ordering::Relaxed) != count {
+                ctx.request_update();
+            }
+            std::thread::sleep(Duration::from_secs(POLL_INTERVAL));
+        });
+
+        Self { queued }
+    }
+}
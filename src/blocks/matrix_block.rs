@@ -0,0 +1,436 @@
+//! Counts unread mentions across Matrix rooms using the client-server sync API
+//!
+//! The config file is JSON like
+//! `{"homeserver": "https://matrix.org", "access_token": "…", "refresh_token": "…"}`, the refresh
+//! token is optional. Refreshed tokens are kept in the state directory, since the homeserver
+//! invalidates the old ones.
+
+use super::color::Color;
+use super::diagnostics_block::report;
+use super::http;
+use super::{state_file, Block, BlockContext, I3Block, I3Event};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::os::unix::fs::OpenOptionsExt as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// How long the homeserver may hold a sync request when nothing happens, in seconds
+const SYNC_TIMEOUT: u64 = 30;
+
+/// First and longest wait after a failed sync in seconds, doubling in between
+const MIN_BACKOFF: u64 = 5;
+const MAX_BACKOFF: u64 = 300;
+
+/// Only what's needed for counting mentions and naming rooms
+const FILTER: &str = r#"{"presence":{"types":[]},"account_data":{"types":[]},"room":{"ephemeral":{"types":[]},"account_data":{"types":[]},"state":{"types":["m.room.name","m.room.canonical_alias"],"lazy_load_members":true},"timeline":{"limit":5,"types":["m.room.name","m.room.canonical_alias"]}}}"#;
+
+#[derive(Clone, Deserialize, Serialize)]
+struct Tokens {
+    access_token: String,
+    refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MatrixConfig {
+    homeserver: String,
+    #[serde(flatten)]
+    tokens: Tokens,
+}
+
+/// Tokens in the state file, along with the refresh token of the config they were refreshed from
+#[derive(Deserialize, Serialize)]
+struct RefreshedTokens {
+    configured: Option<String>,
+    #[serde(flatten)]
+    tokens: Tokens,
+}
+
+/// What is known about a joined room
+#[derive(Default)]
+struct Room {
+    name: Option<String>,
+    alias: Option<String>,
+    /// Users shown in place of a name, like for direct messages
+    heroes: Vec<String>,
+    mentions: u64,
+}
+
+impl Room {
+    fn display_name(&self, id: &str) -> String {
+        if let Some(name) = self.name.as_ref().or(self.alias.as_ref()) {
+            return name.clone();
+        }
+        if self.heroes.is_empty() {
+            id.to_owned()
+        } else {
+            self.heroes.join(", ")
+        }
+    }
+}
+
+/// Names of rooms with unread mentions and their counts
+type Mentions = Vec<(String, u64)>;
+
+/// Why a sync failed
+enum SyncError {
+    /// Rate limited, try again after the given time
+    Limited(Duration),
+    Failed(String),
+}
+
+pub struct MatrixBlock {
+    /// Rooms with unread mentions, or why syncing failed
+    unread: Arc<RwLock<Option<Result<Mentions, String>>>>,
+}
+
+impl Block for MatrixBlock {
+    fn render(&self) -> Option<I3Block> {
+        let unread = self.unread.read().unwrap();
+        match unread.as_ref()? {
+            Ok(rooms) => {
+                let total: u64 = rooms.iter().map(|(_, mentions)| mentions).sum();
+                if total == 0 {
+                    return None;
+                }
+                let mut tooltip = format!("{total} unread mentions");
+                for (name, mentions) in rooms {
+                    let _idc = write!(tooltip, "\n{name}: {mentions}");
+                }
+                Some(I3Block {
+                    full_text: format!("💬{total}"),
+                    tooltip: Some(tooltip),
+                    ..Default::default()
+                })
+            }
+            Err(e) => Some(I3Block {
+                full_text: "💬?".to_owned(),
+                color: Some(Color::critical()),
+                tooltip: Some(e.clone()),
+                ..Default::default()
+            }),
+        }
+    }
+
+    fn click(&self, _: &I3Event) {}
+}
+
+impl MatrixBlock {
+    /// Creates the block for the account in the JSON config file at `config`
+    #[must_use]
+    pub fn new(ctx: &BlockContext, config: &Path) -> Self {
+        let unread = Arc::new(RwLock::new(None));
+        let config = std::fs::read_to_string(config)
+            .map_err(|e| format!("Failed to read {}: {e}", config.display()))
+            .and_then(|config| {
+                serde_json::from_str::<MatrixConfig>(&config)
+                    .map_err(|e| format!("Invalid Matrix config: {e}"))
+            });
+        let config = match config {
+            Ok(config) => config,
+            Err(e) => {
+                *unread.write().unwrap() = Some(Err(e));
+                return Self { unread };
+            }
+        };
+
+        let unread2 = Arc::clone(&unread);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let mut session = Session::new(config);
+            let mut backoff = MIN_BACKOFF;
+            loop {
+                let current = match session.sync() {
+                    Ok(()) => {
+                        backoff = MIN_BACKOFF;
+                        Some(Ok(session.unread()))
+                    }
+                    Err(SyncError::Limited(wait)) => {
+                        std::thread::sleep(wait);
+                        continue;
+                    }
+                    Err(SyncError::Failed(e)) => Some(Err(e)),
+                };
+                let failed = matches!(current, Some(Err(_)));
+                if *unread2.read().unwrap() != current {
+                    *unread2.write().unwrap() = current;
+                    ctx.request_update();
+                }
+                if failed {
+                    std::thread::sleep(Duration::from_secs(backoff));
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            }
+        });
+
+        Self { unread }
+    }
+}
+
+/// A logged in client following the sync stream
+struct Session {
+    homeserver: String,
+    tokens: Tokens,
+    /// Refresh token of the config, to tell whether refreshed tokens belong to it
+    configured: Option<String>,
+    state_file: Option<PathBuf>,
+    /// Where the last sync ended, none before the first one
+    since: Option<String>,
+    rooms: HashMap<String, Room>,
+}
+
+impl Session {
+    fn new(config: MatrixConfig) -> Self {
+        let state_file = state_file("matrix");
+        let configured = config.tokens.refresh_token.clone();
+        // Refreshed tokens only replace the configured ones as long as the config is unchanged
+        let tokens = state_file
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|state| serde_json::from_str::<RefreshedTokens>(&state).ok())
+            .filter(|state| state.configured == configured)
+            .map_or(config.tokens, |state| state.tokens);
+        Self {
+            homeserver: config.homeserver.trim_end_matches('/').to_owned(),
+            tokens,
+            configured,
+            state_file,
+            since: None,
+            rooms: HashMap::new(),
+        }
+    }
+
+    /// Waits for the next batch of changes and applies it, refreshing the access token if needed
+    fn sync(&mut self) -> Result<(), SyncError> {
+        let mut url = format!(
+            "{}/_matrix/client/v3/sync?filter={}",
+            self.homeserver,
+            http::encode(FILTER)
+        );
+        // The first sync returns right away with the current state of every room
+        let timeout = if let Some(since) = &self.since {
+            let _idc = write!(
+                url,
+                "&since={}&timeout={}",
+                http::encode(since),
+                SYNC_TIMEOUT * 1000
+            );
+            SYNC_TIMEOUT + 10
+        } else {
+            SYNC_TIMEOUT
+        };
+
+        let response = self.send("GET", &url, None, timeout)?;
+        self.since = response
+            .get("next_batch")
+            .and_then(Value::as_str)
+            .map(str::to_owned);
+        apply(&mut self.rooms, &response);
+        Ok(())
+    }
+
+    /// Sends an authenticated request and returns the parsed response. Expired access tokens are
+    /// refreshed and the request repeated.
+    fn send(
+        &mut self,
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+        timeout: u64,
+    ) -> Result<Value, SyncError> {
+        let mut refreshed = false;
+        loop {
+            let headers = [format!(
+                "Authorization: Bearer {}",
+                self.tokens.access_token
+            )];
+            let (status, body) = http::request_status(method, url, &headers, body, timeout)
+                .map_err(SyncError::Failed)?;
+            let response: Value = serde_json::from_slice(&body).unwrap_or_default();
+            if status == 200 {
+                return Ok(response);
+            }
+            let errcode = response
+                .get("errcode")
+                .and_then(Value::as_str)
+                .unwrap_or_default();
+            match errcode {
+                "M_LIMIT_EXCEEDED" => {
+                    let wait = response
+                        .get("retry_after_ms")
+                        .and_then(Value::as_u64)
+                        .unwrap_or(MIN_BACKOFF * 1000);
+                    return Err(SyncError::Limited(Duration::from_millis(wait)));
+                }
+                "M_UNKNOWN_TOKEN" if !refreshed && self.tokens.refresh_token.is_some() => {
+                    self.refresh().map_err(SyncError::Failed)?;
+                    refreshed = true;
+                }
+                _ => {
+                    let error = response
+                        .get("error")
+                        .and_then(Value::as_str)
+                        .unwrap_or(errcode);
+                    return Err(SyncError::Failed(format!("Matrix error {status}: {error}")));
+                }
+            }
+        }
+    }
+
+    /// Trades the refresh token for new tokens and saves them
+    fn refresh(&mut self) -> Result<(), String> {
+        let body = json!({ "refresh_token": self.tokens.refresh_token }).to_string();
+        let url = format!("{}/_matrix/client/v3/refresh", self.homeserver);
+        let (status, response) = http::request_status("POST", &url, &[], Some(&body), 10)?;
+        if status != 200 {
+            return Err(format!(
+                "Failed to refresh the Matrix access token, status {status}"
+            ));
+        }
+        let refreshed: Tokens = serde_json::from_slice(&response)
+            .map_err(|e| format!("Invalid refresh response: {e}"))?;
+        self.tokens = Tokens {
+            access_token: refreshed.access_token,
+            // The homeserver may keep the refresh token as it is
+            refresh_token: refreshed.refresh_token.or(self.tokens.refresh_token.take()),
+        };
+        self.save();
+        Ok(())
+    }
+
+    /// Writes the current tokens to the state file, readable only by us
+    fn save(&self) {
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        let state = RefreshedTokens {
+            configured: self.configured.clone(),
+            tokens: self.tokens.clone(),
+        };
+        let result = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .and_then(|mut file| file.write_all(json!(state).to_string().as_bytes()));
+        if let Err(e) = result {
+            report(format!("Failed to save the Matrix tokens: {e}"));
+        }
+    }
+
+    /// Returns the names and mention counts of rooms with mentions, most mentions first
+    fn unread(&self) -> Mentions {
+        let mut unread: Vec<_> = self
+            .rooms
+            .iter()
+            .filter(|(_, room)| room.mentions > 0)
+            .map(|(id, room)| (room.display_name(id), room.mentions))
+            .collect();
+        unread.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        unread
+    }
+}
+
+/// Updates the rooms with a sync response
+fn apply(rooms: &mut HashMap<String, Room>, response: &Value) {
+    if let Some(joined) = response.pointer("/rooms/join").and_then(Value::as_object) {
+        for (id, update) in joined {
+            let room = rooms.entry(id.clone()).or_default();
+            if let Some(mentions) = update
+                .pointer("/unread_notifications/highlight_count")
+                .and_then(Value::as_u64)
+            {
+                room.mentions = mentions;
+            }
+            if let Some(heroes) = update
+                .pointer("/summary/m.heroes")
+                .and_then(Value::as_array)
+            {
+                room.heroes = heroes
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(str::to_owned)
+                    .collect();
+            }
+            // Later events replace earlier ones, and the timeline follows the state
+            let events = ["/state/events", "/timeline/events"]
+                .into_iter()
+                .filter_map(|pointer| update.pointer(pointer).and_then(Value::as_array))
+                .flatten();
+            for event in events {
+                let content = |key| {
+                    event
+                        .pointer(key)
+                        .and_then(Value::as_str)
+                        .filter(|value| !value.is_empty())
+                        .map(str::to_owned)
+                };
+                match event.get("type").and_then(Value::as_str) {
+                    Some("m.room.name") => room.name = content("/content/name"),
+                    Some("m.room.canonical_alias") => room.alias = content("/content/alias"),
+                    _ => {}
+                }
+            }
+        }
+    }
+    if let Some(left) = response.pointer("/rooms/leave").and_then(Value::as_object) {
+        for id in left.keys() {
+            rooms.remove(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_mentions_by_room() {
+        let mut session = Session {
+            homeserver: String::new(),
+            tokens: Tokens {
+                access_token: String::new(),
+                refresh_token: None,
+            },
+            configured: None,
+            state_file: None,
+            since: None,
+            rooms: HashMap::new(),
+        };
+        apply(
+            &mut session.rooms,
+            &json!({"rooms": {"join": {
+                "!a:x": {
+                    "unread_notifications": {"highlight_count": 2},
+                    "state": {"events": [{"type": "m.room.name", "content": {"name": "Ops"}}]}
+                },
+                "!b:x": {
+                    "unread_notifications": {"highlight_count": 3},
+                    "summary": {"m.heroes": ["@bob:x"]}
+                },
+                "!c:x": {"unread_notifications": {"highlight_count": 0}}
+            }}}),
+        );
+        assert_eq!(
+            session.unread(),
+            [("@bob:x".to_owned(), 3), ("Ops".to_owned(), 2)]
+        );
+
+        // Renamed in the timeline, the other room read and left
+        apply(
+            &mut session.rooms,
+            &json!({"rooms": {
+                "join": {"!a:x": {
+                    "timeline": {"events": [{"type": "m.room.name", "content": {"name": "Infra"}}]}
+                }},
+                "leave": {"!b:x": {}}
+            }}),
+        );
+        assert_eq!(session.unread(), [("Infra".to_owned(), 2)]);
+    }
+}
@@ -0,0 +1,180 @@
+//! Shows the available memory, the used memory or the swap usage including zram
+
+use super::color::Color;
+use super::units::format_bytes;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::fmt::Write as _;
+use std::sync::Mutex;
+
+/// Below this percentage of available memory, the block is colored
+const AVAILABLE_CRITICAL: u64 = 10;
+
+/// Above this percentage of used swap, the swap usage is shown in every mode
+const SWAP_SHOWN: u64 = 10;
+
+/// What the block shows, cycled by clicking
+#[derive(Clone, Copy, Default)]
+enum Mode {
+    #[default]
+    Available,
+    Used,
+    Swap,
+}
+
+pub struct MemoryBlock {
+    mode: Mutex<Mode>,
+    ctx: BlockContext,
+}
+
+/// Sizes from `/proc/meminfo` in bytes
+#[derive(Default)]
+struct MemInfo {
+    total: u64,
+    available: u64,
+    swap_total: u64,
+    swap_free: u64,
+}
+
+/// Sizes of all zram devices in bytes
+#[derive(Default)]
+struct Zram {
+    /// Uncompressed size of the stored data
+    original: u64,
+    /// Memory used for it, including overhead
+    used: u64,
+}
+
+impl Block for MemoryBlock {
+    fn render(&self) -> Option<I3Block> {
+        let info = read_meminfo()?;
+        let zram = read_zram();
+        let used = info.total.saturating_sub(info.available);
+        let swap_used = info.swap_total.saturating_sub(info.swap_free);
+        let swap_percent = swap_used * 100 / info.swap_total.max(1);
+
+        let mode = *self.mode.lock().unwrap();
+        let mut full_text = match mode {
+            Mode::Available => format!("🧠{}", format_bytes(info.available)),
+            Mode::Used => format!("🧠{} used", format_bytes(used)),
+            Mode::Swap => format!(
+                "🧠swap {}/{}",
+                format_bytes(swap_used),
+                format_bytes(info.swap_total)
+            ),
+        };
+        if !matches!(mode, Mode::Swap) && swap_percent >= SWAP_SHOWN {
+            let _idc = write!(full_text, " swap {swap_percent}%");
+        }
+
+        let mut tooltip = format!(
+            "{} available, {} used of {}",
+            format_bytes(info.available),
+            format_bytes(used),
+            format_bytes(info.total)
+        );
+        if info.swap_total > 0 {
+            let _idc = write!(
+                tooltip,
+                "\nSwap: {} used of {}",
+                format_bytes(swap_used),
+                format_bytes(info.swap_total)
+            );
+        }
+        if let Some(zram) = zram.filter(|zram| zram.used > 0) {
+            #[allow(clippy::cast_precision_loss)] // Who cares
+            let ratio = zram.original as f64 / zram.used as f64;
+            let _idc = write!(
+                tooltip,
+                "\nzram: {} stored in {} ({ratio:.1}x)",
+                format_bytes(zram.original),
+                format_bytes(zram.used)
+            );
+        }
+        tooltip.push_str("\nClick to switch between available, used and swap");
+
+        Some(I3Block {
+            full_text,
+            color: if info.available * 100 < info.total * AVAILABLE_CRITICAL {
+                Some(Color::critical())
+            } else {
+                None
+            },
+            tooltip: Some(tooltip),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button != 1 {
+            return;
+        }
+        let mut mode = self.mode.lock().unwrap();
+        *mode = match *mode {
+            Mode::Available => Mode::Used,
+            Mode::Used => Mode::Swap,
+            Mode::Swap => Mode::Available,
+        };
+        self.ctx.request_update();
+    }
+}
+
+impl MemoryBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        Self {
+            mode: Mutex::default(),
+            ctx: ctx.clone(),
+        }
+    }
+}
+
+/// Reads the memory and swap sizes
+fn read_meminfo() -> Option<MemInfo> {
+    // Lines like "MemAvailable:   12345678 kB"
+    let contents = std::fs::read_to_string("/proc/meminfo").ok()?;
+    let mut info = MemInfo::default();
+    for line in contents.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let Some(kib) = value
+            .split_whitespace()
+            .next()
+            .and_then(|kib| kib.parse::<u64>().ok())
+        else {
+            continue;
+        };
+        match key {
+            "MemTotal" => info.total = kib * 1024,
+            "MemAvailable" => info.available = kib * 1024,
+            "SwapTotal" => info.swap_total = kib * 1024,
+            "SwapFree" => info.swap_free = kib * 1024,
+            _ => {}
+        }
+    }
+    (info.total > 0).then_some(info)
+}
+
+/// Sums up the sizes of all zram devices, if there are any
+fn read_zram() -> Option<Zram> {
+    let mut zram: Option<Zram> = None;
+    for entry in std::fs::read_dir("/sys/block").ok()?.flatten() {
+        if !entry.file_name().to_string_lossy().starts_with("zram") {
+            continue;
+        }
+        // Starts with the original data size, the compressed data size and the used memory
+        let Ok(stat) = std::fs::read_to_string(entry.path().join("mm_stat")) else {
+            continue;
+        };
+        let fields: Vec<u64> = stat
+            .split_whitespace()
+            .filter_map(|field| field.parse().ok())
+            .collect();
+        if let [original, _, used, ..] = fields[..] {
+            let zram = zram.get_or_insert_with(Zram::default);
+            zram.original += original;
+            zram.used += used;
+        }
+    }
+    zram
+}
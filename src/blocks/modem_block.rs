@@ -0,0 +1,198 @@
+//! Shows the state of the first WWAN modem using `ModemManager`
+
+use super::color::Color;
+use super::diagnostics_block::report;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+
+/// How often to ask `ModemManager` for the state in seconds
+const POLL_INTERVAL: u64 = 10;
+
+/// Modems in this state or above are enabled
+const MM_MODEM_STATE_ENABLED: i32 = 6;
+
+/// Registered in a foreign network
+const MM_MODEM_3GPP_REGISTRATION_STATE_ROAMING: u32 = 5;
+
+/// Access technology bits, best first
+const ACCESS_TECHNOLOGIES: [(u32, &str); 4] = [
+    (1 << 15, "5G"),
+    (1 << 14, "LTE"),
+    // UMTS, HSDPA, HSUPA, HSPA, HSPA+
+    (0b111_1110_0000, "3G"),
+    // GSM, GSM compact, GPRS, EDGE
+    (0b1_1110, "2G"),
+];
+
+pub struct ModemBlock {
+    state: Arc<RwLock<Option<ModemState>>>,
+    /// Enables or disables the modem
+    toggle_channel: Mutex<Sender<()>>,
+}
+
+/// What `ModemManager` knows about the modem
+struct ModemState {
+    enabled: bool,
+    /// Signal quality in percent
+    signal: u32,
+    technology: Option<&'static str>,
+    operator: Option<String>,
+    roaming: bool,
+}
+
+impl Block for ModemBlock {
+    fn render(&self) -> Option<I3Block> {
+        let state = self.state.read().unwrap();
+        let state = state.as_ref()?;
+        if !state.enabled {
+            return Some(I3Block {
+                full_text: "📡off".to_owned(),
+                ..Default::default()
+            });
+        }
+
+        let mut full_text = format!("📡{}%", state.signal);
+        if let Some(technology) = state.technology {
+            let _idc = write!(full_text, " {technology}");
+        }
+        if let Some(operator) = &state.operator {
+            let _idc = write!(full_text, " {operator}");
+        }
+        if state.roaming {
+            full_text.push_str(" (R)");
+        }
+        Some(I3Block {
+            full_text,
+            color: if state.roaming {
+                Some(Color::critical())
+            } else {
+                None
+            },
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button == 3 {
+            let _idc = self.toggle_channel.lock().unwrap().send(());
+        }
+    }
+}
+
+impl ModemBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        let state = Arc::new(RwLock::new(None::<ModemState>));
+        let (send, receive) = mpsc::channel::<()>();
+
+        let state2 = Arc::clone(&state);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let Ok(dbus_conn) = Connection::system() else {
+                return;
+            };
+            let mut toggle = false;
+            loop {
+                let new_state = if let Ok(Some(modem)) = first_modem(&dbus_conn) {
+                    if toggle {
+                        let enabled = state2
+                            .read()
+                            .unwrap()
+                            .as_ref()
+                            .is_some_and(|state| state.enabled);
+                        if let Err(e) = enable(&dbus_conn, &modem, !enabled) {
+                            report(format!("Failed to toggle the modem: {e}"));
+                        }
+                    }
+                    read_state(&dbus_conn, &modem).ok()
+                } else {
+                    None
+                };
+                *state2.write().unwrap() = new_state;
+                ctx.request_update();
+
+                toggle = match receive.recv_timeout(Duration::from_secs(POLL_INTERVAL)) {
+                    Ok(()) => true,
+                    Err(RecvTimeoutError::Timeout) => false,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                };
+            }
+        });
+
+        Self {
+            state,
+            toggle_channel: Mutex::new(send),
+        }
+    }
+}
+
+/// Returns the path of the first modem, if any
+fn first_modem(dbus_conn: &Connection) -> zbus::Result<Option<OwnedObjectPath>> {
+    let object_manager = Proxy::new(
+        dbus_conn,
+        "org.freedesktop.ModemManager1",
+        "/org/freedesktop/ModemManager1",
+        "org.freedesktop.DBus.ObjectManager",
+    )?;
+    let objects: HashMap<OwnedObjectPath, HashMap<String, HashMap<String, OwnedValue>>> =
+        object_manager.call("GetManagedObjects", &())?;
+    Ok(objects
+        .into_keys()
+        .min_by(|a, b| a.as_str().cmp(b.as_str())))
+}
+
+/// Enables or disables the modem
+fn enable(dbus_conn: &Connection, modem: &OwnedObjectPath, enable: bool) -> zbus::Result<()> {
+    let proxy = Proxy::new(
+        dbus_conn,
+        "org.freedesktop.ModemManager1",
+        modem,
+        "org.freedesktop.ModemManager1.Modem",
+    )?;
+    proxy.call("Enable", &(enable,))
+}
+
+/// Queries the state of the modem
+fn read_state(dbus_conn: &Connection, modem: &OwnedObjectPath) -> zbus::Result<ModemState> {
+    let proxy = Proxy::new(
+        dbus_conn,
+        "org.freedesktop.ModemManager1",
+        modem,
+        "org.freedesktop.ModemManager1.Modem",
+    )?;
+    let state = proxy.get_property::<i32>("State")?;
+    let (signal, _recent) = proxy.get_property::<(u32, bool)>("SignalQuality")?;
+    let technologies = proxy.get_property::<u32>("AccessTechnologies")?;
+
+    // Only 3GPP modems have an operator
+    let modem_3gpp = Proxy::new(
+        dbus_conn,
+        "org.freedesktop.ModemManager1",
+        modem,
+        "org.freedesktop.ModemManager1.Modem.Modem3gpp",
+    )?;
+    let operator = modem_3gpp
+        .get_property::<String>("OperatorName")
+        .ok()
+        .filter(|operator| !operator.is_empty());
+    let roaming = modem_3gpp
+        .get_property::<u32>("RegistrationState")
+        .is_ok_and(|registration| registration == MM_MODEM_3GPP_REGISTRATION_STATE_ROAMING);
+
+    Ok(ModemState {
+        enabled: state >= MM_MODEM_STATE_ENABLED,
+        signal,
+        technology: ACCESS_TECHNOLOGIES
+            .iter()
+            .find(|(bits, _)| technologies & bits != 0)
+            .map(|(_, name)| *name),
+        operator,
+        roaming,
+    })
+}
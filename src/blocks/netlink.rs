@@ -0,0 +1,160 @@
+//! Minimal generic netlink client
+
+use std::io;
+use std::os::fd::{AsRawFd as _, FromRawFd as _, OwnedFd};
+
+/// Family of the generic netlink controller
+const GENL_ID_CTRL: u16 = 0x10;
+
+/// Controller command resolving a family name
+const CTRL_CMD_GETFAMILY: u8 = 3;
+
+/// Controller attribute holding the family ID
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+
+/// Controller attribute holding the family name
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+
+/// Size of the netlink and the generic netlink header
+const HEADER_LEN: usize = 16 + 4;
+
+/// An open generic netlink socket
+pub struct GenericNetlink {
+    fd: OwnedFd,
+    seq: u32,
+}
+
+impl GenericNetlink {
+    pub fn connect() -> io::Result<Self> {
+        // SAFETY: socket() has no memory safety preconditions
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_GENERIC,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            // SAFETY: The fd was just opened and is owned by nobody else
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            seq: 0,
+        })
+    }
+
+    /// Resolves the ID of a family, e.g. `nl80211`
+    pub fn family(&mut self, name: &str) -> io::Result<u16> {
+        let mut name = name.as_bytes().to_vec();
+        name.push(0);
+        let replies = self.request(
+            GENL_ID_CTRL,
+            CTRL_CMD_GETFAMILY,
+            false,
+            &[(CTRL_ATTR_FAMILY_NAME, &name)],
+        )?;
+        replies
+            .iter()
+            .flat_map(|reply| attributes(reply))
+            .find(|(kind, _)| *kind == CTRL_ATTR_FAMILY_ID)
+            .and_then(|(_, value)| Some(u16::from_ne_bytes(value.try_into().ok()?)))
+            .ok_or_else(|| io::Error::other("family has no ID"))
+    }
+
+    /// Sends a command and returns the attributes of all replies, without their headers
+    pub fn request(
+        &mut self,
+        family: u16,
+        command: u8,
+        dump: bool,
+        attrs: &[(u16, &[u8])],
+    ) -> io::Result<Vec<Vec<u8>>> {
+        self.seq += 1;
+        let mut msg = Vec::with_capacity(64);
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        msg.extend_from_slice(&family.to_ne_bytes());
+        let flags = if dump {
+            libc::NLM_F_REQUEST | libc::NLM_F_DUMP
+        } else {
+            libc::NLM_F_REQUEST
+        };
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Flags fit
+        msg.extend_from_slice(&(flags as u16).to_ne_bytes());
+        msg.extend_from_slice(&self.seq.to_ne_bytes());
+        msg.extend_from_slice(&0u32.to_ne_bytes());
+        // Generic netlink header with version 1
+        msg.extend_from_slice(&[command, 1, 0, 0]);
+        for (kind, value) in attrs {
+            let len = u16::try_from(4 + value.len()).map_err(io::Error::other)?;
+            msg.extend_from_slice(&len.to_ne_bytes());
+            msg.extend_from_slice(&kind.to_ne_bytes());
+            msg.extend_from_slice(value);
+            msg.resize(msg.len().next_multiple_of(4), 0);
+        }
+        let len = u32::try_from(msg.len()).map_err(io::Error::other)?;
+        msg[..4].copy_from_slice(&len.to_ne_bytes());
+
+        // SAFETY: The buffer is valid for its length
+        let sent = unsafe { libc::send(self.fd.as_raw_fd(), msg.as_ptr().cast(), msg.len(), 0) };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let mut replies = vec![];
+        let mut buf = vec![0u8; 32 * 1024];
+        loop {
+            // SAFETY: The buffer is valid for its length
+            let len =
+                unsafe { libc::recv(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+            let len = usize::try_from(len).map_err(|_| io::Error::last_os_error())?;
+
+            let mut rest = &buf[..len];
+            while rest.len() >= HEADER_LEN - 4 {
+                let msg_len = u32::from_ne_bytes(rest[..4].try_into().unwrap_or_default());
+                let msg_len = usize::try_from(msg_len).unwrap_or(usize::MAX);
+                let kind = u16::from_ne_bytes([rest[4], rest[5]]);
+                if msg_len < HEADER_LEN - 4 || msg_len > rest.len() {
+                    return Err(io::Error::other("truncated netlink message"));
+                }
+                let msg = &rest[..msg_len];
+                rest = &rest[msg_len.next_multiple_of(4).min(rest.len())..];
+
+                match i32::from(kind) {
+                    libc::NLMSG_DONE => return Ok(replies),
+                    libc::NLMSG_ERROR => {
+                        let code = msg.get(16..20).map_or(0, |code| {
+                            i32::from_ne_bytes(code.try_into().unwrap_or_default())
+                        });
+                        if code != 0 {
+                            return Err(io::Error::from_raw_os_error(-code));
+                        }
+                        return Ok(replies);
+                    }
+                    _ => {
+                        replies.push(msg.get(HEADER_LEN..).unwrap_or_default().to_vec());
+                        if !dump {
+                            return Ok(replies);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Splits attributes into their types and values
+pub fn attributes(mut buf: &[u8]) -> Vec<(u16, &[u8])> {
+    let mut ret = vec![];
+    while buf.len() >= 4 {
+        let len = usize::from(u16::from_ne_bytes([buf[0], buf[1]]));
+        // Without the nested and byte order flags
+        let kind = u16::from_ne_bytes([buf[2], buf[3]]) & 0x3fff;
+        if len < 4 || len > buf.len() {
+            break;
+        }
+        ret.push((kind, &buf[4..len]));
+        buf = &buf[len.next_multiple_of(4).min(buf.len())..];
+    }
+    ret
+}
@@ -0,0 +1,130 @@
+//! Flags network mounts that stopped responding
+
+use super::color::Color;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt as _;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// How often to check the mounts in seconds
+const POLL_INTERVAL: u64 = 30;
+
+/// How long a mount may take to respond in seconds
+const TIMEOUT: u64 = 5;
+
+/// Filesystem types that are considered network mounts
+const NETWORK_FS: [&str; 7] = ["nfs", "nfs4", "cifs", "smb3", "smbfs", "fuse.sshfs", "9p"];
+
+pub struct NetworkMountBlock {
+    /// Mounts that didn't respond in time
+    hung: Arc<RwLock<Vec<PathBuf>>>,
+}
+
+impl Block for NetworkMountBlock {
+    fn render(&self) -> Option<I3Block> {
+        let hung = self.hung.read().unwrap();
+        if hung.is_empty() {
+            return None;
+        }
+        let names = hung
+            .iter()
+            .map(|path| path.to_string_lossy())
+            .collect::<Vec<_>>();
+        Some(I3Block {
+            full_text: format!("⚠ {}", names.join(" ")),
+            short_text: Some(format!("⚠ {}", hung.len())),
+            color: Some(Color::critical()),
+            tooltip: Some(format!("Hung mounts\n{}", names.join("\n"))),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+}
+
+impl NetworkMountBlock {
+    /// Creates the block. If no mounts are given, all network mounts are checked.
+    #[must_use]
+    pub fn new(ctx: &BlockContext, mounts: Vec<PathBuf>) -> Self {
+        let hung = Arc::new(RwLock::new(vec![]));
+
+        let hung2 = Arc::clone(&hung);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            // Mounts that are still stuck in `statvfs()`
+            let in_flight = Arc::new(Mutex::new(HashSet::new()));
+            loop {
+                let mounts = if mounts.is_empty() {
+                    network_mounts()
+                } else {
+                    mounts.clone()
+                };
+
+                let mut new_hung = vec![];
+                for mount in mounts {
+                    // Don't pile up threads for mounts that are still stuck
+                    if !in_flight.lock().unwrap().insert(mount.clone()) {
+                        new_hung.push(mount);
+                        continue;
+                    }
+                    let (send, receive) = mpsc::channel();
+                    let in_flight = Arc::clone(&in_flight);
+                    let path = mount.clone();
+                    std::thread::spawn(move || {
+                        statvfs(&path);
+                        in_flight.lock().unwrap().remove(&path);
+                        let _idc = send.send(());
+                    });
+                    if receive.recv_timeout(Duration::from_secs(TIMEOUT)).is_err() {
+                        new_hung.push(mount);
+                    }
+                }
+
+                let mut hung = hung2.write().unwrap();
+                if *hung != new_hung {
+                    *hung = new_hung;
+                    ctx.request_update();
+                }
+                drop(hung);
+                std::thread::sleep(Duration::from_secs(POLL_INTERVAL));
+            }
+        });
+
+        Self { hung }
+    }
+}
+
+/// Finds all currently mounted network filesystems
+fn network_mounts() -> Vec<PathBuf> {
+    let Ok(contents) = std::fs::read_to_string("/proc/mounts") else {
+        return vec![];
+    };
+    contents
+        .lines()
+        .filter_map(|line| {
+            let mut split = line.split(' ');
+            let mountpoint = split.nth(1)?;
+            let fs_type = split.next()?;
+            if NETWORK_FS.contains(&fs_type) {
+                Some(PathBuf::from(mountpoint.replace("\\040", " ")))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Calls `statvfs()` on the path, which blocks when the mount is hung
+fn statvfs(path: &Path) {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return;
+    };
+    // SAFETY: The path is a valid C string and buf is a valid statvfs struct
+    unsafe {
+        let mut buf = std::mem::zeroed::<libc::statvfs>();
+        libc::statvfs(path.as_ptr(), &raw mut buf);
+    }
+}
@@ -0,0 +1,102 @@
+//! Shows how many derivations the nix-daemon is building and how full the filesystem holding the
+//! Nix store is. That's not the size of the store, which would take walking all of it.
+
+use super::color::Color;
+use super::disk_block::disk_space;
+use super::simple_poll_block::SimplePollBlock;
+use super::units::format_bytes;
+use super::I3Block;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::os::unix::fs::MetadataExt as _;
+use std::path::Path;
+use std::time::Duration;
+
+/// How often to look for builds in seconds
+const POLL_INTERVAL: u64 = 5;
+
+/// Above this percentage of used space, the block is colored
+const USAGE_CRITICAL: u64 = 90;
+
+pub type NixBlock = SimplePollBlock<Nix>;
+
+/// Running builds and the space on the filesystem of the store
+pub struct Nix {
+    builds: usize,
+    /// Bytes of the filesystem the store is on, used by anything
+    used: u64,
+    total: u64,
+}
+
+#[must_use]
+pub fn new() -> NixBlock {
+    SimplePollBlock::new(Duration::from_secs(POLL_INTERVAL), poll, format)
+}
+
+fn poll() -> Option<Nix> {
+    let (free, total) = disk_space(Path::new("/nix/store"))?;
+    Some(Nix {
+        builds: count_builds(),
+        used: total - free,
+        total,
+    })
+}
+
+fn format(nix: &Nix) -> I3Block {
+    let percent = nix.used * 100 / nix.total.max(1);
+    let mut full_text = "❄".to_owned();
+    if nix.builds > 0 {
+        let _idc = write!(full_text, "{} ", nix.builds);
+    }
+    let _idc = write!(full_text, "{percent}%");
+    I3Block {
+        full_text,
+        color: if percent >= USAGE_CRITICAL {
+            Some(Color::critical())
+        } else {
+            None
+        },
+        tooltip: Some(format!(
+            "{} derivations building\nFilesystem of /nix/store: {} of {} used",
+            nix.builds,
+            format_bytes(nix.used),
+            format_bytes(nix.total)
+        )),
+        ..Default::default()
+    }
+}
+
+/// Counts the builders, which are started by the nix-daemon as one of the build users
+fn count_builds() -> usize {
+    let Ok(dir) = std::fs::read_dir("/proc") else {
+        return 0;
+    };
+    // Parent and name of every process, and whether it runs as root
+    let processes: HashMap<u32, (u32, String, bool)> = dir
+        .flatten()
+        .filter_map(|entry| {
+            let pid = entry.file_name().to_str()?.parse().ok()?;
+            let root = entry.metadata().ok()?.uid() == 0;
+            let stat = std::fs::read_to_string(entry.path().join("stat")).ok()?;
+            // The name is in parentheses and may contain anything, including parentheses
+            let (open, close) = (stat.find('(')?, stat.rfind(')')?);
+            let name = stat.get(open + 1..close)?.to_owned();
+            let parent = stat
+                .get(close + 1..)?
+                .split_whitespace()
+                .nth(1)?
+                .parse()
+                .ok()?;
+            Some((pid, (parent, name, root)))
+        })
+        .collect();
+    processes
+        .values()
+        .filter(|(parent, _, root)| {
+            !root
+                && processes
+                    .get(parent)
+                    .is_some_and(|(_, name, _)| name == "nix-daemon")
+        })
+        .count()
+}
@@ -0,0 +1,125 @@
+//! Queries wireless interfaces via nl80211
+
+use super::netlink::{attributes, GenericNetlink};
+
+/// Command dumping all wireless interfaces
+const NL80211_CMD_GET_INTERFACE: u8 = 5;
+
+/// Command dumping the stations an interface is connected to
+const NL80211_CMD_GET_STATION: u8 = 17;
+
+const NL80211_ATTR_IFINDEX: u16 = 3;
+const NL80211_ATTR_IFNAME: u16 = 4;
+const NL80211_ATTR_IFTYPE: u16 = 5;
+const NL80211_ATTR_STA_INFO: u16 = 21;
+const NL80211_ATTR_WIPHY_FREQ: u16 = 38;
+const NL80211_ATTR_SSID: u16 = 52;
+
+/// Station info attribute holding the signal strength in dBm
+const NL80211_STA_INFO_SIGNAL: u16 = 7;
+
+/// Interface type of access points
+pub const NL80211_IFTYPE_AP: u32 = 3;
+
+/// Interface type of clients
+pub const NL80211_IFTYPE_STATION: u32 = 2;
+
+/// An open nl80211 connection
+pub struct Nl80211 {
+    socket: GenericNetlink,
+    family: u16,
+}
+
+/// A wireless interface
+pub struct Interface {
+    pub index: u32,
+    pub name: String,
+    /// One of the `NL80211_IFTYPE_` constants
+    pub kind: u32,
+    /// Network the interface is connected to or provides
+    pub ssid: Option<String>,
+    /// Frequency in MHz
+    pub frequency: Option<u32>,
+}
+
+/// A station an interface is connected to, the access point for clients
+pub struct Station {
+    /// Signal strength in dBm
+    pub signal: Option<i8>,
+}
+
+impl Nl80211 {
+    pub fn connect() -> std::io::Result<Self> {
+        let mut socket = GenericNetlink::connect()?;
+        let family = socket.family("nl80211")?;
+        Ok(Self { socket, family })
+    }
+
+    /// Lists all wireless interfaces
+    pub fn interfaces(&mut self) -> std::io::Result<Vec<Interface>> {
+        let replies = self
+            .socket
+            .request(self.family, NL80211_CMD_GET_INTERFACE, true, &[])?;
+        Ok(replies
+            .iter()
+            .filter_map(|reply| {
+                let mut index = None;
+                let mut name = String::new();
+                let mut kind = 0;
+                let mut ssid = None;
+                let mut frequency = None;
+                for (attr, value) in attributes(reply) {
+                    match attr {
+                        NL80211_ATTR_IFINDEX => {
+                            index = value.try_into().ok().map(u32::from_ne_bytes);
+                        }
+                        NL80211_ATTR_IFNAME => {
+                            String::from_utf8_lossy(value)
+                                .trim_end_matches('\0')
+                                .clone_into(&mut name);
+                        }
+                        NL80211_ATTR_IFTYPE => {
+                            kind = value.try_into().map(u32::from_ne_bytes).unwrap_or_default();
+                        }
+                        NL80211_ATTR_SSID => {
+                            ssid = Some(String::from_utf8_lossy(value).into_owned());
+                        }
+                        NL80211_ATTR_WIPHY_FREQ => {
+                            frequency = value.try_into().ok().map(u32::from_ne_bytes);
+                        }
+                        _ => {}
+                    }
+                }
+                Some(Interface {
+                    index: index?,
+                    name,
+                    kind,
+                    ssid,
+                    frequency,
+                })
+            })
+            .collect())
+    }
+
+    /// Lists the stations the interface is connected to
+    pub fn stations(&mut self, interface: &Interface) -> std::io::Result<Vec<Station>> {
+        let replies = self.socket.request(
+            self.family,
+            NL80211_CMD_GET_STATION,
+            true,
+            &[(NL80211_ATTR_IFINDEX, &interface.index.to_ne_bytes())],
+        )?;
+        Ok(replies
+            .iter()
+            .map(|reply| Station {
+                signal: attributes(reply)
+                    .into_iter()
+                    .filter(|(attr, _)| *attr == NL80211_ATTR_STA_INFO)
+                    .flat_map(|(_, info)| attributes(info))
+                    .find(|(attr, _)| *attr == NL80211_STA_INFO_SIGNAL)
+                    .and_then(|(_, value)| value.first())
+                    .map(|signal| i8::from_ne_bytes([*signal])),
+            })
+            .collect())
+    }
+}
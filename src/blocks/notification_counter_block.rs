@@ -0,0 +1,133 @@
+//! Counts notifications of certain applications until clicked
+
+use super::diagnostics_block::report;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock};
+use zbus::blocking::{Connection, MessageIterator};
+use zbus::message::Type;
+use zbus::zvariant::OwnedValue;
+
+/// Body of a Notify call
+type NotifyArgs = (
+    String,
+    u32,
+    String,
+    String,
+    String,
+    Vec<String>,
+    HashMap<String, OwnedValue>,
+    i32,
+);
+
+pub struct NotificationCounterBlock {
+    /// Number of unseen notifications by application name
+    counts: Arc<RwLock<BTreeMap<String, usize>>>,
+    ctx: BlockContext,
+}
+
+impl Block for NotificationCounterBlock {
+    fn render(&self) -> Option<I3Block> {
+        let counts = self.counts.read().unwrap();
+        let total = counts.values().sum::<usize>();
+        if total == 0 {
+            return None;
+        }
+        Some(I3Block {
+            full_text: format!("🔔{total}"),
+            tooltip: Some(
+                counts
+                    .iter()
+                    .map(|(app, count)| format!("{app}: {count}"))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {
+        self.counts.write().unwrap().clear();
+        self.ctx.request_update();
+    }
+}
+
+impl NotificationCounterBlock {
+    /// Creates the block counting notifications of applications matching one of the patterns.
+    /// Patterns may contain `*` as a wildcard.
+    #[must_use]
+    pub fn new(ctx: &BlockContext, patterns: Vec<String>) -> Self {
+        let counts = Arc::new(RwLock::new(BTreeMap::new()));
+
+        let counts2 = Arc::clone(&counts);
+        let ctx2 = ctx.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = monitor(&counts2, &patterns, &ctx2) {
+                report(format!("Failed to monitor notifications: {e}"));
+            }
+        });
+
+        Self {
+            counts,
+            ctx: ctx.clone(),
+        }
+    }
+}
+
+/// Eavesdrops on all Notify calls on the session bus
+fn monitor(
+    counts: &RwLock<BTreeMap<String, usize>>,
+    patterns: &[String],
+    ctx: &BlockContext,
+) -> zbus::Result<()> {
+    // A separate connection is needed since monitors can't do anything else
+    let dbus_conn = Connection::session()?;
+    dbus_conn.call_method(
+        Some("org.freedesktop.DBus"),
+        "/org/freedesktop/DBus",
+        Some("org.freedesktop.DBus.Monitoring"),
+        "BecomeMonitor",
+        &(
+            &["type='method_call',interface='org.freedesktop.Notifications',member='Notify'"]
+                as &[&str],
+            0u32,
+        ),
+    )?;
+
+    for msg in MessageIterator::from(dbus_conn) {
+        let msg = msg?;
+        if msg.message_type() != Type::MethodCall {
+            continue;
+        }
+        let Ok(args) = msg.body().deserialize::<NotifyArgs>() else {
+            continue;
+        };
+        let app = args.0;
+        if patterns.iter().any(|pattern| glob_match(pattern, &app)) {
+            *counts.write().unwrap().entry(app).or_default() += 1;
+            ctx.request_update();
+        }
+    }
+    Ok(())
+}
+
+/// Matches a string against a pattern where `*` matches any number of characters
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts = parts.collect::<Vec<_>>();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        let Some(index) = rest.find(part) else {
+            return false;
+        };
+        rest = &rest[index + part.len()..];
+    }
+    rest.ends_with(last)
+}
@@ -0,0 +1,120 @@
+//! Shared helper for desktop notifications
+
+use super::diagnostics_block::report;
+use std::collections::HashMap;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::Value;
+
+/// How urgent a notification is
+#[derive(Debug, Default, Clone, Copy)]
+pub enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    /// Stays until it is dismissed
+    Critical,
+}
+
+/// A notification that can be sent to the notification daemon
+#[derive(Debug, Default, Clone)]
+pub struct Notification {
+    pub summary: String,
+    pub body: String,
+    pub urgency: Urgency,
+    /// Transient notifications don't end up in the notification history
+    pub transient: bool,
+    /// Buttons as pairs of key and label
+    pub actions: Vec<(String, String)>,
+}
+
+impl Notification {
+    /// Sends the notification in the background
+    pub fn show(self) {
+        std::thread::spawn(move || {
+            if let Err(e) = self.send() {
+                report(format!("Failed to send notification: {e}"));
+            }
+        });
+    }
+
+    /// Sends the notification in the background and calls `on_close` with the key of the invoked
+    /// action, or `None` if it was closed without one
+    pub fn show_with_actions<F: FnOnce(Option<&str>) + Send + 'static>(self, on_close: F) {
+        std::thread::spawn(move || match self.send_and_wait() {
+            Ok(action) => on_close(action.as_deref()),
+            Err(e) => report(format!("Failed to send notification: {e}")),
+        });
+    }
+
+    /// Sends the notification
+    fn send(&self) -> zbus::Result<u32> {
+        let dbus_conn = Connection::session()?;
+        let proxy = proxy(&dbus_conn)?;
+        self.notify(&proxy)
+    }
+
+    /// Sends the notification and waits until an action is invoked or it is closed
+    fn send_and_wait(&self) -> zbus::Result<Option<String>> {
+        let dbus_conn = Connection::session()?;
+        let proxy = proxy(&dbus_conn)?;
+        // Subscribe first so no signal is missed
+        let signals = proxy.receive_all_signals()?;
+        let id = self.notify(&proxy)?;
+
+        for signal in signals {
+            let header = signal.header();
+            match header.member().map(zbus::names::MemberName::as_str) {
+                Some("ActionInvoked") => {
+                    let (signal_id, key) = signal.body().deserialize::<(u32, String)>()?;
+                    if signal_id == id {
+                        return Ok(Some(key));
+                    }
+                }
+                Some("NotificationClosed") => {
+                    let (signal_id, _reason) = signal.body().deserialize::<(u32, u32)>()?;
+                    if signal_id == id {
+                        return Ok(None);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    /// Calls `Notify` and returns the ID of the notification
+    fn notify(&self, proxy: &Proxy) -> zbus::Result<u32> {
+        let mut hints = HashMap::<&str, Value>::new();
+        hints.insert("urgency", Value::U8(self.urgency as u8));
+        if self.transient {
+            hints.insert("transient", Value::Bool(true));
+        }
+
+        proxy.call(
+            "Notify",
+            &(
+                "statusbar-rs",
+                0u32,
+                "",
+                &self.summary,
+                &self.body,
+                self.actions
+                    .iter()
+                    .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+                    .collect::<Vec<_>>(),
+                hints,
+                -1i32,
+            ),
+        )
+    }
+}
+
+/// Connects to the notification daemon
+fn proxy(dbus_conn: &Connection) -> zbus::Result<Proxy<'_>> {
+    Proxy::new(
+        dbus_conn,
+        "org.freedesktop.Notifications",
+        "/org/freedesktop/Notifications",
+        "org.freedesktop.Notifications",
+    )
+}
@@ -0,0 +1,259 @@
+//! Shows the progress of a 3D print from Moonraker (Klipper) or `OctoPrint` and pauses it
+
+use super::color::Color;
+use super::confirm::Confirmation;
+use super::diagnostics_block::report;
+use super::http::{self, Body};
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::fmt::Write as _;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// How often to poll the printer in seconds
+const POLL_INTERVAL: u64 = 30;
+
+/// Which API the printer speaks
+#[derive(Clone)]
+pub enum PrinterApi {
+    Moonraker,
+    OctoPrint { api_key: String },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PrintState {
+    Printing,
+    Paused,
+    Error,
+}
+
+#[derive(Clone, PartialEq)]
+struct Print {
+    state: PrintState,
+    /// Between 0 and 1
+    progress: Option<f64>,
+    remaining: Option<Duration>,
+    /// Current temperatures in degrees
+    hotend: Option<f64>,
+    bed: Option<f64>,
+    /// Why the printer stopped, if it tells us
+    message: Option<String>,
+}
+
+pub struct PrinterBlock {
+    /// The running print, if any
+    print: Arc<RwLock<Option<Print>>>,
+    /// Pausing or resuming needs a second click
+    confirmation: Confirmation,
+    /// Pauses or resumes the print
+    pause_channel: Mutex<Sender<()>>,
+}
+
+impl Block for PrinterBlock {
+    fn render(&self) -> Option<I3Block> {
+        let print = self.print.read().unwrap();
+        let print = print.as_ref()?;
+
+        let mut full_text = match print.state {
+            PrintState::Printing => "🖨".to_owned(),
+            PrintState::Paused => "🖨⏸".to_owned(),
+            PrintState::Error => "🖨error".to_owned(),
+        };
+        if self.confirmation.pending() {
+            full_text.push_str(if print.state == PrintState::Paused {
+                " Resume?"
+            } else {
+                " Pause?"
+            });
+        } else if print.state != PrintState::Error {
+            if let Some(progress) = print.progress {
+                let _idc = write!(full_text, "{:.0}%", progress * 100.0);
+            }
+            if let Some(remaining) = print.remaining {
+                let minutes = remaining.as_secs() / 60;
+                let _idc = write!(full_text, " {}:{:02}", minutes / 60, minutes % 60);
+            }
+        }
+        let temperature = |temperature: Option<f64>| {
+            temperature.map_or_else(
+                || "?".to_owned(),
+                |temperature| format!("{temperature:.0}°C"),
+            )
+        };
+        let mut tooltip = format!(
+            "Hotend {}, bed {}",
+            temperature(print.hotend),
+            temperature(print.bed)
+        );
+        if let Some(message) = &print.message {
+            let _idc = write!(tooltip, "\n{message}");
+        }
+        Some(I3Block {
+            full_text,
+            color: match print.state {
+                PrintState::Printing => Some(Color::good()),
+                PrintState::Paused => None,
+                PrintState::Error => Some(Color::critical()),
+            },
+            urgent: print.state == PrintState::Error,
+            tooltip: Some(tooltip),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button != 1 {
+            return;
+        }
+        let pausable = self
+            .print
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|print| print.state != PrintState::Error);
+        if pausable && self.confirmation.confirm() {
+            let _idc = self.pause_channel.lock().unwrap().send(());
+        }
+    }
+}
+
+impl PrinterBlock {
+    /// Creates the block for the printer at `url`, like `http://voron.local`
+    #[must_use]
+    pub fn new(ctx: &BlockContext, url: &str, api: PrinterApi) -> Self {
+        let print = Arc::new(RwLock::new(None));
+        let (send, receive) = mpsc::channel::<()>();
+
+        let print2 = Arc::clone(&print);
+        let ctx2 = ctx.clone();
+        let url = url.trim_end_matches('/').to_owned();
+        std::thread::spawn(move || loop {
+            // The printer is most likely just switched off when it's unreachable
+            let current = query(&url, &api).ok().flatten();
+            if *print2.read().unwrap() != current {
+                *print2.write().unwrap() = current;
+                ctx2.request_update();
+            }
+
+            match receive.recv_timeout(Duration::from_secs(POLL_INTERVAL)) {
+                Ok(()) => {
+                    let paused = print2
+                        .read()
+                        .unwrap()
+                        .as_ref()
+                        .is_some_and(|print| print.state == PrintState::Paused);
+                    if let Err(e) = set_paused(&url, &api, !paused) {
+                        report(format!("Failed to pause the print: {e}"));
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        });
+
+        Self {
+            print,
+            confirmation: Confirmation::new(ctx),
+            pause_channel: Mutex::new(send),
+        }
+    }
+}
+
+/// Returns the running print, `None` when the printer is idle
+fn query(url: &str, api: &PrinterApi) -> Result<Option<Print>, String> {
+    match api {
+        PrinterApi::Moonraker => {
+            let document = http::get_json(
+                &format!(
+                    "{url}/printer/objects/query?print_stats&virtual_sdcard&extruder&heater_bed"
+                ),
+                &[],
+            )?;
+            let status = &document["result"]["status"];
+            let state = match status["print_stats"]["state"].as_str() {
+                Some("printing") => PrintState::Printing,
+                Some("paused") => PrintState::Paused,
+                Some("error") => PrintState::Error,
+                _ => return Ok(None),
+            };
+            let progress = status["virtual_sdcard"]["progress"].as_f64();
+            // Moonraker only knows how long it has been printing, so extrapolate
+            let elapsed = status["print_stats"]["print_duration"].as_f64();
+            let remaining = progress
+                .zip(elapsed)
+                .filter(|(progress, _)| *progress > 0.0)
+                .map(|(progress, elapsed)| {
+                    Duration::from_secs_f64((elapsed / progress - elapsed).max(0.0))
+                });
+            Ok(Some(Print {
+                state,
+                progress,
+                remaining,
+                hotend: status["extruder"]["temperature"].as_f64(),
+                bed: status["heater_bed"]["temperature"].as_f64(),
+                message: status["print_stats"]["message"]
+                    .as_str()
+                    .filter(|message| !message.is_empty())
+                    .map(ToOwned::to_owned),
+            }))
+        }
+        PrinterApi::OctoPrint { api_key } => {
+            let headers = [format!("X-Api-Key: {api_key}")];
+            let job = http::get_json(&format!("{url}/api/job"), &headers)?;
+            // States like "Printing from SD" or "Offline after error"
+            let state_text = job["state"].as_str().unwrap_or_default();
+            let state = if state_text.starts_with("Printing") {
+                PrintState::Printing
+            } else if state_text.starts_with("Paus") {
+                PrintState::Paused
+            } else if state_text.contains("rror") {
+                PrintState::Error
+            } else {
+                return Ok(None);
+            };
+            // Temperatures are only available while the printer is connected
+            let printer = http::get_json(&format!("{url}/api/printer"), &headers).ok();
+            let temperature = |heater: &str| {
+                printer
+                    .as_ref()
+                    .and_then(|printer| printer["temperature"][heater]["actual"].as_f64())
+            };
+            Ok(Some(Print {
+                state,
+                progress: job["progress"]["completion"]
+                    .as_f64()
+                    .map(|completion| completion / 100.0),
+                remaining: job["progress"]["printTimeLeft"]
+                    .as_f64()
+                    .map(|secs| Duration::from_secs_f64(secs.max(0.0))),
+                hotend: temperature("tool0"),
+                bed: temperature("bed"),
+                message: (state == PrintState::Error)
+                    .then(|| job["error"].as_str().unwrap_or(state_text).to_owned()),
+            }))
+        }
+    }
+}
+
+/// Pauses or resumes the print
+fn set_paused(url: &str, api: &PrinterApi, paused: bool) -> Result<(), String> {
+    match api {
+        PrinterApi::Moonraker => {
+            let action = if paused { "pause" } else { "resume" };
+            http::request("POST", &format!("{url}/printer/print/{action}"), &[], None)?;
+        }
+        PrinterApi::OctoPrint { api_key } => {
+            let body = serde_json::json!({
+                "command": "pause",
+                "action": if paused { "pause" } else { "resume" },
+            });
+            http::request(
+                "POST",
+                &format!("{url}/api/job"),
+                &[format!("X-Api-Key: {api_key}")],
+                Some(Body::Json(&body.to_string())),
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,41 @@
+//! Shows how often the bar was restarted after crashing
+
+use super::color::Color;
+use super::{Block, I3Block, I3Event};
+
+/// Environment variable the supervisor passes the number of restarts in
+pub const RESTARTS_VAR: &str = "STATUSBAR_RESTARTS";
+
+pub struct RestartsBlock {
+    restarts: u32,
+}
+
+impl Block for RestartsBlock {
+    fn render(&self) -> Option<I3Block> {
+        if self.restarts == 0 {
+            return None;
+        }
+        Some(I3Block {
+            full_text: format!("↻{}", self.restarts),
+            color: Some(Color::critical()),
+            tooltip: Some(format!(
+                "The bar was restarted {} times after crashing",
+                self.restarts
+            )),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+}
+
+impl Default for RestartsBlock {
+    fn default() -> Self {
+        Self {
+            restarts: std::env::var(RESTARTS_VAR)
+                .ok()
+                .and_then(|restarts| restarts.parse().ok())
+                .unwrap_or_default(),
+        }
+    }
+}
@@ -0,0 +1,212 @@
+//! Tracks how long each application was focused today
+
+use super::diagnostics_block::report;
+use super::i3_ipc::I3Ipc;
+use super::text::truncate_display;
+use super::{Block, BlockContext, I3Block, I3Event};
+use chrono::{Local, NaiveDate};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// IPC event type of window events
+const WINDOW_EVENT: u32 = 3;
+
+/// How often to persist the totals in seconds
+const SAVE_INTERVAL: u64 = 60;
+
+/// How many applications to show in the tooltip
+const TOOLTIP_APPS: usize = 10;
+
+/// How many columns the name of the focused application may take up
+const MAX_APP_WIDTH: usize = 20;
+
+pub struct ScreenTimeBlock {
+    state: Arc<Mutex<ScreenTime>>,
+    ctx: BlockContext,
+}
+
+struct ScreenTime {
+    /// Day the totals are counted for
+    day: NaiveDate,
+    /// How long each application was focused today
+    totals: HashMap<String, Duration>,
+    /// Currently focused application and when it was last accounted
+    current: Option<(String, Instant)>,
+    /// Tracking is paused for privacy
+    paused: bool,
+    /// Where the totals are persisted to
+    state_file: Option<PathBuf>,
+    last_save: Instant,
+}
+
+impl Block for ScreenTimeBlock {
+    fn render(&self) -> Option<I3Block> {
+        let mut state = self.state.lock().unwrap();
+        state.account();
+
+        if state.paused {
+            return Some(I3Block {
+                full_text: "⏸".to_owned(),
+                ..Default::default()
+            });
+        }
+
+        let (app, _) = state.current.as_ref()?;
+        let total = state.totals.get(app).copied().unwrap_or_default();
+
+        let mut totals = state.totals.iter().collect::<Vec<_>>();
+        totals.sort_by(|a, b| b.1.cmp(a.1));
+        let summary = totals
+            .iter()
+            .take(TOOLTIP_APPS)
+            .map(|(app, total)| format!("{app} {}", format_duration(**total)))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Some(I3Block {
+            full_text: format!(
+                "{} {}",
+                truncate_display(app, MAX_APP_WIDTH),
+                format_duration(total)
+            ),
+            short_text: Some(format_duration(total)),
+            tooltip: Some(format!("Screen time today\n{summary}")),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button == 3 {
+            let mut state = self.state.lock().unwrap();
+            state.account();
+            state.paused = !state.paused;
+            self.ctx.request_update();
+        }
+    }
+}
+
+impl ScreenTimeBlock {
+    /// Creates the block. The totals are persisted to `state_file` if given.
+    #[must_use]
+    pub fn new(ctx: &BlockContext, state_file: Option<PathBuf>) -> Self {
+        let today = Local::now().date_naive();
+        let state = Arc::new(Mutex::new(ScreenTime {
+            day: today,
+            totals: state_file
+                .as_ref()
+                .and_then(|path| load(path, today))
+                .unwrap_or_default(),
+            current: None,
+            paused: false,
+            state_file,
+            last_save: Instant::now(),
+        }));
+
+        let state2 = Arc::clone(&state);
+        let ctx2 = ctx.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = watch_focus(&state2, &ctx2) {
+                report(format!("Failed to watch window focus: {e}"));
+            }
+        });
+
+        Self {
+            state,
+            ctx: ctx.clone(),
+        }
+    }
+}
+
+impl ScreenTime {
+    /// Adds the time since the last call to the focused application
+    fn account(&mut self) {
+        if let Some((app, since)) = &mut self.current {
+            if !self.paused {
+                *self.totals.entry(app.clone()).or_default() += since.elapsed();
+            }
+            *since = Instant::now();
+        }
+
+        // Start over every day
+        let today = Local::now().date_naive();
+        if self.day != today {
+            self.day = today;
+            self.totals.clear();
+        }
+
+        if self.last_save.elapsed().as_secs() >= SAVE_INTERVAL {
+            self.save();
+        }
+    }
+
+    /// Writes the totals to the state file
+    fn save(&mut self) {
+        self.last_save = Instant::now();
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        let mut contents = format!("{}\n", self.day);
+        for (app, total) in &self.totals {
+            let _idc = writeln!(contents, "{}\t{app}", total.as_secs());
+        }
+        if let Err(e) = std::fs::write(path, contents) {
+            report(format!("Failed to write screen time: {e}"));
+        }
+    }
+}
+
+/// Loads the totals from the state file if they are from today
+fn load(path: &Path, today: NaiveDate) -> Option<HashMap<String, Duration>> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let mut lines = contents.lines();
+    if lines.next()?.parse::<NaiveDate>().ok()? != today {
+        return None;
+    }
+    Some(
+        lines
+            .filter_map(|line| {
+                let (secs, app) = line.split_once('\t')?;
+                Some((app.to_owned(), Duration::from_secs(secs.parse().ok()?)))
+            })
+            .collect(),
+    )
+}
+
+/// Follows window focus changes of i3/sway
+fn watch_focus(state: &Mutex<ScreenTime>, ctx: &BlockContext) -> std::io::Result<()> {
+    let mut ipc = I3Ipc::connect()?;
+    ipc.subscribe(&["window"])?;
+    loop {
+        let (kind, event) = ipc.next_event()?;
+        if kind != WINDOW_EVENT || event["change"] != "focus" {
+            continue;
+        }
+        // Wayland windows have an app_id, X11 windows a class
+        let container = &event["container"];
+        let Some(app) = container["app_id"]
+            .as_str()
+            .or_else(|| container["window_properties"]["class"].as_str())
+        else {
+            continue;
+        };
+
+        let mut state = state.lock().unwrap();
+        state.account();
+        state.current = Some((app.to_owned(), Instant::now()));
+        drop(state);
+        ctx.request_update();
+    }
+}
+
+/// Formats a duration as hours and minutes
+fn format_duration(duration: Duration) -> String {
+    let mins = duration.as_secs() / 60;
+    if mins >= 60 {
+        format!("{}h{:02}", mins / 60, mins % 60)
+    } else {
+        format!("{mins}m")
+    }
+}
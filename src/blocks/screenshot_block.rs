@@ -0,0 +1,142 @@
+//! Takes screenshots and screen recordings
+
+use super::color::Color;
+use super::diagnostics_block::report;
+use super::{unblock_signals, Block, BlockContext, I3Block, I3Event};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+pub struct ScreenshotBlock {
+    /// Shell command taking a screenshot
+    screenshot_command: String,
+    /// Shell command recording the screen until it receives SIGINT
+    record_command: String,
+    /// Process ID of the running recording and when it started
+    recording: Arc<Mutex<Option<(libc::pid_t, Instant)>>>,
+    ctx: BlockContext,
+}
+
+impl Block for ScreenshotBlock {
+    fn render(&self) -> Option<I3Block> {
+        if let Some((_, since)) = *self.recording.lock().unwrap() {
+            let secs = since.elapsed().as_secs();
+            Some(I3Block {
+                full_text: format!("⏺ {}:{:02}", secs / 60, secs % 60),
+                color: Some(Color::critical()),
+                ..Default::default()
+            })
+        } else {
+            Some(I3Block {
+                full_text: "📷".to_owned(),
+                ..Default::default()
+            })
+        }
+    }
+
+    fn click(&self, evt: &I3Event) {
+        match evt.button {
+            1 => {
+                let command = self.screenshot_command.clone();
+                std::thread::spawn(move || {
+                    if let Err(e) = Command::new("sh").arg("-c").arg(command).status() {
+                        report(format!("Failed to take screenshot: {e}"));
+                    }
+                });
+            }
+            3 => self.toggle_recording(),
+            _ => {}
+        }
+    }
+}
+
+impl ScreenshotBlock {
+    /// Creates the block. Both commands are run by `sh`, e.g. `grim -g "$(slurp)" shot.png`.
+    #[must_use]
+    pub fn new(ctx: &BlockContext, screenshot_command: String, record_command: String) -> Self {
+        Self {
+            screenshot_command,
+            record_command,
+            recording: Arc::new(Mutex::new(None)),
+            ctx: ctx.clone(),
+        }
+    }
+
+    /// Starts a recording or stops the running one
+    fn toggle_recording(&self) {
+        let mut recording = self.recording.lock().unwrap();
+        if let Some((pid, _)) = *recording {
+            // Recorders finish the file when interrupted
+            // SAFETY: kill() has no memory safety preconditions
+            unsafe {
+                libc::kill(pid, libc::SIGINT);
+            }
+            return;
+        }
+
+        // exec so the recorder itself receives the signal instead of the shell
+        let mut child = match unblock_signals(
+            Command::new("sh")
+                .arg("-c")
+                .arg(format!("exec {}", self.record_command)),
+        )
+        .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                report(format!("Failed to start recording: {e}"));
+                return;
+            }
+        };
+        let Ok(pid) = libc::pid_t::try_from(child.id()) else {
+            return;
+        };
+        *recording = Some((pid, Instant::now()));
+        self.ctx.request_update();
+
+        let recording = Arc::clone(&self.recording);
+        let ctx = self.ctx.clone();
+        std::thread::spawn(move || {
+            let _idc = child.wait();
+            *recording.lock().unwrap() = None;
+            ctx.request_update();
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    #[test]
+    fn recording_stops_with_signals_blocked() {
+        // Like a thread of a bar that was started with SIGINT blocked
+        // SAFETY: The set is initialized before it's used
+        unsafe {
+            let mut set = std::mem::zeroed::<libc::sigset_t>();
+            libc::sigemptyset(&raw mut set);
+            libc::sigaddset(&raw mut set, libc::SIGINT);
+            libc::pthread_sigmask(libc::SIG_BLOCK, &raw const set, std::ptr::null_mut());
+        }
+        let (send, _receive) = mpsc::channel();
+        let block = ScreenshotBlock::new(
+            &BlockContext::new(send),
+            String::new(),
+            "sleep 60".to_owned(),
+        );
+
+        block.toggle_recording();
+        assert!(block.recording.lock().unwrap().is_some());
+        block.toggle_recording();
+        let stopped = Instant::now();
+        while block.recording.lock().unwrap().is_some() {
+            assert!(
+                stopped.elapsed() < Duration::from_secs(5),
+                "still recording"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Runs an external script, compatible with i3blocks scripts
+//!
+//! The script prints the full text, optionally followed by the short text and the color on the
+//! next lines. Clicks run the script again with `BLOCK_BUTTON` and friends set.
+//!
+//! Scripts can be confined by a [`Sandbox`], which runs them in a transient systemd user unit.
+//! Scripts running longer than their timeout are killed and the block shows an error.
+
+use super::color::Color;
+use super::diagnostics_block::report;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::os::unix::process::CommandExt as _;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// Exit code of i3blocks scripts asking for the block to be urgent
+const URGENT_EXIT_CODE: i32 = 33;
+
+/// Environment variables describing a click
+type ClickEnv = Vec<(&'static str, String)>;
+
+/// Resource limits and file system restrictions of a script
+#[derive(Default)]
+pub struct Sandbox {
+    /// CPU time in seconds
+    pub cpu_secs: Option<u64>,
+    /// Memory in bytes
+    pub memory: Option<u64>,
+    /// Number of processes and threads, so the script can't fork-bomb
+    pub tasks: Option<u64>,
+    /// Paths the script may read in the otherwise hidden home directories
+    pub read_paths: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// Builds the command running `command` inside the sandbox for at most `timeout`
+    fn command(&self, command: &str, env: &ClickEnv, timeout: Duration) -> Command {
+        let mut ret = Command::new("systemd-run");
+        ret.args(["--user", "--pipe", "--wait", "--quiet", "--collect"]);
+        // Killing systemd-run leaves the unit running, so systemd has to stop it
+        ret.arg(format!(
+            "--property=RuntimeMaxSec={}ms",
+            timeout.as_millis()
+        ));
+        if let Some(cpu_secs) = self.cpu_secs {
+            ret.arg(format!("--property=LimitCPU={cpu_secs}"));
+        }
+        if let Some(memory) = self.memory {
+            ret.arg(format!("--property=MemoryMax={memory}"));
+        }
+        if let Some(tasks) = self.tasks {
+            ret.arg(format!("--property=TasksMax={tasks}"));
+        }
+        ret.arg("--property=ProtectHome=tmpfs");
+        for path in &self.read_paths {
+            ret.arg(format!("--property=BindReadOnlyPaths={}", path.display()));
+        }
+        // The unit doesn't inherit our environment
+        for (key, value) in env {
+            ret.arg(format!("--setenv={key}={value}"));
+        }
+        ret.args(["--", "sh", "-c", command]);
+        ret
+    }
+}
+
+pub struct ScriptBlock {
+    /// Output of the last run
+    output: Arc<RwLock<Option<ScriptOutput>>>,
+    /// Runs the script again with the click in the environment
+    click_channel: Mutex<Sender<ClickEnv>>,
+}
+
+/// What the script printed
+struct ScriptOutput {
+    full_text: String,
+    short_text: Option<String>,
+    color: Option<Color>,
+    urgent: bool,
+}
+
+impl Block for ScriptBlock {
+    fn render(&self) -> Option<I3Block> {
+        let output = self.output.read().unwrap();
+        let output = output.as_ref()?;
+        Some(I3Block {
+            full_text: output.full_text.clone(),
+            short_text: output.short_text.clone(),
+            color: output.color,
+            urgent: output.urgent,
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        let env = vec![
+            ("BLOCK_BUTTON", evt.button.to_string()),
+            ("BLOCK_INSTANCE", evt.instance.clone().unwrap_or_default()),
+            ("BLOCK_MODIFIERS", evt.modifiers.join(",")),
+            ("BLOCK_X", evt.x.to_string()),
+            ("BLOCK_Y", evt.y.to_string()),
+            ("BLOCK_RELATIVE_X", evt.relative_x.to_string()),
+            ("BLOCK_RELATIVE_Y", evt.relative_y.to_string()),
+            ("BLOCK_WIDTH", evt.width.to_string()),
+            ("BLOCK_HEIGHT", evt.height.to_string()),
+        ];
+        let _idc = self.click_channel.lock().unwrap().send(env);
+    }
+}
+
+impl ScriptBlock {
+    /// Creates the block running `command` with `sh` every `interval`, inside `sandbox` if given.
+    /// Runs taking longer than `timeout` are killed.
+    #[must_use]
+    pub fn new(
+        ctx: &BlockContext,
+        command: String,
+        interval: Duration,
+        timeout: Duration,
+        sandbox: Option<Sandbox>,
+    ) -> Self {
+        let output = Arc::new(RwLock::new(None));
+        let (send, receive) = mpsc::channel::<ClickEnv>();
+
+        let output2 = Arc::clone(&output);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let mut env = vec![];
+            loop {
+                *output2.write().unwrap() = run(&command, &env, timeout, sandbox.as_ref());
+                ctx.request_update();
+                env = match receive.recv_timeout(interval) {
+                    Ok(env) => env,
+                    Err(RecvTimeoutError::Timeout) => vec![],
+                    Err(RecvTimeoutError::Disconnected) => return,
+                };
+            }
+        });
+
+        Self {
+            output,
+            click_channel: Mutex::new(send),
+        }
+    }
+}
+
+/// Runs the script and parses its output
+fn run(
+    command: &str,
+    env: &ClickEnv,
+    timeout: Duration,
+    sandbox: Option<&Sandbox>,
+) -> Option<ScriptOutput> {
+    let mut cmd = if let Some(sandbox) = sandbox {
+        sandbox.command(command, env, timeout)
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c")
+            .arg(command)
+            .envs(env.iter().map(|(key, value)| (key, value)));
+        cmd
+    };
+    let output = match wait(&mut cmd, timeout) {
+        Ok(Some(output)) => output,
+        Ok(None) => {
+            report(format!("{command} timed out after {timeout:?}"));
+            return Some(ScriptOutput {
+                full_text: "⏱timed out".to_owned(),
+                short_text: None,
+                color: Some(Color::critical()),
+                urgent: false,
+            });
+        }
+        Err(e) => {
+            report(format!("Failed to run {command}: {e}"));
+            return None;
+        }
+    };
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let full_text = lines.next().filter(|line| !line.is_empty())?.to_owned();
+    let short_text = lines
+        .next()
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned);
+    let color = lines.next().and_then(|line| line.parse().ok());
+    Some(ScriptOutput {
+        full_text,
+        short_text,
+        color,
+        urgent: output.status.code() == Some(URGENT_EXIT_CODE),
+    })
+}
+
+/// Runs the command and collects its output, or kills it and everything it started when it takes
+/// longer than `timeout`
+fn wait(cmd: &mut Command, timeout: Duration) -> std::io::Result<Option<Output>> {
+    let child = cmd
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .spawn()?;
+    let pid = child.id();
+
+    let (send, receive) = mpsc::channel();
+    std::thread::spawn(move || {
+        let _idc = send.send(child.wait_with_output());
+    });
+    if let Ok(output) = receive.recv_timeout(timeout) {
+        return output.map(Some);
+    }
+    if let Ok(pid) = libc::pid_t::try_from(pid) {
+        // SAFETY: kill() has no memory safety preconditions. If the script exits just now, the
+        // group is gone and this fails harmlessly, since its ID isn't reused that quickly.
+        unsafe { libc::kill(-pid, libc::SIGKILL) };
+    }
+    Ok(None)
+}
@@ -0,0 +1,73 @@
+//! Adapter for blocks that only poll a value and format it
+
+use super::{Block, I3Block, I3Event};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Returns the current value, `None` hides the block
+type PollFn<T> = Box<dyn Fn() -> Option<T> + Send + Sync>;
+
+/// Turns the value into what is displayed
+type FormatFn<T> = Box<dyn Fn(&T) -> I3Block + Send + Sync>;
+
+/// A block that polls a value at most every `interval` and formats it.
+///
+/// This is all most one-value blocks need, for example reading a file from sysfs and picking a
+/// color depending on the value. Use [`read_value`] for the polling part in that case.
+pub struct SimplePollBlock<T> {
+    poll: PollFn<T>,
+    format: FormatFn<T>,
+    /// How long a polled value is displayed before polling again
+    interval: Duration,
+    /// Last polled value and when it was polled
+    last: Mutex<Option<(Instant, Option<T>)>>,
+}
+
+impl<T: Send> Block for SimplePollBlock<T> {
+    fn render(&self) -> Option<I3Block> {
+        let mut last = self.last.lock().unwrap();
+        if last
+            .as_ref()
+            .is_none_or(|(time, _)| time.elapsed() >= self.interval)
+        {
+            *last = Some((Instant::now(), (self.poll)()));
+        }
+        let (_, value) = last.as_ref()?;
+        value.as_ref().map(&self.format)
+    }
+
+    fn click(&self, _: &I3Event) {}
+
+    fn resume(&self) {
+        *self.last.lock().unwrap() = None;
+    }
+
+    fn clock_changed(&self) {
+        *self.last.lock().unwrap() = None;
+    }
+}
+
+impl<T> SimplePollBlock<T> {
+    /// Creates a block that calls `poll` at most every `interval` and displays the result using
+    /// `format`
+    #[must_use]
+    pub fn new(
+        interval: Duration,
+        poll: impl Fn() -> Option<T> + Send + Sync + 'static,
+        format: impl Fn(&T) -> I3Block + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            poll: Box::new(poll),
+            format: Box::new(format),
+            interval,
+            last: Mutex::new(None),
+        }
+    }
+}
+
+/// Reads a file and parses its trimmed contents
+pub fn read_value<T: FromStr>(path: impl AsRef<Path>) -> Option<T> {
+    std::fs::read_to_string(path).ok()?.trim().parse().ok()
+}
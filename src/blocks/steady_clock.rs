@@ -0,0 +1,33 @@
+//! Wall clock time that is advanced by the monotonic clock
+
+use chrono::{DateTime, TimeDelta, Utc};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// The wall clock as of the last reset, advanced by the monotonic clock since. Durations between
+/// its readings don't jump when the wall clock is stepped. Blocks reset it in
+/// [`Block::clock_changed`](super::Block::clock_changed) to pick up the new wall clock, and in
+/// [`Block::resume`](super::Block::resume) since the monotonic clock stands still while
+/// suspended.
+#[derive(Default)]
+pub struct SteadyClock {
+    /// Monotonic and wall clock time taken at the same moment
+    anchor: Mutex<Option<(Instant, DateTime<Utc>)>>,
+}
+
+impl SteadyClock {
+    /// Returns the current time
+    pub fn now(&self) -> DateTime<Utc> {
+        let (instant, wall) = *self
+            .anchor
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| (Instant::now(), Utc::now()));
+        wall + TimeDelta::from_std(instant.elapsed()).unwrap_or_default()
+    }
+
+    /// Reads the wall clock again on the next call to [`SteadyClock::now`]
+    pub fn reset(&self) {
+        *self.anchor.lock().unwrap() = None;
+    }
+}
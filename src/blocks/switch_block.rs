@@ -0,0 +1,191 @@
+//! Shows the state of the laptop lid and tablet mode, running commands when they change
+//!
+//! The switches are read from their input devices, which usually requires being in the `input`
+//! group.
+
+use super::diagnostics_block::report;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::Read as _;
+use std::os::fd::AsRawFd as _;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, RwLock};
+
+/// Input event type of switches
+const EV_SW: u16 = 0x05;
+
+/// Input switch reporting that the lid is closed
+const SW_LID: u16 = 0x00;
+
+/// Input switch reporting tablet mode
+const SW_TABLET_MODE: u16 = 0x01;
+
+/// `EVIOCGSW(8)`, reading the state of all switches into 8 bytes
+const EVIOCGSW: u32 = (2 << 30) | (8 << 16) | ((b'E' as u32) << 8) | 0x1b;
+
+/// Commands to run when a switch changes, through `sh -c`
+#[derive(Default)]
+pub struct SwitchCommands {
+    pub lid_closed: Option<String>,
+    pub lid_opened: Option<String>,
+    pub tablet_entered: Option<String>,
+    pub tablet_left: Option<String>,
+}
+
+pub struct SwitchBlock {
+    state: Arc<RwLock<Switches>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Switches {
+    /// `None` if the machine has no lid
+    lid_open: Option<bool>,
+    /// `None` if the machine can't be turned into a tablet
+    tablet: Option<bool>,
+}
+
+impl Block for SwitchBlock {
+    fn render(&self) -> Option<I3Block> {
+        let state = *self.state.read().unwrap();
+        let full_text = match (state.lid_open, state.tablet) {
+            (_, Some(true)) => "📱tablet",
+            (Some(false), _) => "💻closed",
+            (Some(true), _) | (None, Some(false)) => "💻",
+            (None, None) => return None,
+        };
+        Some(I3Block {
+            full_text: full_text.to_owned(),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+}
+
+impl SwitchBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext, commands: SwitchCommands) -> Self {
+        let lid_device = switch_device(SW_LID);
+        let tablet_device = switch_device(SW_TABLET_MODE);
+        let devices: BTreeSet<_> = lid_device.iter().chain(&tablet_device).cloned().collect();
+        let read = move || Switches {
+            lid_open: lid_device
+                .as_deref()
+                .and_then(|device| read_switch(device, SW_LID))
+                .map(|closed| !closed),
+            tablet: tablet_device
+                .as_deref()
+                .and_then(|device| read_switch(device, SW_TABLET_MODE)),
+        };
+        let state = Arc::new(RwLock::new(read()));
+
+        let (send, receive) = mpsc::channel();
+        for device in devices {
+            let send = send.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = wait_for_switches(&device, &send) {
+                    report(format!("Failed to read {}: {e}", device.display()));
+                }
+            });
+        }
+
+        let state2 = Arc::clone(&state);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            for () in receive {
+                let new = read();
+                let old = std::mem::replace(&mut *state2.write().unwrap(), new);
+                if new == old {
+                    continue;
+                }
+
+                let lid_command = match (old.lid_open, new.lid_open) {
+                    (Some(true), Some(false)) => &commands.lid_closed,
+                    (Some(false), Some(true)) => &commands.lid_opened,
+                    _ => &None,
+                };
+                let tablet_command = match (old.tablet, new.tablet) {
+                    (Some(false), Some(true)) => &commands.tablet_entered,
+                    (Some(true), Some(false)) => &commands.tablet_left,
+                    _ => &None,
+                };
+                let transition: Vec<_> = [lid_command, tablet_command]
+                    .into_iter()
+                    .flatten()
+                    .cloned()
+                    .collect();
+                // Commands may take a while, like locking the screen, and must not hold up the
+                // next change
+                if !transition.is_empty() {
+                    std::thread::spawn(move || transition.iter().for_each(|command| run(command)));
+                }
+                ctx.request_update();
+            }
+        });
+
+        Self { state }
+    }
+}
+
+/// Runs a transition command
+fn run(command: &str) {
+    match std::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .status()
+    {
+        Ok(status) if !status.success() => report(format!("`{command}` failed with {status}")),
+        Ok(_) => {}
+        Err(e) => report(format!("Failed to run `{command}`: {e}")),
+    }
+}
+
+/// Finds the input device that has the switch
+fn switch_device(switch: u16) -> Option<PathBuf> {
+    std::fs::read_dir("/sys/class/input")
+        .ok()?
+        .flatten()
+        .filter(|device| device.file_name().to_string_lossy().starts_with("event"))
+        .find(|device| {
+            // A bitmask of the supported switches
+            std::fs::read_to_string(device.path().join("device/capabilities/sw"))
+                .ok()
+                .and_then(|sw| u64::from_str_radix(sw.trim(), 16).ok())
+                .is_some_and(|sw| sw & (1 << switch) != 0)
+        })
+        .map(|device| PathBuf::from("/dev/input").join(device.file_name()))
+}
+
+/// Sends on `changed` whenever a switch of the device changes
+fn wait_for_switches(device: &Path, changed: &Sender<()>) -> std::io::Result<()> {
+    const EVENT: usize = std::mem::size_of::<libc::input_event>();
+    let mut file = File::open(device)?;
+    let mut buf = [0u8; EVENT * 16];
+    loop {
+        let len = file.read(&mut buf)?;
+        // The type, code and value come after the time, whose size depends on the platform
+        let switched = buf[..len]
+            .chunks_exact(EVENT)
+            .any(|event| u16::from_ne_bytes([event[EVENT - 8], event[EVENT - 7]]) == EV_SW);
+        if switched && changed.send(()).is_err() {
+            return Ok(());
+        }
+    }
+}
+
+/// Reads whether the switch of the device is on
+fn read_switch(device: &Path, switch: u16) -> Option<bool> {
+    let file = File::open(device).ok()?;
+    let mut switches = 0u64;
+    // The request is an unsigned long with glibc and an int with musl
+    #[allow(clippy::cast_possible_wrap, clippy::cast_lossless)]
+    let request = EVIOCGSW as libc::Ioctl;
+    // SAFETY: The buffer is as large as the ioctl is told
+    let ret = unsafe { libc::ioctl(file.as_raw_fd(), request, std::ptr::addr_of_mut!(switches)) };
+    if ret < 0 {
+        return None;
+    }
+    Some(switches & (1 << switch) != 0)
+}
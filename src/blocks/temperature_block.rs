@@ -1,20 +1,41 @@
+use super::color::Color;
+use super::notify::Urgency;
+use super::threshold::{Threshold, ThresholdNotifier};
 use super::{Block, I3Block, I3Event};
 use std::fs::File;
 use std::io::{Read as _, Seek as _, SeekFrom};
+use std::path::Path;
 use std::sync::Mutex;
 
 pub struct TemperatureBlock {
+    /// The sensor that is displayed
+    sensor: Mutex<Option<Sensor>>,
+    /// Warns about high temperatures in degrees
+    overheat: ThresholdNotifier,
+}
+
+/// An opened temperature sensor
+struct Sensor {
+    /// Identifies the sensor when hwmon devices are renumbered
+    id: SensorId,
     /// The file where the temperature is read from
-    temperature_file: Option<Mutex<File>>,
+    temperature_file: File,
     /// A temperature the kernel considers "high"
     high_temp: Option<u32>,
 }
 
+/// Chip name and label of a sensor, which stay the same across reboots unlike the hwmon index
+#[derive(PartialEq)]
+struct SensorId {
+    chip: String,
+    label: Option<String>,
+}
+
 impl TemperatureBlock {
     fn err() -> I3Block {
         I3Block {
             full_text: "ERROR".to_owned(),
-            color: Some("#ff0202".to_owned()),
+            color: Some(Color::critical()),
             ..Default::default()
         }
     }
@@ -22,85 +43,125 @@ impl TemperatureBlock {
 
 impl Block for TemperatureBlock {
     fn render(&self) -> Option<I3Block> {
-        if let Some(f) = &self.temperature_file {
-            let mut f = f.lock().unwrap();
+        let mut sensor = self.sensor.lock().unwrap();
+        let current = sensor.as_mut()?;
 
-            if f.seek(SeekFrom::Start(0)).is_err() {
-                return Some(Self::err());
-            }
-
-            let mut contents = String::new();
-            if f.read_to_string(&mut contents).is_err() {
+        let temperature = if let Some(temperature) = current.read() {
+            temperature
+        } else {
+            // The hwmon device may be gone after a module reload or suspend, so look for it again
+            let Some(mut found) = find_sensor(Some(&current.id)) else {
                 return Some(Self::err());
-            }
-            let contents = contents.trim();
-            let Ok(temperature) = contents.parse::<u32>() else {
+            };
+            let Some(temperature) = found.read() else {
                 return Some(Self::err());
             };
+            *current = found;
+            temperature
+        };
+
+        let degrees = temperature / 1000;
+        self.overheat
+            .update(f64::from(degrees), &format!("{degrees}°C"));
 
-            let color = if let Some(high) = self.high_temp {
-                if temperature >= high {
-                    Some("#ff0202".to_owned())
-                } else {
-                    None
-                }
+        let color = if let Some(high) = current.high_temp {
+            if temperature >= high {
+                Some(Color::critical())
             } else {
                 None
-            };
+            }
+        } else {
+            None
+        };
 
-            Some(I3Block {
-                full_text: format!("{}°C", temperature / 1000),
+        Some(
+            I3Block {
+                full_text: format!("{degrees}°C"),
                 color,
                 ..Default::default()
-            })
-        } else {
-            None
-        }
+            }
+            .fixed_width("100°C"),
+        )
     }
 
     fn click(&self, _: &I3Event) {}
 }
 
+impl TemperatureBlock {
+    /// Creates the block. `overheat` is checked against the temperature in degrees.
+    #[must_use]
+    pub fn new(overheat: ThresholdNotifier) -> Self {
+        Self {
+            sensor: Mutex::new(find_sensor(None)),
+            overheat,
+        }
+    }
+}
+
 impl Default for TemperatureBlock {
     fn default() -> Self {
-        // List all sensors
-        let mut ret = Self {
-            temperature_file: None,
-            high_temp: None,
+        Self::new(
+            ThresholdNotifier::above(vec![Threshold::new(
+                90.0,
+                Urgency::Critical,
+                "Overheating",
+                "The CPU reached {value}",
+            )])
+            .with_hysteresis(5.0),
+        )
+    }
+}
+
+impl Sensor {
+    /// Reads the temperature in millidegrees
+    fn read(&mut self) -> Option<u32> {
+        self.temperature_file.seek(SeekFrom::Start(0)).ok()?;
+        let mut contents = String::new();
+        self.temperature_file.read_to_string(&mut contents).ok()?;
+        contents.trim().parse::<u32>().ok()
+    }
+}
+
+/// Finds the sensor with the given id, or the preferred one if no id is given
+fn find_sensor(wanted: Option<&SensorId>) -> Option<Sensor> {
+    let read = |path: &Path| {
+        std::fs::read_to_string(path)
+            .ok()
+            .map(|contents| contents.trim().to_owned())
+    };
+
+    let mut ret = None;
+    for sensor in std::fs::read_dir("/sys/class/hwmon").ok()?.flatten() {
+        let path = sensor.path();
+        // No temperature sensor here
+        if !path.join("temp1_input").exists() {
+            continue;
+        }
+        let id = SensorId {
+            chip: read(&path.join("name")).unwrap_or_default(),
+            label: read(&path.join("temp1_label")),
         };
-        if let Ok(dir) = std::fs::read_dir("/sys/class/hwmon") {
-            for sensor in dir.flatten() {
-                let mut path = sensor.path();
-                path.push("temp1_input");
-                // No temperature sensor here
-                if !path.as_path().exists() {
-                    continue;
-                }
-                // Prefer coretemp on ThinkPads
-                if ret.temperature_file.is_some() && sensor.file_name() != "coretemp" {
-                    continue;
-                }
-                // Open file
-                let Ok(f) = File::open(path.clone()) else {
-                    continue;
-                };
-                ret.temperature_file = Some(Mutex::new(f));
-                // Check if the kernel tells us what a high temperature is
-                path.pop();
-                path.push("temp1_max");
-                ret.high_temp = {
-                    if path.as_path().exists() {
-                        if let Ok(contents) = std::fs::read_to_string(path) {
-                            contents.parse::<u32>().ok()
-                        } else {
-                            None
-                        }
-                    } else {
-                        None
-                    }
-                };
+        if let Some(wanted) = wanted {
+            if &id != wanted {
+                continue;
             }
+        } else if ret.is_some() && id.chip != "coretemp" {
+            // Prefer coretemp on ThinkPads
+            continue;
+        }
+        // Open file
+        let Ok(temperature_file) = File::open(path.join("temp1_input")) else {
+            continue;
+        };
+        ret = Some(Sensor {
+            id,
+            temperature_file,
+            // Check if the kernel tells us what a high temperature is
+            high_temp: read(&path.join("temp1_max")).and_then(|max| max.parse::<u32>().ok()),
+        });
+        if wanted.is_some() {
+            break;
         }
-        ret
     }
+    ret
 }
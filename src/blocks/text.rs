@@ -0,0 +1,225 @@
+//! Measures and shortens text by the space it takes up in the bar
+//!
+//! Grapheme clusters and their widths are approximated closely enough for the scripts and emoji
+//! that show up in window titles, song names and tags. A cluster is a character with all following
+//! combining marks, variation selectors, skin tones and ZWJ sequences.
+
+/// Appended to truncated text
+const ELLIPSIS: &str = "…";
+
+/// Shortens the text to at most `max_width` columns, ending it with an ellipsis if anything was
+/// cut off. Clusters are never split, and a wide character that doesn't fit is left out entirely.
+#[must_use]
+pub fn truncate_display(text: &str, max_width: usize) -> String {
+    if display_width(text) <= max_width {
+        return text.to_owned();
+    }
+    let Some(max_width) = max_width.checked_sub(display_width(ELLIPSIS)) else {
+        return String::new();
+    };
+    let mut ret = String::with_capacity(text.len());
+    let mut width = 0;
+    for cluster in clusters(text) {
+        let cluster_width = cluster_width(cluster);
+        if width + cluster_width > max_width {
+            break;
+        }
+        width += cluster_width;
+        ret.push_str(cluster);
+    }
+    ret.push_str(ELLIPSIS);
+    ret
+}
+
+/// Returns how many columns the text takes up
+#[must_use]
+pub fn display_width(text: &str) -> usize {
+    clusters(text).map(cluster_width).sum()
+}
+
+/// Splits the text into grapheme clusters
+fn clusters(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        let mut chars = rest.char_indices();
+        let (_, first) = chars.next()?;
+        let mut end = rest.len();
+        let mut joined = false;
+        let mut regional = is_regional_indicator(first);
+        for (index, c) in chars {
+            if joined || is_extend(c) {
+                joined = c == '\u{200d}';
+            } else if regional && is_regional_indicator(c) {
+                // Flags are pairs of regional indicators
+                regional = false;
+            } else {
+                end = index;
+                break;
+            }
+        }
+        let (cluster, remaining) = rest.split_at(end);
+        rest = remaining;
+        Some(cluster)
+    })
+}
+
+/// Returns how many columns a grapheme cluster takes up
+fn cluster_width(cluster: &str) -> usize {
+    let mut chars = cluster.chars();
+    let Some(first) = chars.next() else {
+        return 0;
+    };
+    if first.is_control() || is_extend(first) {
+        0
+    } else if is_wide(first) || cluster.contains('\u{fe0f}') || cluster.contains('\u{200d}') {
+        // Emoji presentation and emoji sequences are always wide
+        2
+    } else {
+        1
+    }
+}
+
+/// Whether the character continues the previous cluster
+fn is_extend(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036f}' // Combining diacritical marks
+        | '\u{0483}'..='\u{0489}'
+        | '\u{0591}'..='\u{05bd}'
+        | '\u{0610}'..='\u{061a}'
+        | '\u{064b}'..='\u{065f}'
+        | '\u{0900}'..='\u{0903}' // Devanagari signs
+        | '\u{093a}'..='\u{094f}'
+        | '\u{0e31}' | '\u{0e34}'..='\u{0e3a}' | '\u{0e47}'..='\u{0e4e}' // Thai vowels and tones
+        | '\u{1ab0}'..='\u{1aff}'
+        | '\u{1dc0}'..='\u{1dff}'
+        | '\u{200c}'..='\u{200d}' // Zero width (non-)joiner
+        | '\u{20d0}'..='\u{20ff}'
+        | '\u{3099}'..='\u{309a}' // Kana voicing marks
+        | '\u{fe00}'..='\u{fe0f}' // Variation selectors
+        | '\u{fe20}'..='\u{fe2f}'
+        | '\u{1f3fb}'..='\u{1f3ff}' // Skin tones
+        | '\u{e0020}'..='\u{e007f}' // Tags of subdivision flags
+        | '\u{e0100}'..='\u{e01ef}'
+    )
+}
+
+/// Whether the character is one of a flag
+fn is_regional_indicator(c: char) -> bool {
+    matches!(c, '\u{1f1e6}'..='\u{1f1ff}')
+}
+
+/// Whether the character takes up two columns, see Unicode Standard Annex #11
+fn is_wide(c: char) -> bool {
+    matches!(c,
+        '\u{1100}'..='\u{115f}' // Hangul Jamo
+        | '\u{231a}'..='\u{231b}'
+        | '\u{2329}'..='\u{232a}'
+        | '\u{23e9}'..='\u{23ec}'
+        | '\u{23f0}' | '\u{23f3}'
+        | '\u{25fd}'..='\u{25fe}'
+        | '\u{2614}'..='\u{2615}'
+        | '\u{2648}'..='\u{2653}'
+        | '\u{267f}' | '\u{2693}' | '\u{26a1}' | '\u{26aa}'..='\u{26ab}'
+        | '\u{26bd}'..='\u{26be}' | '\u{26c4}'..='\u{26c5}' | '\u{26ce}' | '\u{26d4}' | '\u{26ea}'
+        | '\u{26f2}'..='\u{26f3}' | '\u{26f5}' | '\u{26fa}' | '\u{26fd}'
+        | '\u{2705}' | '\u{270a}'..='\u{270b}' | '\u{2728}' | '\u{274c}' | '\u{274e}'
+        | '\u{2753}'..='\u{2755}' | '\u{2757}' | '\u{2795}'..='\u{2797}' | '\u{27b0}' | '\u{27bf}'
+        | '\u{2b1b}'..='\u{2b1c}' | '\u{2b50}' | '\u{2b55}'
+        | '\u{2e80}'..='\u{303e}' // CJK radicals, symbols and punctuation
+        | '\u{3041}'..='\u{33ff}' // Kana, Bopomofo, CJK compatibility
+        | '\u{3400}'..='\u{4dbf}' // CJK extension A
+        | '\u{4e00}'..='\u{9fff}' // CJK unified ideographs
+        | '\u{a000}'..='\u{a4cf}' // Yi
+        | '\u{a960}'..='\u{a97f}'
+        | '\u{ac00}'..='\u{d7a3}' // Hangul syllables
+        | '\u{f900}'..='\u{faff}'
+        | '\u{fe10}'..='\u{fe19}'
+        | '\u{fe30}'..='\u{fe6f}'
+        | '\u{ff00}'..='\u{ff60}' // Fullwidth forms
+        | '\u{ffe0}'..='\u{ffe6}'
+        | '\u{16fe0}'..='\u{18aff}'
+        | '\u{1b000}'..='\u{1b2ff}'
+        | '\u{1f004}' | '\u{1f0cf}' | '\u{1f18e}' | '\u{1f191}'..='\u{1f19a}'
+        | '\u{1f1e6}'..='\u{1f1ff}' // Regional indicators
+        | '\u{1f200}'..='\u{1f320}'
+        | '\u{1f32d}'..='\u{1f335}' | '\u{1f337}'..='\u{1f37c}' | '\u{1f37e}'..='\u{1f393}'
+        | '\u{1f3a0}'..='\u{1f3ca}' | '\u{1f3cf}'..='\u{1f3d3}' | '\u{1f3e0}'..='\u{1f3f0}'
+        | '\u{1f3f4}' | '\u{1f3f8}'..='\u{1f43e}' | '\u{1f440}' | '\u{1f442}'..='\u{1f4fc}'
+        | '\u{1f4ff}'..='\u{1f53d}' | '\u{1f54b}'..='\u{1f54e}' | '\u{1f550}'..='\u{1f567}'
+        | '\u{1f57a}' | '\u{1f595}'..='\u{1f596}' | '\u{1f5a4}' | '\u{1f5fb}'..='\u{1f64f}'
+        | '\u{1f680}'..='\u{1f6c5}' | '\u{1f6cc}' | '\u{1f6d0}'..='\u{1f6d2}'
+        | '\u{1f6d5}'..='\u{1f6d7}' | '\u{1f6dc}'..='\u{1f6df}' | '\u{1f6eb}'..='\u{1f6ec}'
+        | '\u{1f6f4}'..='\u{1f6fc}' | '\u{1f7e0}'..='\u{1f7eb}' | '\u{1f7f0}'
+        | '\u{1f90c}'..='\u{1f93a}' | '\u{1f93c}'..='\u{1f945}' | '\u{1f947}'..='\u{1f9ff}'
+        | '\u{1fa70}'..='\u{1faff}'
+        | '\u{20000}'..='\u{2fffd}' // CJK extensions
+        | '\u{30000}'..='\u{3fffd}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn width_of_ascii() {
+        assert_eq!(display_width("Firefox"), 7);
+        assert_eq!(display_width(""), 0);
+    }
+
+    #[test]
+    fn width_of_cjk() {
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("한국어"), 6);
+        assert_eq!(display_width("ｆｕｌｌ"), 8);
+        assert_eq!(display_width("Vim 中文"), 8);
+    }
+
+    #[test]
+    fn width_of_emoji() {
+        assert_eq!(display_width("🎵"), 2);
+        // Emoji presentation of a narrow symbol
+        assert_eq!(display_width("❤\u{fe0f}"), 2);
+        assert_eq!(display_width("👍🏽"), 2);
+        // Family as a ZWJ sequence
+        assert_eq!(display_width("👨\u{200d}👩\u{200d}👧"), 2);
+        assert_eq!(display_width("🇩🇪🇫🇷"), 4);
+    }
+
+    #[test]
+    fn width_of_combining_marks() {
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("Be\u{301}la Bartok"), 11);
+        assert_eq!(display_width("ค\u{e34}\u{e49}"), 1);
+    }
+
+    #[test]
+    fn truncate_keeps_short_text() {
+        assert_eq!(truncate_display("Firefox", 7), "Firefox");
+        assert_eq!(truncate_display("日本語", 6), "日本語");
+    }
+
+    #[test]
+    fn truncate_ascii() {
+        assert_eq!(truncate_display("Firefox Nightly", 8), "Firefox…");
+        assert_eq!(truncate_display("Firefox", 1), "…");
+        assert_eq!(truncate_display("Firefox", 0), "");
+    }
+
+    #[test]
+    fn truncate_leaves_out_wide_characters_that_dont_fit() {
+        assert_eq!(truncate_display("日本語テキスト", 6), "日本…");
+        assert_eq!(display_width(&truncate_display("日本語テキスト", 6)), 5);
+    }
+
+    #[test]
+    fn truncate_never_splits_clusters() {
+        assert_eq!(
+            truncate_display("👨\u{200d}👩\u{200d}👧 family", 3),
+            "👨\u{200d}👩\u{200d}👧…"
+        );
+        assert_eq!(truncate_display("🇩🇪🇫🇷🇮🇹", 5), "🇩🇪🇫🇷…");
+        assert_eq!(truncate_display("e\u{301}e\u{301}e\u{301}", 2), "e\u{301}…");
+        assert_eq!(truncate_display("👍🏽👍🏽", 3), "👍🏽…");
+    }
+}
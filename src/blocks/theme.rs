@@ -0,0 +1,80 @@
+//! Switches between the light and dark palette
+
+use super::color::Palette;
+use super::diagnostics_block::report;
+use super::BlockContext;
+use chrono::{Local, NaiveTime};
+use std::time::Duration;
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::OwnedValue;
+
+/// How often to check the time of day in seconds
+const POLL_INTERVAL: u64 = 60;
+
+/// What decides whether the bar is light or dark
+pub enum ThemeSource {
+    /// Light during the day, dark during the night
+    TimeOfDay {
+        light_from: NaiveTime,
+        dark_from: NaiveTime,
+    },
+    /// The `color-scheme` setting of the desktop portal
+    Portal,
+}
+
+/// Follows the source in the background, redrawing all blocks when the palette changes
+pub fn follow(ctx: &BlockContext, source: ThemeSource) {
+    let ctx = ctx.clone();
+    std::thread::spawn(move || match source {
+        ThemeSource::TimeOfDay {
+            light_from,
+            dark_from,
+        } => loop {
+            let now = Local::now().time();
+            let light = if light_from <= dark_from {
+                light_from <= now && now < dark_from
+            } else {
+                // Light over midnight
+                now >= light_from || now < dark_from
+            };
+            if Palette::set_light(light) {
+                ctx.request_update();
+            }
+            std::thread::sleep(Duration::from_secs(POLL_INTERVAL));
+        },
+        ThemeSource::Portal => {
+            if let Err(e) = follow_portal(&ctx) {
+                report(format!("Failed to follow the color scheme: {e}"));
+            }
+        }
+    });
+}
+
+/// Follows the `color-scheme` setting, which is 1 for dark, 2 for light and 0 for no preference
+fn follow_portal(ctx: &BlockContext) -> zbus::Result<()> {
+    let dbus_conn = Connection::session()?;
+    let proxy = Proxy::new(
+        &dbus_conn,
+        "org.freedesktop.portal.Desktop",
+        "/org/freedesktop/portal/desktop",
+        "org.freedesktop.portal.Settings",
+    )?;
+
+    let apply = |scheme: OwnedValue| {
+        let light = u32::try_from(scheme).is_ok_and(|scheme| scheme == 2);
+        if Palette::set_light(light) {
+            ctx.request_update();
+        }
+    };
+
+    apply(proxy.call("ReadOne", &("org.freedesktop.appearance", "color-scheme"))?);
+    for signal in proxy.receive_signal("SettingChanged")? {
+        let (namespace, key, value) = signal
+            .body()
+            .deserialize::<(String, String, OwnedValue)>()?;
+        if namespace == "org.freedesktop.appearance" && key == "color-scheme" {
+            apply(value);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! Notifies once when a value crosses a threshold, until it clearly recovers
+
+use super::notify::{Notification, Urgency};
+use std::sync::Mutex;
+
+/// Which way a value has to go to cross a threshold
+#[derive(Clone, Copy)]
+enum Direction {
+    Below,
+    Above,
+}
+
+/// A value to notify at
+pub struct Threshold {
+    pub value: f64,
+    /// Sent when the threshold is crossed, with `{value}` in the summary and body replaced by the
+    /// current value
+    pub notification: Notification,
+}
+
+impl Threshold {
+    #[must_use]
+    pub fn new(value: f64, urgency: Urgency, summary: &str, body: &str) -> Self {
+        Self {
+            value,
+            notification: Notification {
+                summary: summary.to_owned(),
+                body: body.to_owned(),
+                urgency,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Sends a notification for every threshold the value crosses, each one only once until the value
+/// is back on the right side of it by more than the hysteresis
+pub struct ThresholdNotifier {
+    direction: Direction,
+    /// Ordered from the mildest to the most severe
+    thresholds: Vec<Threshold>,
+    /// How far the value has to recover past a threshold before it may notify again
+    hysteresis: f64,
+    /// Whether recovering forgets about notifications, otherwise only `reset()` does
+    reset_on_recovery: bool,
+    /// Most severe threshold that was notified about since the value recovered
+    notified: Mutex<Option<usize>>,
+}
+
+impl ThresholdNotifier {
+    /// Notifies when the value drops to or below a threshold
+    #[must_use]
+    pub fn below(mut thresholds: Vec<Threshold>) -> Self {
+        thresholds.sort_by(|a, b| b.value.total_cmp(&a.value));
+        Self::new(Direction::Below, thresholds)
+    }
+
+    /// Notifies when the value rises to or above a threshold
+    #[must_use]
+    pub fn above(mut thresholds: Vec<Threshold>) -> Self {
+        thresholds.sort_by(|a, b| a.value.total_cmp(&b.value));
+        Self::new(Direction::Above, thresholds)
+    }
+
+    fn new(direction: Direction, thresholds: Vec<Threshold>) -> Self {
+        Self {
+            direction,
+            thresholds,
+            hysteresis: 0.0,
+            reset_on_recovery: true,
+            notified: Mutex::new(None),
+        }
+    }
+
+    /// Only notifies again after the value recovered by more than `margin` past a threshold, so
+    /// values hovering around it don't notify over and over
+    #[must_use]
+    pub fn with_hysteresis(mut self, margin: f64) -> Self {
+        self.hysteresis = margin;
+        self
+    }
+
+    /// Only notifies again after `reset()`, no matter how far the value recovers
+    #[must_use]
+    pub fn until_reset(mut self) -> Self {
+        self.reset_on_recovery = false;
+        self
+    }
+
+    /// Returns the most severe threshold the value is past, counting values within `margin` of
+    /// a threshold as past it
+    fn crossed(&self, value: f64, margin: f64) -> Option<usize> {
+        let direction = self.direction;
+        self.thresholds
+            .iter()
+            .rposition(|threshold| match direction {
+                Direction::Below => value <= threshold.value + margin,
+                Direction::Above => value >= threshold.value - margin,
+            })
+    }
+
+    /// Checks the value and notifies if it crossed a more severe threshold than before. `shown`
+    /// is how the value appears in the notification.
+    pub fn update(&self, value: f64, shown: &str) {
+        let mut notified = self.notified.lock().unwrap();
+        if self.reset_on_recovery {
+            // Forget the thresholds the value is clearly back from
+            let held = self.crossed(value, self.hysteresis);
+            *notified = notified
+                .zip(held)
+                .map(|(notified, held)| notified.min(held));
+        }
+        let Some(crossed) = self.crossed(value, 0.0) else {
+            return;
+        };
+        if notified.is_some_and(|notified| notified >= crossed) {
+            return;
+        }
+        *notified = Some(crossed);
+
+        let mut notification = self.thresholds[crossed].notification.clone();
+        notification.summary = notification.summary.replace("{value}", shown);
+        notification.body = notification.body.replace("{value}", shown);
+        notification.show();
+    }
+
+    /// Forgets about all notifications, e.g. when the battery is charging
+    pub fn reset(&self) {
+        *self.notified.lock().unwrap() = None;
+    }
+}
@@ -0,0 +1,104 @@
+//! Shows when the CPU was thermally throttled recently
+
+use super::color::Color;
+use super::{Block, I3Block, I3Event};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// How often to read the counters in seconds
+const POLL_INTERVAL: u64 = 10;
+
+/// How long to show the warning after throttling occurred in seconds
+const RECENT: u64 = 5 * 60;
+
+#[derive(Default)]
+pub struct ThrottleBlock {
+    state: Mutex<ThrottleState>,
+}
+
+#[derive(Default)]
+struct ThrottleState {
+    last_poll: Option<Instant>,
+    /// Last time a counter increased
+    last_throttle: Option<Instant>,
+    /// Package throttle count, summed over all packages
+    package_count: u64,
+    /// Core throttle count, summed over all cores
+    core_count: u64,
+}
+
+impl Block for ThrottleBlock {
+    fn render(&self) -> Option<I3Block> {
+        let mut state = self.state.lock().unwrap();
+
+        if state
+            .last_poll
+            .is_none_or(|last| last.elapsed().as_secs() >= POLL_INTERVAL)
+        {
+            let (package_count, core_count) = read_counters()?;
+            // Don't count the initial values as throttling
+            if state.last_poll.is_some()
+                && (package_count > state.package_count || core_count > state.core_count)
+            {
+                state.last_throttle = Some(Instant::now());
+            }
+            state.package_count = package_count;
+            state.core_count = core_count;
+            state.last_poll = Some(Instant::now());
+        }
+
+        let last_throttle = state.last_throttle?;
+        if last_throttle.elapsed().as_secs() > RECENT {
+            return None;
+        }
+        Some(I3Block {
+            full_text: "⚠🌡".to_owned(),
+            color: Some(Color::critical()),
+            tooltip: Some(format!(
+                "Thermal throttling {} min ago\nPackage throttled {} times\nCores throttled {} times",
+                last_throttle.elapsed().as_secs() / 60,
+                state.package_count,
+                state.core_count
+            )),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+
+    fn resume(&self) {
+        // Counters are reinitialized on resume, don't count that as throttling
+        self.state.lock().unwrap().last_poll = None;
+    }
+}
+
+/// Reads the package and core throttle counters of all CPUs
+fn read_counters() -> Option<(u64, u64)> {
+    let read = |path: std::path::PathBuf| -> Option<u64> {
+        std::fs::read_to_string(path).ok()?.trim().parse().ok()
+    };
+
+    // All CPUs of a package report the same package count
+    let mut packages = HashMap::new();
+    let mut core_count = 0;
+    let mut found = false;
+    for cpu in std::fs::read_dir("/sys/devices/system/cpu").ok()?.flatten() {
+        let path = cpu.path();
+        let Some(core) = read(path.join("thermal_throttle/core_throttle_count")) else {
+            continue;
+        };
+        found = true;
+        core_count += core;
+        if let Some(package) = read(path.join("thermal_throttle/package_throttle_count")) {
+            let id = read(path.join("topology/physical_package_id")).unwrap_or_default();
+            packages.insert(id, package);
+        }
+    }
+
+    if found {
+        Some((packages.values().sum(), core_count))
+    } else {
+        None
+    }
+}
@@ -0,0 +1,200 @@
+//! Shows Thunderbolt devices waiting for authorization and connected docks using `boltd`
+
+use super::color::Color;
+use super::confirm::Confirmation;
+use super::diagnostics_block::report;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::sync::{Arc, Mutex, RwLock};
+use zbus::blocking::{Connection, MessageIterator, Proxy};
+use zbus::message::Type;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::MatchRule;
+
+pub struct ThunderboltBlock {
+    devices: Arc<RwLock<Vec<Device>>>,
+    /// Authorizing a device gives it DMA access, so it needs a second click
+    confirmation: Confirmation,
+    /// Device the second click authorizes
+    target: Mutex<Option<OwnedObjectPath>>,
+}
+
+/// A connected peripheral
+struct Device {
+    path: OwnedObjectPath,
+    name: String,
+    /// Whether the device still needs to be authorized before it can be used
+    pending: bool,
+}
+
+impl Block for ThunderboltBlock {
+    fn render(&self) -> Option<I3Block> {
+        let devices = self.devices.read().unwrap();
+        if devices.is_empty() {
+            return None;
+        }
+
+        let pending = devices.iter().filter(|device| device.pending).count();
+        let target = self.target.lock().unwrap();
+        let confirming = devices
+            .iter()
+            .find(|device| Some(&device.path) == target.as_ref())
+            .filter(|_| self.confirmation.pending());
+        let full_text = if let Some(device) = confirming {
+            format!("⚡Authorize {}?", device.name)
+        } else if pending > 0 {
+            format!("⚡{pending} to authorize")
+        } else {
+            format!(
+                "⚡{}",
+                devices
+                    .iter()
+                    .map(|device| device.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            )
+        };
+        Some(I3Block {
+            full_text,
+            color: if pending > 0 {
+                Some(Color::critical())
+            } else {
+                None
+            },
+            urgent: pending > 0,
+            tooltip: Some(
+                devices
+                    .iter()
+                    .map(|device| {
+                        if device.pending {
+                            format!("{} (click twice to authorize)", device.name)
+                        } else {
+                            device.name.clone()
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            ),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button != 1 {
+            return;
+        }
+        let devices = self.devices.read().unwrap();
+        let mut target = self.target.lock().unwrap();
+        // Confirm the device that was asked about, one device at a time
+        let still_pending = target.as_ref().is_some_and(|target| {
+            devices
+                .iter()
+                .any(|device| device.pending && device.path == *target)
+        });
+        if !still_pending {
+            *target = devices
+                .iter()
+                .find(|device| device.pending)
+                .map(|device| device.path.clone());
+        }
+        let Some(path) = target.clone() else {
+            return;
+        };
+        drop(devices);
+        drop(target);
+        if !self.confirmation.confirm() {
+            return;
+        }
+        std::thread::spawn(move || {
+            if let Err(e) = authorize(&path) {
+                report(format!("Failed to authorize Thunderbolt device: {e}"));
+            }
+        });
+    }
+}
+
+impl ThunderboltBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        let devices = Arc::new(RwLock::new(vec![]));
+
+        let devices2 = Arc::clone(&devices);
+        let ctx2 = ctx.clone();
+        std::thread::spawn(move || {
+            let Ok(dbus_conn) = Connection::system() else {
+                return;
+            };
+            // Devices coming, going and changing their status are all signals of boltd
+            let Ok(rule) = MatchRule::builder()
+                .msg_type(Type::Signal)
+                .sender("org.freedesktop.bolt1")
+                .map(zbus::match_rule::Builder::build)
+            else {
+                return;
+            };
+            let Ok(messages) = MessageIterator::for_match_rule(rule, &dbus_conn, None) else {
+                return;
+            };
+
+            *devices2.write().unwrap() = read_devices(&dbus_conn).unwrap_or_default();
+            ctx2.request_update();
+            for _ in messages {
+                *devices2.write().unwrap() = read_devices(&dbus_conn).unwrap_or_default();
+                ctx2.request_update();
+            }
+        });
+
+        Self {
+            devices,
+            confirmation: Confirmation::new(ctx),
+            target: Mutex::new(None),
+        }
+    }
+}
+
+/// Lists all connected peripherals
+fn read_devices(dbus_conn: &Connection) -> zbus::Result<Vec<Device>> {
+    let manager = Proxy::new(
+        dbus_conn,
+        "org.freedesktop.bolt1",
+        "/org/freedesktop/bolt",
+        "org.freedesktop.bolt1.Manager",
+    )?;
+    let paths: Vec<OwnedObjectPath> = manager.call("ListDevices", &())?;
+
+    let mut devices = vec![];
+    for path in paths {
+        let proxy = Proxy::new(
+            dbus_conn,
+            "org.freedesktop.bolt1",
+            path.clone(),
+            "org.freedesktop.bolt1.Device",
+        )?;
+        // The host controller is listed as well
+        if proxy.get_property::<String>("Type")? != "peripheral" {
+            continue;
+        }
+        let pending = match proxy.get_property::<String>("Status")?.as_str() {
+            "connected" | "auth-error" => true,
+            "authorizing" | "authorized" => false,
+            _ => continue,
+        };
+        devices.push(Device {
+            name: proxy.get_property::<String>("Name")?,
+            path,
+            pending,
+        });
+    }
+    Ok(devices)
+}
+
+/// Authorizes the device for this session
+fn authorize(path: &OwnedObjectPath) -> zbus::Result<()> {
+    let dbus_conn = Connection::system()?;
+    let proxy = Proxy::new(
+        &dbus_conn,
+        "org.freedesktop.bolt1",
+        path,
+        "org.freedesktop.bolt1.Device",
+    )?;
+    proxy.call::<_, _, ()>("Authorize", &("",))
+}
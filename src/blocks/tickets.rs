@@ -0,0 +1,234 @@
+//! Assigned issues of Jira or GitLab, for tracking time on them
+//!
+//! The config file is JSON like
+//! `{"tracker": "jira", "url": "https://jira.example.com", "token": "…", "picker": "rofi -dmenu"}`.
+//! Jira takes a personal access token, GitLab one with the `api` scope. GitLab has no state for
+//! issues in progress, so all open issues assigned to the user are offered.
+
+use super::http::{self, Body};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::path::Path;
+use std::time::Duration;
+
+/// Command reading one issue per line on stdin and printing the chosen one
+const DEFAULT_PICKER: &str = "rofi -dmenu -i -p Issue";
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tracker {
+    Jira,
+    Gitlab,
+}
+
+#[derive(Deserialize)]
+pub struct TicketConfig {
+    pub tracker: Tracker,
+    /// Base URL of the tracker, like `https://gitlab.com`
+    pub url: String,
+    token: String,
+    #[serde(default = "default_picker")]
+    pub picker: String,
+}
+
+fn default_picker() -> String {
+    DEFAULT_PICKER.to_owned()
+}
+
+/// An issue to track time on
+#[derive(Debug, PartialEq)]
+pub struct Issue {
+    /// Like `OPS-12` for Jira or `group/project#12` for GitLab
+    pub key: String,
+    pub summary: String,
+}
+
+impl TicketConfig {
+    /// Reads the JSON config file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let config = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+        let mut config: Self =
+            serde_json::from_str(&config).map_err(|e| format!("Invalid ticket config: {e}"))?;
+        config.url = config.url.trim_end_matches('/').to_owned();
+        Ok(config)
+    }
+
+    fn headers(&self) -> [String; 1] {
+        match self.tracker {
+            Tracker::Jira => [format!("Authorization: Bearer {}", self.token)],
+            Tracker::Gitlab => [format!("PRIVATE-TOKEN: {}", self.token)],
+        }
+    }
+
+    /// Fetches the issues in progress that are assigned to the user
+    pub fn assigned(&self) -> Result<Vec<Issue>, String> {
+        match self.tracker {
+            Tracker::Jira => {
+                let jql = r#"assignee = currentUser() AND statusCategory = "In Progress""#;
+                let url = format!(
+                    "{}/rest/api/2/search?fields=summary&jql={}",
+                    self.url,
+                    http::encode(jql)
+                );
+                Ok(jira_issues(&http::get_json(&url, &self.headers())?))
+            }
+            Tracker::Gitlab => {
+                let url = format!(
+                    "{}/api/v4/issues?scope=assigned_to_me&state=opened&per_page=100",
+                    self.url
+                );
+                Ok(gitlab_issues(&http::get_json(&url, &self.headers())?))
+            }
+        }
+    }
+
+    /// Finds the key of an issue of this tracker in the tags of an interval
+    pub fn find_key<'a>(&self, tags: &'a str) -> Option<&'a str> {
+        tags.split_whitespace().find(|tag| match self.tracker {
+            Tracker::Jira => is_jira_key(tag),
+            Tracker::Gitlab => tag
+                .split_once('#')
+                .is_some_and(|(project, iid)| is_gitlab_key(project, iid)),
+        })
+    }
+
+    /// Adds the time spent on an issue to it, rounded to minutes. Less than half a minute isn't
+    /// logged.
+    pub fn log_work(&self, key: &str, start: DateTime<Utc>, spent: Duration) -> Result<(), String> {
+        let minutes = (spent.as_secs() + 30) / 60;
+        if minutes == 0 {
+            return Ok(());
+        }
+        match self.tracker {
+            Tracker::Jira => {
+                let url = format!(
+                    "{}/rest/api/2/issue/{}/worklog",
+                    self.url,
+                    http::encode(key)
+                );
+                let body = json!({
+                    "started": start.format("%Y-%m-%dT%H:%M:%S%.3f%z").to_string(),
+                    "timeSpentSeconds": minutes * 60,
+                })
+                .to_string();
+                http::request("POST", &url, &self.headers(), Some(Body::Json(&body)))?;
+            }
+            Tracker::Gitlab => {
+                let (project, iid) = key
+                    .split_once('#')
+                    .ok_or_else(|| format!("Invalid GitLab issue {key}"))?;
+                let url = format!(
+                    "{}/api/v4/projects/{}/issues/{iid}/add_spent_time",
+                    self.url,
+                    http::encode(project)
+                );
+                let body = format!("duration={minutes}m");
+                http::request("POST", &url, &self.headers(), Some(Body::Form(&body)))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keys look like `OPS-12`
+fn is_jira_key(tag: &str) -> bool {
+    tag.split_once('-').is_some_and(|(project, number)| {
+        project.starts_with(|c: char| c.is_ascii_uppercase())
+            && project
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+            && !number.is_empty()
+            && number.chars().all(|c| c.is_ascii_digit())
+    })
+}
+
+/// Keys look like `group/project#12`
+fn is_gitlab_key(project: &str, iid: &str) -> bool {
+    project.contains('/') && !iid.is_empty() && iid.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Parses the response of a Jira search
+fn jira_issues(response: &Value) -> Vec<Issue> {
+    let Some(issues) = response.get("issues").and_then(Value::as_array) else {
+        return vec![];
+    };
+    issues
+        .iter()
+        .filter_map(|issue| {
+            Some(Issue {
+                key: issue.get("key")?.as_str()?.to_owned(),
+                summary: issue
+                    .pointer("/fields/summary")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Parses a list of GitLab issues
+fn gitlab_issues(response: &Value) -> Vec<Issue> {
+    let Some(issues) = response.as_array() else {
+        return vec![];
+    };
+    issues
+        .iter()
+        .filter_map(|issue| {
+            Some(Issue {
+                key: issue.pointer("/references/full")?.as_str()?.to_owned(),
+                summary: issue
+                    .get("title")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_owned(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(tracker: Tracker) -> TicketConfig {
+        TicketConfig {
+            tracker,
+            url: String::new(),
+            token: String::new(),
+            picker: default_picker(),
+        }
+    }
+
+    #[test]
+    fn finds_keys_in_tags() {
+        let jira = config(Tracker::Jira);
+        assert_eq!(jira.find_key("meeting OPS-12"), Some("OPS-12"));
+        assert_eq!(jira.find_key("follow-up OPS-"), None);
+        let gitlab = config(Tracker::Gitlab);
+        assert_eq!(gitlab.find_key("group/app#7 review"), Some("group/app#7"));
+        assert_eq!(gitlab.find_key("#7 OPS-12"), None);
+    }
+
+    #[test]
+    fn parses_issues() {
+        let jira = json!({"issues": [{"key": "OPS-12", "fields": {"summary": "Rotate keys"}}]});
+        assert_eq!(
+            jira_issues(&jira),
+            [Issue {
+                key: "OPS-12".to_owned(),
+                summary: "Rotate keys".to_owned()
+            }]
+        );
+        let gitlab = json!([{"title": "Fix build", "references": {"full": "group/app#7"}}]);
+        assert_eq!(
+            gitlab_issues(&gitlab),
+            [Issue {
+                key: "group/app#7".to_owned(),
+                summary: "Fix build".to_owned()
+            }]
+        );
+    }
+}
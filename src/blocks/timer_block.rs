@@ -0,0 +1,194 @@
+//! A stopwatch and countdown timer
+
+use super::color::Color;
+use super::notify::{Notification, Urgency};
+use super::{Block, BlockContext, I3Block, I3Event};
+use chrono::{DateTime, Local, TimeDelta};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How much scrolling changes the countdown in seconds
+const SCROLL_STEP: u64 = 60;
+
+pub struct TimerBlock {
+    state: Arc<Mutex<TimerState>>,
+    ctx: BlockContext,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Stopwatch,
+    Countdown,
+}
+
+struct TimerState {
+    mode: Mode,
+    /// What the countdown starts from
+    countdown: Duration,
+    /// Time counted before the timer was last started
+    elapsed: Duration,
+    /// When the timer was started, if it's running
+    started: Option<Instant>,
+    /// Whether the countdown reached zero
+    finished: bool,
+    /// Wall clock time a running countdown reaches zero at
+    ends_at: Option<DateTime<Local>>,
+    /// Changed whenever the timer is started, stopped or adjusted so outdated countdown threads
+    /// know they are outdated
+    generation: u64,
+}
+
+impl TimerState {
+    fn elapsed(&self) -> Duration {
+        self.elapsed
+            + self
+                .started
+                .map(|started| started.elapsed())
+                .unwrap_or_default()
+    }
+
+    fn remaining(&self) -> Duration {
+        self.countdown.saturating_sub(self.elapsed())
+    }
+}
+
+impl Block for TimerBlock {
+    fn render(&self) -> Option<I3Block> {
+        let state = self.state.lock().unwrap();
+        let full_text = match state.mode {
+            Mode::Stopwatch => format!("⏱{}", format_duration(state.elapsed())),
+            Mode::Countdown => format!("⏲{}", format_duration(state.remaining())),
+        };
+        Some(I3Block {
+            full_text,
+            color: if state.finished {
+                Some(Color::critical())
+            } else if state.started.is_some() {
+                Some(Color::good())
+            } else {
+                None
+            },
+            urgent: state.finished,
+            tooltip: state
+                .ends_at
+                .map(|ends_at| format!("Ends at {}", ends_at.format("%H:%M:%S"))),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        let mut state = self.state.lock().unwrap();
+        match evt.button {
+            // Start or stop
+            1 => {
+                if let Some(started) = state.started.take() {
+                    state.elapsed += started.elapsed();
+                } else if !state.finished {
+                    state.started = Some(Instant::now());
+                }
+            }
+            // Reset
+            2 => {
+                state.elapsed = Duration::ZERO;
+                state.started = None;
+                state.finished = false;
+            }
+            // Switch between stopwatch and countdown
+            3 => {
+                state.mode = match state.mode {
+                    Mode::Stopwatch => Mode::Countdown,
+                    Mode::Countdown => Mode::Stopwatch,
+                };
+                state.elapsed = Duration::ZERO;
+                state.started = None;
+                state.finished = false;
+            }
+            4 if state.mode == Mode::Countdown => {
+                state.countdown += Duration::from_secs(SCROLL_STEP);
+                state.finished = false;
+            }
+            5 if state.mode == Mode::Countdown => {
+                state.countdown = state
+                    .countdown
+                    .saturating_sub(Duration::from_secs(SCROLL_STEP))
+                    .max(Duration::from_secs(SCROLL_STEP));
+            }
+            _ => return,
+        }
+        state.generation += 1;
+        self.arm(&mut state);
+        self.ctx.request_update();
+    }
+
+    fn clock_changed(&self) {
+        // The countdown itself runs on the monotonic clock, only its end moved
+        let mut state = self.state.lock().unwrap();
+        state.ends_at = state.ends_at.map(|_| end_time(state.remaining()));
+    }
+}
+
+impl TimerBlock {
+    /// Creates the block in stopwatch mode. The countdown starts at `countdown`.
+    #[must_use]
+    pub fn new(ctx: &BlockContext, countdown: Duration) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(TimerState {
+                mode: Mode::Stopwatch,
+                countdown,
+                elapsed: Duration::ZERO,
+                started: None,
+                finished: false,
+                ends_at: None,
+                generation: 0,
+            })),
+            ctx: ctx.clone(),
+        }
+    }
+
+    /// Waits for a running countdown to reach zero in the background
+    fn arm(&self, state: &mut TimerState) {
+        if state.mode != Mode::Countdown || state.started.is_none() {
+            state.ends_at = None;
+            return;
+        }
+        let remaining = state.remaining();
+        state.ends_at = Some(end_time(remaining));
+        let generation = state.generation;
+        let state = Arc::clone(&self.state);
+        let ctx = self.ctx.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(remaining);
+            let mut state = state.lock().unwrap();
+            if state.generation != generation {
+                return;
+            }
+            state.elapsed = state.countdown;
+            state.started = None;
+            state.finished = true;
+            state.ends_at = None;
+            Notification {
+                summary: "Timer finished".to_owned(),
+                body: format!("{} are over", format_duration(state.countdown)),
+                urgency: Urgency::Critical,
+                ..Default::default()
+            }
+            .show();
+            ctx.request_update();
+        });
+    }
+}
+
+/// Returns the wall clock time after the remaining time
+fn end_time(remaining: Duration) -> DateTime<Local> {
+    Local::now() + TimeDelta::from_std(remaining).unwrap_or_default()
+}
+
+/// Formats a duration as h:mm:ss or m:ss
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
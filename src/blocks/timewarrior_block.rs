@@ -0,0 +1,324 @@
+//! Local time tracking using timewarrior
+//!
+//! With a ticket config, left clicking picks one of the assigned issues to track time on, and the
+//! time is logged to the issue when the interval is stopped from the bar.
+
+use super::bus::BusEvent;
+use super::color::Color;
+use super::confirm::Confirmation;
+use super::diagnostics_block::report;
+use super::steady_clock::SteadyClock;
+use super::text::truncate_display;
+use super::tickets::TicketConfig;
+use super::{Block, BlockContext, I3Block, I3Event};
+use chrono::{DateTime, Datelike as _, Local, NaiveDateTime, TimeDelta, Utc};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// How many columns the tags of the running interval may take up
+const MAX_TAGS_WIDTH: usize = 30;
+
+pub struct TimewarriorBlock {
+    /// Where the timewarrior data files are
+    data_dir: Option<PathBuf>,
+    /// Parsed data files of this and the previous month, with their modification times
+    cache: Mutex<HashMap<PathBuf, (SystemTime, Vec<Interval>)>>,
+    /// The interval that was running at the last render
+    running: Mutex<Option<Interval>>,
+    /// Where to pick issues from and log the time spent on them
+    tickets: Option<Arc<TicketConfig>>,
+    /// Stopping the running interval needs a second click
+    stop_confirmation: Confirmation,
+    /// Keeps the running interval from jumping when the wall clock is stepped
+    clock: SteadyClock,
+    ctx: BlockContext,
+}
+
+/// A tracked interval
+#[derive(Clone)]
+struct Interval {
+    start: DateTime<Utc>,
+    /// Intervals without end are still running
+    end: Option<DateTime<Utc>>,
+    tags: String,
+}
+
+impl Block for TimewarriorBlock {
+    fn render(&self) -> Option<I3Block> {
+        let now = self.clock.now();
+        let local = now.with_timezone(&Local);
+        let data_dir = self.data_dir.as_ref()?;
+        let path = data_dir.join(local.format("%Y-%m.data").to_string());
+        let mut intervals = self.intervals(&path);
+        // Intervals are stored in the file of the month they started in, so one started last
+        // month may still be running
+        let last_month = local.date_naive().with_day(1)?.pred_opt()?;
+        let last_path = data_dir.join(last_month.format("%Y-%m.data").to_string());
+        if intervals
+            .last()
+            .is_none_or(|interval| interval.end.is_some())
+        {
+            if let Some(running) = self
+                .intervals(&last_path)
+                .pop()
+                .filter(|interval| interval.end.is_none())
+            {
+                intervals.insert(0, running);
+            }
+        }
+        self.cache
+            .lock()
+            .unwrap()
+            .retain(|cached, _| *cached == path || *cached == last_path);
+
+        // Sum up everything that happened today
+        let midnight = local
+            .date_naive()
+            .and_hms_opt(0, 0, 0)?
+            .and_local_timezone(Local)
+            .earliest()?
+            .with_timezone(&Utc);
+        let today = intervals
+            .iter()
+            .map(|interval| {
+                let start = interval.start.max(midnight);
+                let end = interval.end.unwrap_or(now);
+                (end - start).max(TimeDelta::zero())
+            })
+            .sum::<TimeDelta>();
+        let minutes = u64::try_from(today.num_minutes()).unwrap_or_default();
+        self.ctx
+            .publish(BusEvent::TrackedToday(Duration::from_secs(minutes * 60)));
+
+        let running = intervals.iter().find(|interval| interval.end.is_none());
+        *self.running.lock().unwrap() = running.cloned();
+        let full_text = if let Some(running) = running {
+            let tags = truncate_display(&running.tags, MAX_TAGS_WIDTH);
+            if self.stop_confirmation.pending() {
+                format!("⏹ Stop {tags}?")
+            } else {
+                format!(
+                    "⏱{tags} {} ({})",
+                    format_delta(now - running.start),
+                    format_delta(today)
+                )
+            }
+        } else {
+            format!("⏱{}", format_delta(today))
+        };
+        Some(I3Block {
+            full_text,
+            color: if running.is_some() {
+                Some(Color::good())
+            } else {
+                None
+            },
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        match evt.button {
+            1 => {
+                if let Some(tickets) = &self.tickets {
+                    let tickets = Arc::clone(tickets);
+                    let running = self.running.lock().unwrap().clone();
+                    let ctx = self.ctx.clone();
+                    std::thread::spawn(move || {
+                        if let Err(e) = pick_issue(&tickets, running.as_ref()) {
+                            report(e);
+                        }
+                        ctx.request_update();
+                    });
+                }
+            }
+            3 => {
+                let running = self.running.lock().unwrap().clone();
+                if running.is_some() && !self.stop_confirmation.confirm() {
+                    return;
+                }
+                let tickets = self.tickets.clone();
+                let ctx = self.ctx.clone();
+                std::thread::spawn(move || {
+                    // Resume the last tracked tags when starting
+                    let stopped = Command::new("timew")
+                        .arg(if running.is_some() {
+                            "stop"
+                        } else {
+                            "continue"
+                        })
+                        .output()
+                        .is_ok_and(|output| output.status.success());
+                    ctx.request_update();
+                    if let (true, Some(tickets), Some(running)) = (stopped, tickets, running) {
+                        log_work(&tickets, &running);
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+
+    fn resume(&self) {
+        self.clock.reset();
+    }
+
+    fn clock_changed(&self) {
+        self.clock.reset();
+    }
+}
+
+impl TimewarriorBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        Self {
+            data_dir: find_data_dir(),
+            cache: Mutex::new(HashMap::new()),
+            running: Mutex::new(None),
+            tickets: None,
+            stop_confirmation: Confirmation::new(ctx),
+            clock: SteadyClock::default(),
+            ctx: ctx.clone(),
+        }
+    }
+
+    /// Creates the block with issues from the tracker in the JSON config file at `config`, see
+    /// the `tickets` module
+    #[must_use]
+    pub fn with_tickets(ctx: &BlockContext, config: &Path) -> Self {
+        let mut block = Self::new(ctx);
+        match TicketConfig::load(config) {
+            Ok(tickets) => block.tickets = Some(Arc::new(tickets)),
+            Err(e) => report(e),
+        }
+        block
+    }
+
+    /// Returns the intervals of the data file, only parsing it again when it changed
+    fn intervals(&self, path: &PathBuf) -> Vec<Interval> {
+        let mut cache = self.cache.lock().unwrap();
+        let Ok(modified) = std::fs::metadata(path).and_then(|meta| meta.modified()) else {
+            cache.remove(path);
+            return vec![];
+        };
+        if let Some((cached, intervals)) = cache.get(path) {
+            if *cached == modified {
+                return intervals.clone();
+            }
+        }
+        let intervals: Vec<_> = std::fs::read_to_string(path)
+            .map(|contents| contents.lines().filter_map(parse_interval).collect())
+            .unwrap_or_default();
+        cache.insert(path.clone(), (modified, intervals.clone()));
+        intervals
+    }
+}
+
+/// Lets the user pick one of the assigned issues and starts tracking it, which stops the running
+/// interval
+fn pick_issue(tickets: &TicketConfig, running: Option<&Interval>) -> Result<(), String> {
+    let issues = tickets.assigned()?;
+    let mut picker = Command::new("sh")
+        .arg("-c")
+        .arg(&tickets.picker)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {e}", tickets.picker))?;
+    let mut choices = String::new();
+    for issue in &issues {
+        let _idc = writeln!(choices, "{} {}", issue.key, issue.summary);
+    }
+    // Pickers read all choices before showing them, so this can't block on their output
+    let written = picker
+        .stdin
+        .take()
+        .map(|mut stdin| stdin.write_all(choices.as_bytes()));
+    let output = picker
+        .wait_with_output()
+        .map_err(|e| format!("Failed to run {}: {e}", tickets.picker))?;
+    if let Some(Err(e)) = written {
+        return Err(format!("Failed to pass issues to {}: {e}", tickets.picker));
+    }
+    // Closing the picker without choosing fails or prints nothing
+    let chosen = String::from_utf8_lossy(&output.stdout);
+    let Some(key) = chosen
+        .split_whitespace()
+        .next()
+        .filter(|_| output.status.success())
+    else {
+        return Ok(());
+    };
+
+    let started = Command::new("timew")
+        .args(["start", key])
+        .output()
+        .map_err(|e| format!("Failed to run timew: {e}"))?;
+    if !started.status.success() {
+        return Err(format!(
+            "Failed to start tracking {key}: {}",
+            String::from_utf8_lossy(&started.stderr).trim()
+        ));
+    }
+    if let Some(running) = running {
+        log_work(tickets, running);
+    }
+    Ok(())
+}
+
+/// Logs the time spent on a stopped interval to its issue, if it was tracking one
+fn log_work(tickets: &TicketConfig, interval: &Interval) {
+    let Some(key) = tickets.find_key(&interval.tags) else {
+        return;
+    };
+    let spent = (Utc::now() - interval.start).to_std().unwrap_or_default();
+    if let Err(e) = tickets.log_work(key, interval.start, spent) {
+        report(format!("Failed to log work on {key}: {e}"));
+    }
+}
+
+/// Finds the timewarrior data directory
+fn find_data_dir() -> Option<PathBuf> {
+    let candidates = [
+        std::env::var_os("TIMEWARRIORDB").map(PathBuf::from),
+        std::env::var_os("XDG_DATA_HOME").map(|dir| PathBuf::from(dir).join("timewarrior")),
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share/timewarrior")),
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".timewarrior")),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .map(|dir| dir.join("data"))
+        .find(|dir| dir.is_dir())
+}
+
+/// Parses a line like `inc 20240101T080000Z - 20240101T090000Z # tag`
+fn parse_interval(line: &str) -> Option<Interval> {
+    let line = line.strip_prefix("inc ")?;
+    let (times, tags) = line.split_once(" # ").unwrap_or((line, ""));
+    let parse = |time: &str| {
+        NaiveDateTime::parse_from_str(time.trim(), "%Y%m%dT%H%M%SZ")
+            .ok()
+            .map(|time| time.and_utc())
+    };
+    let (start, end) = match times.split_once(" - ") {
+        Some((start, end)) => (parse(start)?, Some(parse(end)?)),
+        None => (parse(times)?, None),
+    };
+    Some(Interval {
+        start,
+        end,
+        tags: tags.replace('"', ""),
+    })
+}
+
+/// Formats a duration as hours and minutes
+fn format_delta(delta: TimeDelta) -> String {
+    let mins = delta.num_minutes();
+    format!("{}:{:02}", mins / 60, mins % 60)
+}
@@ -0,0 +1,286 @@
+//! Shows the active torrents of transmission or qBittorrent, and pauses or resumes them all
+//!
+//! qBittorrent has to allow clients on localhost without authentication.
+
+use super::confirm::Confirmation;
+use super::diagnostics_block::report;
+use super::http::{self, Body};
+use super::units::format_rate;
+use super::{Block, BlockContext, I3Block, I3Event};
+use serde_json::Value;
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+/// How often to poll the client in seconds
+const POLL_INTERVAL: u64 = 10;
+
+/// Status of transmission torrents that are downloading
+const TR_STATUS_DOWNLOAD: u64 = 4;
+
+/// Status of transmission torrents that are seeding
+const TR_STATUS_SEED: u64 = 6;
+
+/// Which client to talk to
+#[derive(Clone, Copy)]
+pub enum TorrentClient {
+    Transmission,
+    QBittorrent,
+}
+
+#[derive(Clone, PartialEq)]
+struct Transfers {
+    /// Torrents downloading or seeding
+    active: usize,
+    /// Bytes per second
+    download: f64,
+    upload: f64,
+    /// Name and remaining time of the largest downloading torrent
+    largest: Option<(String, Option<Duration>)>,
+}
+
+pub struct TorrentBlock {
+    transfers: Arc<RwLock<Option<Transfers>>>,
+    /// Whether the torrents were paused by clicking, so the block stays visible to resume them
+    paused: Arc<AtomicBool>,
+    /// Pausing or resuming needs a second click
+    confirmation: Confirmation,
+    /// Pauses or resumes all torrents
+    pause_channel: Mutex<Sender<()>>,
+}
+
+impl Block for TorrentBlock {
+    fn render(&self) -> Option<I3Block> {
+        let transfers = self.transfers.read().unwrap();
+        let transfers = transfers.as_ref()?;
+        let paused = self.paused.load(Ordering::Relaxed);
+        // Nothing to see when nothing is moving
+        if !paused && transfers.download == 0.0 && transfers.upload == 0.0 {
+            return None;
+        }
+
+        let (mut full_text, hint) = if paused {
+            ("🧲⏸".to_owned(), "Click twice to resume all torrents")
+        } else {
+            (
+                format!(
+                    "🧲{} ↓{} ↑{}",
+                    transfers.active,
+                    format_rate(transfers.download),
+                    format_rate(transfers.upload)
+                ),
+                "Click twice to pause all torrents",
+            )
+        };
+        let mut tooltip = hint.to_owned();
+        if self.confirmation.pending() {
+            full_text.push_str(if paused { " Resume?" } else { " Pause?" });
+        } else if let Some((name, eta)) = transfers.largest.as_ref().filter(|_| !paused) {
+            tooltip = format!("{name}\n{tooltip}");
+            if let Some(eta) = eta {
+                let minutes = eta.as_secs() / 60;
+                let _idc = write!(full_text, " {}:{:02}", minutes / 60, minutes % 60);
+            }
+        }
+        Some(I3Block {
+            full_text,
+            tooltip: Some(tooltip),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button == 1
+            && self.transfers.read().unwrap().is_some()
+            && self.confirmation.confirm()
+        {
+            let _idc = self.pause_channel.lock().unwrap().send(());
+        }
+    }
+}
+
+impl TorrentBlock {
+    /// Creates the block for the client at `url`, like `http://localhost:9091` for transmission
+    /// or `http://localhost:8080` for qBittorrent
+    #[must_use]
+    pub fn new(ctx: &BlockContext, url: &str, client: TorrentClient) -> Self {
+        let transfers = Arc::new(RwLock::new(None));
+        let paused = Arc::new(AtomicBool::new(false));
+        let (send, receive) = mpsc::channel::<()>();
+
+        let transfers2 = Arc::clone(&transfers);
+        let paused2 = Arc::clone(&paused);
+        let ctx2 = ctx.clone();
+        let url = url.trim_end_matches('/').to_owned();
+        std::thread::spawn(move || loop {
+            // The client is most likely just not running when it's unreachable
+            let current = query(&url, client).ok();
+            // Torrents are active again when they were resumed elsewhere
+            if current.as_ref().is_none_or(|current| current.active > 0)
+                && paused2.swap(false, Ordering::Relaxed)
+            {
+                ctx2.request_update();
+            }
+            if *transfers2.read().unwrap() != current {
+                *transfers2.write().unwrap() = current;
+                ctx2.request_update();
+            }
+
+            match receive.recv_timeout(Duration::from_secs(POLL_INTERVAL)) {
+                Ok(()) => {
+                    let result = if paused2.load(Ordering::Relaxed) {
+                        resume_all(&url, client).map(|()| false)
+                    } else {
+                        pause_all(&url, client).map(|()| true)
+                    };
+                    match result {
+                        Ok(paused) => {
+                            paused2.store(paused, Ordering::Relaxed);
+                            ctx2.request_update();
+                        }
+                        Err(e) => report(format!("Failed to pause or resume the torrents: {e}")),
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        });
+
+        Self {
+            transfers,
+            paused,
+            confirmation: Confirmation::new(ctx),
+            pause_channel: Mutex::new(send),
+        }
+    }
+}
+
+/// Calls a method of the transmission RPC interface
+fn transmission(url: &str, method: &str, arguments: &Value) -> Result<Value, String> {
+    let url = format!("{url}/transmission/rpc");
+    // Requests are only accepted with the session ID the server sends with a 409 response
+    let session = http::response_header(&url, "X-Transmission-Session-Id")?
+        .ok_or("transmission sent no session ID")?;
+    let body = serde_json::json!({ "method": method, "arguments": arguments }).to_string();
+    let response = http::request(
+        "POST",
+        &url,
+        &[format!("X-Transmission-Session-Id: {session}")],
+        Some(Body::Json(&body)),
+    )?;
+    let response: Value = serde_json::from_slice(&response).map_err(|e| e.to_string())?;
+    match response["result"].as_str() {
+        Some("success") => Ok(response["arguments"].clone()),
+        result => Err(result.unwrap_or("invalid response").to_owned()),
+    }
+}
+
+/// Sums up the transfers of all active torrents
+fn query(url: &str, client: TorrentClient) -> Result<Transfers, String> {
+    // Size, name and remaining time of downloading torrents
+    let mut downloading: Vec<(u64, String, Option<Duration>)> = vec![];
+    // Negative when unknown
+    let eta = |eta: &Value| {
+        eta.as_i64()
+            .and_then(|eta| u64::try_from(eta).ok())
+            .map(Duration::from_secs)
+    };
+    let mut transfers = Transfers {
+        active: 0,
+        download: 0.0,
+        upload: 0.0,
+        largest: None,
+    };
+    match client {
+        TorrentClient::Transmission => {
+            let arguments = transmission(
+                url,
+                "torrent-get",
+                &serde_json::json!({
+                    "fields": ["name", "status", "rateDownload", "rateUpload", "eta", "totalSize"]
+                }),
+            )?;
+            for torrent in arguments["torrents"].as_array().into_iter().flatten() {
+                let status = torrent["status"].as_u64();
+                if status != Some(TR_STATUS_DOWNLOAD) && status != Some(TR_STATUS_SEED) {
+                    continue;
+                }
+                transfers.active += 1;
+                transfers.download += torrent["rateDownload"].as_f64().unwrap_or_default();
+                transfers.upload += torrent["rateUpload"].as_f64().unwrap_or_default();
+                if status == Some(TR_STATUS_DOWNLOAD) {
+                    downloading.push((
+                        torrent["totalSize"].as_u64().unwrap_or_default(),
+                        torrent["name"].as_str().unwrap_or_default().to_owned(),
+                        eta(&torrent["eta"]),
+                    ));
+                }
+            }
+        }
+        TorrentClient::QBittorrent => {
+            let torrents =
+                http::get_json(&format!("{url}/api/v2/torrents/info?filter=active"), &[])?;
+            for torrent in torrents.as_array().into_iter().flatten() {
+                transfers.active += 1;
+                transfers.download += torrent["dlspeed"].as_f64().unwrap_or_default();
+                transfers.upload += torrent["upspeed"].as_f64().unwrap_or_default();
+                let state = torrent["state"].as_str().unwrap_or_default();
+                if state == "downloading" || state == "forcedDL" {
+                    downloading.push((
+                        torrent["size"].as_u64().unwrap_or_default(),
+                        torrent["name"].as_str().unwrap_or_default().to_owned(),
+                        // 8640000 means forever
+                        eta(&torrent["eta"]).filter(|eta| eta.as_secs() < 8_640_000),
+                    ));
+                }
+            }
+        }
+    }
+    transfers.largest = downloading
+        .into_iter()
+        .max_by_key(|(size, _, _)| *size)
+        .map(|(_, name, eta)| (name, eta));
+    Ok(transfers)
+}
+
+/// Pauses all torrents
+fn pause_all(url: &str, client: TorrentClient) -> Result<(), String> {
+    match client {
+        // Without IDs, all torrents are stopped
+        TorrentClient::Transmission => {
+            transmission(url, "torrent-stop", &serde_json::json!({})).map(|_| ())
+        }
+        // Called stop since qBittorrent 5
+        TorrentClient::QBittorrent => qbittorrent_all(url, "stop", "pause"),
+    }
+}
+
+/// Resumes all torrents
+fn resume_all(url: &str, client: TorrentClient) -> Result<(), String> {
+    match client {
+        TorrentClient::Transmission => {
+            transmission(url, "torrent-start", &serde_json::json!({})).map(|_| ())
+        }
+        // Called start since qBittorrent 5
+        TorrentClient::QBittorrent => qbittorrent_all(url, "start", "resume"),
+    }
+}
+
+/// Applies a qBittorrent action to all torrents, falling back to the name used before
+/// qBittorrent 5
+fn qbittorrent_all(url: &str, action: &str, legacy: &str) -> Result<(), String> {
+    let request = |action: &str| {
+        http::request(
+            "POST",
+            &format!("{url}/api/v2/torrents/{action}"),
+            &[],
+            Some(Body::Form("hashes=all")),
+        )
+    };
+    if request(action).is_err() {
+        request(legacy)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,188 @@
+//! Counts the traffic of an interface per month and warns when a data cap comes close
+
+use super::color::Color;
+use super::diagnostics_block::report;
+use super::units::format_bytes;
+use super::{Block, I3Block, I3Event};
+use chrono::{Datelike as _, Local, NaiveDate};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// How often to persist the totals in seconds
+const SAVE_INTERVAL: u64 = 60;
+
+/// Above this share of the cap in percent, the block is colored
+const WARN_PERCENT: u64 = 90;
+
+pub struct TrafficQuotaBlock {
+    interface: String,
+    /// Data cap in bytes
+    cap: Option<u64>,
+    /// Day of the month the counting starts over
+    reset_day: u32,
+    state: Mutex<QuotaState>,
+}
+
+struct QuotaState {
+    /// First day of the counted period
+    period_start: NaiveDate,
+    /// Received bytes in the period
+    rx: u64,
+    /// Sent bytes in the period
+    tx: u64,
+    /// Counters of the interface at the previous render
+    last_counters: Option<(u64, u64)>,
+    /// Where the totals are persisted to
+    state_file: Option<PathBuf>,
+    last_save: Instant,
+}
+
+impl Block for TrafficQuotaBlock {
+    fn render(&self) -> Option<I3Block> {
+        let mut state = self.state.lock().unwrap();
+
+        // Start over every period
+        let period_start = period_start(Local::now().date_naive(), self.reset_day);
+        if state.period_start != period_start {
+            state.period_start = period_start;
+            state.rx = 0;
+            state.tx = 0;
+        }
+
+        let counters = read_counters(&self.interface);
+        if let (Some((rx, tx)), Some((last_rx, last_tx))) = (counters, state.last_counters) {
+            // Counters start over when the interface is recreated
+            state.rx += if rx >= last_rx { rx - last_rx } else { rx };
+            state.tx += if tx >= last_tx { tx - last_tx } else { tx };
+        }
+        state.last_counters = counters;
+
+        if state.last_save.elapsed().as_secs() >= SAVE_INTERVAL {
+            state.save();
+        }
+
+        let total = state.rx + state.tx;
+        if counters.is_none() && total == 0 {
+            return None;
+        }
+        let tooltip = format!(
+            "Traffic since {}\n↓ {}\n↑ {}",
+            state.period_start.format("%d.%m."),
+            format_bytes(state.rx),
+            format_bytes(state.tx)
+        );
+        let (full_text, color) = if let Some(cap) = self.cap {
+            let percent = total.saturating_mul(100) / cap.max(1);
+            (
+                format!(
+                    "{} {}/{}",
+                    self.interface,
+                    format_bytes(total),
+                    format_bytes(cap)
+                ),
+                if percent >= WARN_PERCENT {
+                    Some(Color::critical())
+                } else {
+                    None
+                },
+            )
+        } else {
+            (format!("{} {}", self.interface, format_bytes(total)), None)
+        };
+        Some(I3Block {
+            full_text,
+            color,
+            tooltip: Some(tooltip),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+}
+
+impl TrafficQuotaBlock {
+    /// Creates the block counting the traffic of `interface`, starting over on `reset_day` of
+    /// every month. The totals are persisted to `state_file` if given.
+    #[must_use]
+    pub fn new(
+        interface: String,
+        cap: Option<u64>,
+        reset_day: u32,
+        state_file: Option<PathBuf>,
+    ) -> Self {
+        let period_start = period_start(Local::now().date_naive(), reset_day);
+        let (rx, tx) = state_file
+            .as_ref()
+            .and_then(|path| load(path, period_start))
+            .unwrap_or_default();
+        Self {
+            interface,
+            cap,
+            reset_day,
+            state: Mutex::new(QuotaState {
+                period_start,
+                rx,
+                tx,
+                last_counters: None,
+                state_file,
+                last_save: Instant::now(),
+            }),
+        }
+    }
+}
+
+impl QuotaState {
+    /// Writes the totals to the state file
+    fn save(&mut self) {
+        self.last_save = Instant::now();
+        let Some(path) = &self.state_file else {
+            return;
+        };
+        let contents = format!("{}\n{}\n{}\n", self.period_start, self.rx, self.tx);
+        if let Err(e) = std::fs::write(path, contents) {
+            report(format!("Failed to write traffic totals: {e}"));
+        }
+    }
+}
+
+/// Returns the most recent reset day that is not in the future. Reset days beyond the end of a
+/// month are moved to its last day.
+fn period_start(today: NaiveDate, reset_day: u32) -> NaiveDate {
+    let reset_in = |year: i32, month: u32| {
+        (1..=reset_day.max(1))
+            .rev()
+            .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+            .unwrap_or(today)
+    };
+    let this_month = reset_in(today.year(), today.month());
+    if this_month <= today {
+        this_month
+    } else if today.month() == 1 {
+        reset_in(today.year() - 1, 12)
+    } else {
+        reset_in(today.year(), today.month() - 1)
+    }
+}
+
+/// Loads the totals from the state file if they are from the current period
+fn load(path: &Path, period_start: NaiveDate) -> Option<(u64, u64)> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let mut lines = contents.lines();
+    if lines.next()?.parse::<NaiveDate>().ok()? != period_start {
+        return None;
+    }
+    Some((lines.next()?.parse().ok()?, lines.next()?.parse().ok()?))
+}
+
+/// Reads the received and sent bytes of the interface
+fn read_counters(interface: &str) -> Option<(u64, u64)> {
+    let read = |name: &str| {
+        std::fs::read_to_string(format!("/sys/class/net/{interface}/statistics/{name}"))
+            .ok()?
+            .trim()
+            .parse::<u64>()
+            .ok()
+    };
+    Some((read("rx_bytes")?, read("tx_bytes")?))
+}
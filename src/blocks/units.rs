@@ -0,0 +1,195 @@
+//! Formats sizes and rates the same way in all blocks
+
+use std::sync::RwLock;
+
+/// The format in use, defaults to the one of the locale
+static CURRENT: RwLock<Option<Units>> = RwLock::new(None);
+
+/// Which prefixes sizes are shown with
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Prefixes {
+    /// Powers of 1000, like kB and GB
+    Si,
+    /// Powers of 1024, like KiB and GiB
+    Iec,
+}
+
+/// How sizes and rates are formatted
+#[derive(Clone, Copy)]
+pub struct Units {
+    pub prefixes: Prefixes,
+    /// Digits after the decimal separator. Values that round to 100 and more are shown without
+    /// any.
+    pub precision: usize,
+    /// Separates the integer part from the fraction, e.g. `,` in many European locales
+    pub decimal_separator: char,
+}
+
+impl Units {
+    /// Uses the format for all blocks from now on
+    pub fn configure(self) {
+        *CURRENT.write().unwrap() = Some(self);
+    }
+
+    /// Returns the format in use
+    #[must_use]
+    pub fn current() -> Self {
+        if let Some(units) = *CURRENT.read().unwrap() {
+            return units;
+        }
+        *CURRENT.write().unwrap().get_or_insert_with(Self::default)
+    }
+
+    /// Formats a number of bytes, like "1.5GiB"
+    #[must_use]
+    pub fn bytes(self, bytes: u64) -> String {
+        #[allow(clippy::cast_precision_loss)] // Exact enough for display
+        self.scaled(bytes as f64, "B")
+    }
+
+    /// Formats bytes per second, like "1.5MiB/s"
+    #[must_use]
+    pub fn rate(self, bytes_per_sec: f64) -> String {
+        self.scaled(bytes_per_sec, "B/s")
+    }
+
+    /// Scales the value to the largest fitting prefix and appends the unit
+    fn scaled(self, value: f64, unit: &str) -> String {
+        let (base, prefixes) = match self.prefixes {
+            Prefixes::Si => (1000.0, ["", "k", "M", "G", "T", "P"]),
+            Prefixes::Iec => (1024.0, ["", "Ki", "Mi", "Gi", "Ti", "Pi"]),
+        };
+        let mut value = value.max(0.0);
+        let mut prefix = 0;
+        // Decide on the rounded value, so 1023.7KiB becomes 1.0MiB instead of 1024KiB
+        while prefix < prefixes.len() - 1 && self.round(value, prefix).0 >= base {
+            value /= base;
+            prefix += 1;
+        }
+        let (_, precision) = self.round(value, prefix);
+        let mut number = format!("{value:.precision$}");
+        if self.decimal_separator != '.' {
+            number = number.replace('.', &self.decimal_separator.to_string());
+        }
+        format!("{number}{}{unit}", prefixes[prefix])
+    }
+
+    /// Rounds the value to the digits it is shown with, and returns it with that number of
+    /// digits
+    fn round(self, value: f64, prefix: usize) -> (f64, usize) {
+        let round = |precision: usize| {
+            let factor = 10_f64.powi(i32::try_from(precision).unwrap_or(i32::MAX));
+            (value * factor).round() / factor
+        };
+        // Single bytes have no fraction, and neither have values of 100 and more
+        if prefix == 0 || round(self.precision) >= 100.0 {
+            (round(0), 0)
+        } else {
+            (round(self.precision), self.precision)
+        }
+    }
+}
+
+impl Default for Units {
+    fn default() -> Self {
+        Self {
+            prefixes: Prefixes::Iec,
+            precision: 1,
+            decimal_separator: locale_decimal_separator(),
+        }
+    }
+}
+
+/// Shorthand for formatting bytes with the current format
+#[must_use]
+pub fn format_bytes(bytes: u64) -> String {
+    Units::current().bytes(bytes)
+}
+
+/// Shorthand for formatting bytes per second with the current format
+#[must_use]
+pub fn format_rate(bytes_per_sec: f64) -> String {
+    Units::current().rate(bytes_per_sec)
+}
+
+/// Guesses the decimal separator from the numeric locale, like `de_DE.UTF-8`
+fn locale_decimal_separator() -> char {
+    /// Languages writing a decimal comma
+    const COMMA: [&str; 30] = [
+        "bg", "ca", "cs", "da", "de", "el", "es", "et", "eu", "fi", "fr", "gl", "hr", "hu", "id",
+        "it", "lt", "lv", "nb", "nl", "pl", "pt", "ro", "ru", "sk", "sl", "sr", "sv", "tr", "uk",
+    ];
+    let locale = ["LC_ALL", "LC_NUMERIC", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|locale| !locale.is_empty())
+        .unwrap_or_default();
+    let language = locale.split(['_', '.', '@']).next().unwrap_or_default();
+    if COMMA.contains(&language) {
+        ','
+    } else {
+        '.'
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn units(prefixes: Prefixes, precision: usize, decimal_separator: char) -> Units {
+        Units {
+            prefixes,
+            precision,
+            decimal_separator,
+        }
+    }
+
+    #[test]
+    fn iec_prefixes() {
+        let units = units(Prefixes::Iec, 1, '.');
+        assert_eq!(units.bytes(0), "0B");
+        assert_eq!(units.bytes(1023), "1023B");
+        assert_eq!(units.bytes(1024), "1.0KiB");
+        assert_eq!(units.bytes(1536), "1.5KiB");
+        assert_eq!(units.bytes(5 * 1024 * 1024 * 1024), "5.0GiB");
+    }
+
+    #[test]
+    fn si_prefixes() {
+        let units = units(Prefixes::Si, 1, '.');
+        assert_eq!(units.bytes(999), "999B");
+        assert_eq!(units.bytes(1000), "1.0kB");
+        assert_eq!(units.bytes(1_500_000_000), "1.5GB");
+        assert_eq!(units.rate(2_500_000.0), "2.5MB/s");
+    }
+
+    #[test]
+    fn precision() {
+        assert_eq!(units(Prefixes::Iec, 2, '.').bytes(1536), "1.50KiB");
+        assert_eq!(units(Prefixes::Iec, 0, '.').bytes(1536), "2KiB");
+        // Large values have no fraction
+        assert_eq!(units(Prefixes::Iec, 1, '.').bytes(150 * 1024), "150KiB");
+    }
+
+    #[test]
+    fn rounding_rolls_over() {
+        let iec = units(Prefixes::Iec, 1, '.');
+        assert_eq!(iec.bytes(1_048_269), "1.0MiB"); // 1023.7KiB
+        assert_eq!(iec.rate(1023.7), "1.0KiB/s");
+        assert_eq!(iec.bytes(102_359), "100KiB"); // 99.96KiB
+        let si = units(Prefixes::Si, 1, '.');
+        assert_eq!(si.bytes(999_999), "1.0MB");
+        assert_eq!(si.rate(999.6), "1.0kB/s");
+    }
+
+    #[test]
+    fn negative_rates() {
+        assert_eq!(units(Prefixes::Iec, 1, '.').rate(-5.0), "0B/s");
+    }
+
+    #[test]
+    fn decimal_separator() {
+        assert_eq!(units(Prefixes::Iec, 1, ',').bytes(1536), "1,5KiB");
+        assert_eq!(units(Prefixes::Si, 2, ',').rate(1_234_000.0), "1,23MB/s");
+    }
+}
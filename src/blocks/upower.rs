@@ -0,0 +1,134 @@
+//! Follows the batteries of the machine through `UPower`
+
+use super::diagnostics_block::report;
+use super::BlockContext;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use zbus::blocking::{Connection, MessageIterator, Proxy};
+use zbus::message::Type;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::MatchRule;
+
+/// Device type of batteries
+const UP_DEVICE_KIND_BATTERY: u32 = 2;
+
+/// Device state while charging
+const UP_DEVICE_STATE_CHARGING: u32 = 1;
+
+/// The batteries as `UPower` last reported them
+pub struct UPower {
+    state: Arc<RwLock<UPowerState>>,
+}
+
+#[derive(Clone, Default)]
+pub struct UPowerState {
+    pub batteries: Vec<Battery>,
+    /// Whether the machine is connected to a charger
+    pub charging: bool,
+}
+
+#[derive(Clone)]
+pub struct Battery {
+    /// Name of the battery in sysfs, e.g. `BAT0`
+    pub name: String,
+    pub percent: u8,
+    /// Remaining energy in Wh
+    pub energy: f64,
+    /// Energy when full in Wh
+    pub energy_full: f64,
+    /// Current power draw in W
+    pub power: f64,
+    /// Time until empty or full, depending on what the battery is doing
+    pub time_left: Option<Duration>,
+}
+
+impl UPower {
+    /// Connects to `UPower` and follows its changes, requesting updates of the block
+    pub fn new(ctx: &BlockContext) -> Option<Self> {
+        let dbus_conn = Connection::system().ok()?;
+        let state = Arc::new(RwLock::new(read_state(&dbus_conn).ok()?));
+
+        // Every signal of UPower is either a property change or a device coming or going
+        let rule = MatchRule::builder()
+            .msg_type(Type::Signal)
+            .sender("org.freedesktop.UPower")
+            .ok()?
+            .build();
+        let messages = MessageIterator::for_match_rule(rule, &dbus_conn, None).ok()?;
+
+        let state2 = Arc::clone(&state);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            for _ in messages {
+                match read_state(&dbus_conn) {
+                    Ok(state) => *state2.write().unwrap() = state,
+                    Err(e) => report(format!("Failed to read UPower devices: {e}")),
+                }
+                ctx.request_update();
+            }
+        });
+
+        Some(Self { state })
+    }
+
+    /// Returns the last reported batteries, ordered by name
+    pub fn state(&self) -> UPowerState {
+        self.state.read().unwrap().clone()
+    }
+}
+
+/// Queries all batteries and whether the machine is charging
+fn read_state(dbus_conn: &Connection) -> zbus::Result<UPowerState> {
+    let upower = Proxy::new(
+        dbus_conn,
+        "org.freedesktop.UPower",
+        "/org/freedesktop/UPower",
+        "org.freedesktop.UPower",
+    )?;
+    let mut charging = !upower.get_property::<bool>("OnBattery")?;
+
+    let mut batteries = vec![];
+    let devices: Vec<OwnedObjectPath> = upower.call("EnumerateDevices", &())?;
+    for device in devices {
+        let proxy = Proxy::new(
+            dbus_conn,
+            "org.freedesktop.UPower",
+            device,
+            "org.freedesktop.UPower.Device",
+        )?;
+        // Skip batteries of peripherals, the UPS and so on
+        if proxy.get_property::<u32>("Type")? != UP_DEVICE_KIND_BATTERY
+            || !proxy.get_property::<bool>("PowerSupply")?
+        {
+            continue;
+        }
+
+        let state = proxy.get_property::<u32>("State")?;
+        charging |= state == UP_DEVICE_STATE_CHARGING;
+        let time_left = if state == UP_DEVICE_STATE_CHARGING {
+            proxy.get_property::<i64>("TimeToFull")?
+        } else {
+            proxy.get_property::<i64>("TimeToEmpty")?
+        };
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Between 0 and 100
+        let percent = proxy.get_property::<f64>("Percentage")?.round() as u8;
+        batteries.push(Battery {
+            name: proxy.get_property::<String>("NativePath")?,
+            percent,
+            energy: proxy.get_property::<f64>("Energy")?,
+            energy_full: proxy.get_property::<f64>("EnergyFull")?,
+            power: proxy.get_property::<f64>("EnergyRate")?,
+            // Unknown estimates are 0
+            time_left: u64::try_from(time_left)
+                .ok()
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+        });
+    }
+
+    batteries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(UPowerState {
+        batteries,
+        charging,
+    })
+}
@@ -0,0 +1,171 @@
+//! Shows swap activity and recent OOM kills
+
+use super::color::Color;
+use super::units::format_rate;
+use super::{Block, I3Block, I3Event};
+use std::io::Read as _;
+use std::os::unix::fs::OpenOptionsExt as _;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// How long to show an OOM kill in seconds
+const OOM_HIGHLIGHT: u64 = 300;
+
+pub struct VmstatBlock {
+    state: Mutex<State>,
+    /// Size of a page in bytes
+    page_size: u64,
+}
+
+#[derive(Default)]
+struct State {
+    /// Counters of the previous render
+    last: Option<(Instant, Counters)>,
+    /// When the last OOM kill was noticed and which process was killed
+    last_oom: Option<(Instant, Option<String>)>,
+    /// Whether the block is currently red while flashing
+    flash: bool,
+}
+
+/// Counters from `/proc/vmstat`
+#[derive(Clone, Copy)]
+struct Counters {
+    /// Pages swapped in
+    pswpin: u64,
+    /// Pages swapped out
+    pswpout: u64,
+    oom_kill: u64,
+}
+
+impl Block for VmstatBlock {
+    fn render(&self) -> Option<I3Block> {
+        let counters = read_counters()?;
+        let mut state = self.state.lock().unwrap();
+
+        let (swap_in, swap_out) = if let Some((time, last)) = state.last {
+            if counters.oom_kill > last.oom_kill {
+                state.last_oom = Some((Instant::now(), last_oom_victim()));
+            }
+            #[allow(clippy::cast_precision_loss)] // Who cares
+            let rate = |pages: u64| (pages * self.page_size) as f64 / time.elapsed().as_secs_f64();
+            (
+                rate(counters.pswpin.saturating_sub(last.pswpin)),
+                rate(counters.pswpout.saturating_sub(last.pswpout)),
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        state.last = Some((Instant::now(), counters));
+
+        let oom = state
+            .last_oom
+            .clone()
+            .filter(|(time, _)| time.elapsed().as_secs() < OOM_HIGHLIGHT);
+        let swapping = swap_in > 0.0 || swap_out > 0.0;
+        if !swapping && oom.is_none() {
+            return None;
+        }
+
+        let mut parts = vec![];
+        if oom.is_some() {
+            parts.push("OOM".to_owned());
+        }
+        if swapping {
+            parts.push(format!(
+                "swap {}↓ {}↑",
+                format_rate(swap_in),
+                format_rate(swap_out)
+            ));
+        }
+
+        // Flash while the OOM kill is recent
+        state.flash = oom.is_some() && !state.flash;
+        Some(I3Block {
+            full_text: parts.join(" "),
+            color: if state.flash {
+                Some(Color::critical())
+            } else {
+                None
+            },
+            tooltip: oom.map(|(time, victim)| {
+                format!(
+                    "Out of memory {} min ago\nKilled {}",
+                    time.elapsed().as_secs() / 60,
+                    victim.as_deref().unwrap_or("unknown process")
+                )
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+
+    fn resume(&self) {
+        self.state.lock().unwrap().last = None;
+    }
+}
+
+impl Default for VmstatBlock {
+    fn default() -> Self {
+        // SAFETY: sysconf() has no preconditions
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        Self {
+            state: Mutex::new(State::default()),
+            page_size: u64::try_from(page_size).unwrap_or(4096),
+        }
+    }
+}
+
+/// Reads the interesting counters
+fn read_counters() -> Option<Counters> {
+    let contents = std::fs::read_to_string("/proc/vmstat").ok()?;
+    let mut counters = Counters {
+        pswpin: 0,
+        pswpout: 0,
+        oom_kill: 0,
+    };
+    for line in contents.lines() {
+        let Some((key, value)) = line.split_once(' ') else {
+            continue;
+        };
+        let counter = match key {
+            "pswpin" => &mut counters.pswpin,
+            "pswpout" => &mut counters.pswpout,
+            "oom_kill" => &mut counters.oom_kill,
+            _ => continue,
+        };
+        *counter = value.parse().unwrap_or_default();
+    }
+    Some(counters)
+}
+
+/// Looks for the name of the last process killed by the OOM killer in the kernel log
+fn last_oom_victim() -> Option<String> {
+    let mut kmsg = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open("/dev/kmsg")
+        .ok()?;
+
+    // Every read returns one record, like "6,1234,5678,-;Out of memory: Killed process 42 (name) …"
+    let mut victim = None;
+    let mut record = vec![0u8; 8192];
+    loop {
+        match kmsg.read(&mut record) {
+            Ok(0) => break,
+            Ok(len) => {
+                let record = String::from_utf8_lossy(&record[..len]);
+                if let Some((_, rest)) = record.split_once("Killed process ") {
+                    if let Some((_, rest)) = rest.split_once('(') {
+                        victim = rest.split_once(')').map(|(name, _)| name.to_owned());
+                    }
+                }
+            }
+            // Records were overwritten while reading
+            Err(e) if e.raw_os_error() == Some(libc::EPIPE) => {}
+            // No more records
+            Err(_) => break,
+        }
+    }
+    victim
+}
@@ -1,11 +1,20 @@
-use super::{Block, I3Block, I3Event};
+use super::bluetooth_battery::BluetoothBattery;
+use super::bus::BusEvent;
+use super::color::Color;
+use super::diagnostics_block::report;
+use super::{escape_markup, Block, BlockContext, I3Block, I3Event};
 use libpulse_binding::callbacks::ListResult;
+use libpulse_binding::context;
+use libpulse_binding::context::introspect::{
+    SinkInfo, SinkInputInfo, SourceInfo, SourceOutputInfo,
+};
 use libpulse_binding::context::subscribe::{Facility, InterestMaskSet, Operation};
-use libpulse_binding::context::{self, introspect::SinkInfo};
 use libpulse_binding::context::{Context, FlagSet as ContextFlagSet};
 use libpulse_binding::mainloop::standard::{IterateResult, Mainloop};
+use libpulse_binding::proplist::properties;
 use libpulse_binding::volume::{ChannelVolumes, Volume};
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use std::rc::Rc;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::{Arc, Mutex, RwLock};
@@ -24,16 +33,18 @@ enum PulseCommand {
     VolUp,
     VolDown,
     ToggleMute,
+    /// Mutes the application with the highest volume
+    MuteLoudest,
     QuitThread,
 }
 
 pub struct VolumeBlock {
-    /// Cancels the usual interval timer when written to
-    timer_cancel: Arc<Mutex<Sender<()>>>,
     /// Something to tell pulse what to do
     command_sender: Arc<Mutex<Sender<PulseCommand>>>,
     /// The state to display
     state: Arc<RwLock<Option<PulseState>>>,
+    /// Batteries of bluetooth headsets
+    bluetooth: Option<Arc<BluetoothBattery>>,
 }
 
 impl Block for VolumeBlock {
@@ -42,14 +53,19 @@ impl Block for VolumeBlock {
             if state.muted {
                 return Some(I3Block {
                     full_text: "muted".to_owned(),
-                    color: Some("#ff0202".to_owned()),
+                    color: Some(Color::critical()),
                     ..Default::default()
                 });
             }
-            Some(I3Block {
-                full_text: format!("{}%", state.volume),
-                ..Default::default()
-            })
+            Some(
+                I3Block {
+                    full_text: format!("{}%", state.volume),
+                    tooltip: self.tooltip(state),
+                    tooltip_markup: true,
+                    ..Default::default()
+                }
+                .fixed_width("100%"),
+            )
         } else {
             None
         }
@@ -65,6 +81,13 @@ impl Block for VolumeBlock {
                         .wait()
                 });
             }
+            3 if evt.modifiers.iter().any(|modifier| modifier == "Shift") => {
+                let _idc = self
+                    .command_sender
+                    .lock()
+                    .unwrap()
+                    .send(PulseCommand::MuteLoudest);
+            }
             3 => {
                 let _idc = self
                     .command_sender
@@ -94,6 +117,22 @@ impl Block for VolumeBlock {
 struct PulseState {
     volume: u32,
     muted: bool,
+    sink_name: Option<String>,
+    /// Applications that are connected to any sink
+    inputs: Vec<SinkInput>,
+    /// Applications that are recording from any source
+    recording: Vec<String>,
+    source_muted: bool,
+}
+
+/// A stream of an application
+#[derive(Clone)]
+struct SinkInput {
+    app: String,
+    volume: u32,
+    muted: bool,
+    /// Paused streams are corked
+    corked: bool,
 }
 enum PulseEvent {
     Changed(PulseState),
@@ -101,21 +140,22 @@ enum PulseEvent {
 }
 
 impl VolumeBlock {
-    pub fn new(timer_cancel: Sender<()>) -> Self {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
         let (cmd_sender, cmd_receiver) = std::sync::mpsc::channel();
         let ret = Self {
-            timer_cancel: Arc::new(Mutex::new(timer_cancel)),
             state: Arc::new(RwLock::new(None)),
             command_sender: Arc::new(Mutex::new(cmd_sender)),
+            bluetooth: BluetoothBattery::shared(ctx),
         };
 
         // Start Pulse thread
         let (sender, receiver) = std::sync::mpsc::channel();
         let sender2 = sender.clone();
         let state2 = ret.state.clone();
-        let cancel2 = ret.timer_cancel.clone();
+        let ctx = ctx.clone();
         let cmd_sender2 = ret.command_sender.clone();
-        let mut handle = std::thread::spawn(move || pulse_thread(sender2, cmd_receiver));
+        let mut handle = std::thread::spawn(move || run_pulse_thread(sender2, cmd_receiver));
         std::thread::spawn(move || {
             loop {
                 match receiver.recv() {
@@ -127,11 +167,16 @@ impl VolumeBlock {
                                                                                                // thread
                         let (cmd_sender, cmd_receiver) = std::sync::mpsc::channel();
                         *cmd_sender2.lock().unwrap() = cmd_sender;
-                        handle = std::thread::spawn(move || pulse_thread(sender2, cmd_receiver));
+                        handle =
+                            std::thread::spawn(move || run_pulse_thread(sender2, cmd_receiver));
                     }
                     Ok(PulseEvent::Changed(state)) => {
+                        ctx.publish(BusEvent::Recording {
+                            apps: state.recording.clone(),
+                            muted: state.source_muted,
+                        });
                         *state2.write().unwrap() = Some(state);
-                        let _idc = cancel2.lock().unwrap().send(());
+                        ctx.request_update();
                     }
                     Err(_) => {}
                 }
@@ -139,6 +184,51 @@ impl VolumeBlock {
         });
         ret
     }
+
+    /// Shows the battery of a bluetooth headset and which applications are playing
+    fn tooltip(&self, state: &PulseState) -> Option<String> {
+        let mut lines = vec![];
+
+        // Sinks are called bluez_output.<address>.1 (PipeWire) or bluez_sink.<address>.<profile>
+        let headset = state
+            .sink_name
+            .as_deref()
+            .and_then(|name| {
+                name.strip_prefix("bluez_output.")
+                    .or_else(|| name.strip_prefix("bluez_sink."))
+            })
+            .and_then(|name| name.split('.').next());
+        if let Some(percentage) = headset
+            .zip(self.bluetooth.as_ref())
+            .and_then(|(address, bluetooth)| bluetooth.percentage_of(address))
+        {
+            lines.push(format!("🎧 Headset battery {percentage}%"));
+        }
+
+        let playing = state
+            .inputs
+            .iter()
+            .filter(|input| !input.corked)
+            .map(|input| {
+                let app = escape_markup(&input.app);
+                if input.muted {
+                    format!("{app} <i>muted</i>")
+                } else {
+                    format!("{app} <b>{}%</b>", input.volume)
+                }
+            })
+            .collect::<Vec<_>>();
+        if !playing.is_empty() {
+            lines.push("<b>Playing</b>".to_owned());
+            lines.extend(playing);
+        }
+
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join("\n"))
+        }
+    }
 }
 
 struct State {
@@ -147,6 +237,20 @@ struct State {
     default_sink_index: Option<u32>,
     default_sink_name: Option<String>,
     raw_volume: Option<ChannelVolumes>,
+    /// Sink inputs by index
+    inputs: BTreeMap<u32, SinkInput>,
+    default_source_index: Option<u32>,
+    default_source_name: Option<String>,
+    source_muted: bool,
+    /// Applications of uncorked source outputs by index
+    outputs: BTreeMap<u32, String>,
+}
+
+/// Runs the Pulse thread and reports when it fails
+fn run_pulse_thread(sender: Sender<PulseEvent>, receiver: Receiver<PulseCommand>) {
+    if let Err(e) = pulse_thread(sender, receiver) {
+        report(format!("PulseAudio: {e}"));
+    }
 }
 
 #[allow(clippy::too_many_lines)]
@@ -169,6 +273,11 @@ fn pulse_thread(
         default_sink_index: None,
         default_sink_name: None,
         raw_volume: None,
+        inputs: BTreeMap::new(),
+        default_source_index: None,
+        default_source_name: None,
+        source_muted: false,
+        outputs: BTreeMap::new(),
     }));
 
     // Connect the context
@@ -231,19 +340,73 @@ fn pulse_thread(
                             }
                         });
                 }
-                // Did the default sink change?
+                // Did something about the default source change?
+                if facility == Some(Facility::Source)
+                    && operation == Some(Operation::Changed)
+                    && Some(index) == state.read().unwrap().default_source_index
+                {
+                    context
+                        .read()
+                        .unwrap()
+                        .introspect()
+                        .get_source_info_by_index(index, source_info_callback(&state, &sender));
+                }
+                // Did the default sink or source change?
                 if facility == Some(Facility::Server) && operation == Some(Operation::Changed) {
                     request_server_info(&context, &state, &sender);
                 }
+                // Did an application start, stop or change its stream?
+                if facility == Some(Facility::SinkInput) {
+                    if operation == Some(Operation::Removed) {
+                        let mut state = state.write().unwrap();
+                        state.inputs.remove(&index);
+                        send_state(&state, &sender);
+                    } else {
+                        context
+                            .read()
+                            .unwrap()
+                            .introspect()
+                            .get_sink_input_info(index, sink_input_callback(&state, &sender));
+                    }
+                }
+                // Did an application start or stop recording?
+                if facility == Some(Facility::SourceOutput) {
+                    if operation == Some(Operation::Removed) {
+                        let mut state = state.write().unwrap();
+                        if state.outputs.remove(&index).is_some() {
+                            send_state(&state, &sender);
+                        }
+                    } else {
+                        context
+                            .read()
+                            .unwrap()
+                            .introspect()
+                            .get_source_output_info(index, source_output_callback(&state, &sender));
+                    }
+                }
             }
         })));
 
     // Subscribe to events
-    let interest = InterestMaskSet::SERVER | InterestMaskSet::SINK;
+    let interest = InterestMaskSet::SERVER
+        | InterestMaskSet::SINK
+        | InterestMaskSet::SINK_INPUT
+        | InterestMaskSet::SOURCE
+        | InterestMaskSet::SOURCE_OUTPUT;
     context.write().unwrap().subscribe(interest, |_| {});
 
     // Request initial server info
     request_server_info(&context, &state, &sender);
+    context
+        .read()
+        .unwrap()
+        .introspect()
+        .get_sink_input_info_list(sink_input_callback(&state, &sender));
+    context
+        .read()
+        .unwrap()
+        .introspect()
+        .get_source_output_info_list(source_output_callback(&state, &sender));
 
     // Handle commands
     let context2 = context.clone();
@@ -294,21 +457,35 @@ fn pulse_thread(
                         .set_sink_mute_by_index(sink, !state.muted, None);
                 }
             }
+            PulseCommand::MuteLoudest => {
+                let loudest = state
+                    .inputs
+                    .iter()
+                    .filter(|(_, input)| !input.corked && !input.muted)
+                    .max_by_key(|(_, input)| input.volume);
+                if let Some((index, _)) = loudest {
+                    context2
+                        .write()
+                        .unwrap()
+                        .introspect()
+                        .set_sink_input_mute(*index, true, None);
+                }
+            }
             PulseCommand::QuitThread => {
                 return;
             }
-        };
+        }
     });
 
     // Main loop
     loop {
         match mainloop.borrow_mut().iterate(true) {
             IterateResult::Quit(_) => {
-                eprintln!("Quit");
+                report("PulseAudio mainloop quit".to_owned());
                 break;
             }
             IterateResult::Err(_) => {
-                eprintln!("iterate state was not success, quitting...");
+                report("PulseAudio iterate state was not success, quitting...".to_owned());
                 break;
             }
             IterateResult::Success(_) => {}
@@ -359,6 +536,15 @@ fn request_server_info(
                         });
                 }
             }
+            if let Some(name) = &info.default_source_name {
+                if Some(name.to_string()) != state.read().unwrap().default_source_name {
+                    context
+                        .read()
+                        .unwrap()
+                        .introspect()
+                        .get_source_info_by_name(name, source_info_callback(&state, &sender));
+                }
+            }
         }
     });
 }
@@ -366,18 +552,111 @@ fn request_server_info(
 /// Parses sink info into the state
 fn parse_sink_info(info: &SinkInfo, state: &mut State, sender: &Sender<PulseEvent>) {
     state.default_sink_index = Some(info.index);
-    state.default_sink_name = info.name.clone().map(|x| x.to_string());
-    #[allow(
-        clippy::cast_possible_truncation,
-        clippy::cast_sign_loss,
-        clippy::cast_precision_loss
-    )]
-    let volume = ((info.volume.avg().0 as f32 / Volume::NORMAL.0 as f32) * 100.) as u32;
+    let name = info.name.clone().map(|x| x.to_string());
+    let volume = percent(&info.volume);
     let muted = info.mute;
-    if volume != state.volume || muted != state.muted {
+    if volume != state.volume || muted != state.muted || name != state.default_sink_name {
+        state.default_sink_name = name;
         state.volume = volume;
         state.muted = muted;
         state.raw_volume = Some(info.volume);
-        let _idc = sender.send(PulseEvent::Changed(PulseState { volume, muted }));
+        send_state(state, sender);
+    }
+}
+
+/// Returns a callback that parses sink input info into the state
+fn sink_input_callback(
+    state: &Arc<RwLock<State>>,
+    sender: &Rc<Sender<PulseEvent>>,
+) -> impl FnMut(ListResult<&SinkInputInfo>) + 'static {
+    let state = Arc::clone(state);
+    let sender = Sender::clone(sender);
+    move |info| {
+        if let ListResult::Item(info) = info {
+            let app = info
+                .proplist
+                .get_str(properties::APPLICATION_NAME)
+                .or_else(|| info.name.clone().map(|x| x.to_string()))
+                .unwrap_or_default();
+            let mut state = state.write().unwrap();
+            state.inputs.insert(
+                info.index,
+                SinkInput {
+                    app,
+                    volume: percent(&info.volume),
+                    muted: info.mute,
+                    corked: info.corked,
+                },
+            );
+            send_state(&state, &sender);
+        }
     }
 }
+
+/// Returns a callback that parses the info of the default source into the state
+fn source_info_callback(
+    state: &Arc<RwLock<State>>,
+    sender: &Sender<PulseEvent>,
+) -> impl FnMut(ListResult<&SourceInfo>) + 'static {
+    let state = Arc::clone(state);
+    let sender = Sender::clone(sender);
+    move |info| {
+        if let ListResult::Item(info) = info {
+            let mut state = state.write().unwrap();
+            state.default_source_index = Some(info.index);
+            state.default_source_name = info.name.clone().map(|x| x.to_string());
+            if info.mute != state.source_muted {
+                state.source_muted = info.mute;
+                send_state(&state, &sender);
+            }
+        }
+    }
+}
+
+/// Returns a callback that parses source output info into the state
+fn source_output_callback(
+    state: &Arc<RwLock<State>>,
+    sender: &Sender<PulseEvent>,
+) -> impl FnMut(ListResult<&SourceOutputInfo>) + 'static {
+    let state = Arc::clone(state);
+    let sender = Sender::clone(sender);
+    move |info| {
+        if let ListResult::Item(info) = info {
+            let mut state = state.write().unwrap();
+            if info.corked {
+                state.outputs.remove(&info.index);
+            } else {
+                let app = info
+                    .proplist
+                    .get_str(properties::APPLICATION_NAME)
+                    .or_else(|| info.name.clone().map(|x| x.to_string()))
+                    .unwrap_or_default();
+                state.outputs.insert(info.index, app);
+            }
+            send_state(&state, &sender);
+        }
+    }
+}
+
+/// Tells the block about the current state
+fn send_state(state: &State, sender: &Sender<PulseEvent>) {
+    let _idc = sender.send(PulseEvent::Changed(PulseState {
+        volume: state.volume,
+        muted: state.muted,
+        sink_name: state.default_sink_name.clone(),
+        inputs: state.inputs.values().cloned().collect(),
+        recording: state.outputs.values().cloned().collect(),
+        source_muted: state.source_muted,
+    }));
+}
+
+/// Converts a volume to percent
+fn percent(volume: &ChannelVolumes) -> u32 {
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    let percent = ((volume.avg().0 as f32 / Volume::NORMAL.0 as f32) * 100.) as u32;
+    percent
+}
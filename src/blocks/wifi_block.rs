@@ -0,0 +1,99 @@
+//! Shows the SSID, signal strength and frequency of the wireless connection
+
+use super::color::Color;
+use super::nl80211::{Nl80211, NL80211_IFTYPE_STATION};
+use super::{Block, I3Block, I3Event};
+use std::fmt::Write as _;
+use std::sync::Mutex;
+
+pub struct WifiBlock {
+    /// Below this signal strength in dBm, the block is colored
+    min_signal: i8,
+    nl80211: Mutex<Option<Nl80211>>,
+}
+
+/// A connected wireless interface
+struct Connection {
+    ssid: String,
+    /// Frequency in MHz
+    frequency: Option<u32>,
+    /// Signal strength in dBm
+    signal: Option<i8>,
+}
+
+impl Block for WifiBlock {
+    fn render(&self) -> Option<I3Block> {
+        let mut nl80211 = self.nl80211.lock().unwrap();
+        if nl80211.is_none() {
+            *nl80211 = Nl80211::connect().ok();
+        }
+        let Ok(connection) = connection(nl80211.as_mut()?) else {
+            // Reconnect on the next render
+            *nl80211 = None;
+            return None;
+        };
+        drop(nl80211);
+        let connection = connection?;
+
+        let mut full_text = format!("📶{}", connection.ssid);
+        if let Some(signal) = connection.signal {
+            let _idc = write!(full_text, " {signal}dBm");
+        }
+        if let Some(frequency) = connection.frequency {
+            let _idc = write!(full_text, " {:.1}GHz", f64::from(frequency) / 1000.0);
+        }
+        Some(I3Block {
+            full_text,
+            color: if connection
+                .signal
+                .is_some_and(|signal| signal < self.min_signal)
+            {
+                Some(Color::critical())
+            } else {
+                None
+            },
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+}
+
+impl WifiBlock {
+    /// Creates the block, coloring it when the signal is weaker than `min_signal` dBm
+    #[must_use]
+    pub fn new(min_signal: i8) -> Self {
+        Self {
+            min_signal,
+            nl80211: Mutex::new(None),
+        }
+    }
+}
+
+impl Default for WifiBlock {
+    fn default() -> Self {
+        Self::new(-75)
+    }
+}
+
+/// Finds the first client interface that is connected to a network
+fn connection(nl80211: &mut Nl80211) -> std::io::Result<Option<Connection>> {
+    for interface in nl80211.interfaces()? {
+        if interface.kind != NL80211_IFTYPE_STATION {
+            continue;
+        }
+        let Some(ssid) = interface.ssid.clone() else {
+            continue;
+        };
+        let signal = nl80211
+            .stations(&interface)?
+            .iter()
+            .find_map(|station| station.signal);
+        return Ok(Some(Connection {
+            ssid,
+            frequency: interface.frequency,
+            signal,
+        }));
+    }
+    Ok(None)
+}
@@ -0,0 +1,286 @@
+//! Shows the active `WireGuard` tunnel and how long ago its peers were last heard from
+
+use super::color::Color;
+use super::diagnostics_block::report;
+use super::netlink::{attributes, GenericNetlink};
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::fmt::Write as _;
+use std::io::ErrorKind;
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often to poll the tunnels in seconds
+const POLL_INTERVAL: u64 = 10;
+
+/// After this many seconds without a handshake, the peer is gone. Handshakes happen every two
+/// minutes while there is traffic, and the kernel gives up on a session after three.
+const STALE_AFTER: u64 = 180;
+
+/// Command dumping a device
+const WG_CMD_GET_DEVICE: u8 = 0;
+
+/// Device attribute holding the interface name
+const WGDEVICE_A_IFNAME: u16 = 2;
+
+/// Device attribute holding the peers
+const WGDEVICE_A_PEERS: u16 = 8;
+
+/// Peer attribute holding the wall clock time of the last handshake
+const WGPEER_A_LAST_HANDSHAKE_TIME: u16 = 6;
+
+/// Last polled tunnels and when they were polled
+type Polled = Mutex<Option<(Instant, Vec<Tunnel>)>>;
+
+pub struct WireGuardBlock {
+    /// Tunnel brought up when none is active, otherwise the first inactive interface
+    tunnel: Option<String>,
+    last: Arc<Polled>,
+    ctx: BlockContext,
+}
+
+#[derive(Clone)]
+struct Tunnel {
+    name: String,
+    up: bool,
+    /// Configured by systemd-networkd instead of wg-quick
+    networkd: bool,
+    handshake: Handshake,
+}
+
+#[derive(Clone, Copy)]
+enum Handshake {
+    /// Reading the peers needs `CAP_NET_ADMIN`
+    Unknown,
+    Never,
+    /// Most recent handshake of all peers
+    At(SystemTime),
+}
+
+impl Block for WireGuardBlock {
+    fn render(&self) -> Option<I3Block> {
+        let tunnels = self.tunnels();
+        let Some(active) = tunnels.iter().find(|tunnel| tunnel.up) else {
+            let idle = self.idle_tunnel(&tunnels)?;
+            return Some(I3Block {
+                full_text: format!("🔓{}", idle.name),
+                tooltip: Some(format!("Click to bring {} up", idle.name)),
+                ..Default::default()
+            });
+        };
+
+        let (age, stale, mut tooltip) = match active.handshake {
+            Handshake::At(time) => {
+                let age = time.elapsed().unwrap_or_default();
+                let tooltip = format!("Last handshake {} ago", format_age(age));
+                (Some(age), age.as_secs() >= STALE_AFTER, tooltip)
+            }
+            Handshake::Never => (None, true, "No handshake yet".to_owned()),
+            Handshake::Unknown => (
+                None,
+                false,
+                "Handshakes can only be read with CAP_NET_ADMIN".to_owned(),
+            ),
+        };
+        for other in tunnels.iter().filter(|tunnel| tunnel.up).skip(1) {
+            let _idc = write!(tooltip, "\nAlso up: {}", other.name);
+        }
+        tooltip.push_str("\nClick to bring the tunnel down");
+
+        Some(I3Block {
+            full_text: if let Some(age) = age {
+                format!("🔒{} {}", active.name, format_age(age))
+            } else {
+                format!("🔒{}", active.name)
+            },
+            color: if stale {
+                Some(Color::critical())
+            } else {
+                Some(Color::good())
+            },
+            tooltip: Some(tooltip),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button != 1 {
+            return;
+        }
+        let Some((_, tunnels)) = self.last.lock().unwrap().clone() else {
+            return;
+        };
+        let (tunnel, up) = if let Some(active) = tunnels.iter().find(|tunnel| tunnel.up) {
+            (active.clone(), false)
+        } else if let Some(idle) = self.idle_tunnel(&tunnels) {
+            (idle, true)
+        } else {
+            return;
+        };
+
+        // systemctl may wait for a polkit prompt, so don't block the bar
+        let last = Arc::clone(&self.last);
+        let ctx = self.ctx.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = set_up(&tunnel, up) {
+                report(format!("Failed to switch the WireGuard tunnel: {e}"));
+            }
+            // Show the new state on the next render
+            *last.lock().unwrap() = None;
+            ctx.request_update();
+        });
+    }
+}
+
+impl WireGuardBlock {
+    /// Creates the block. `tunnel` is brought up on click when no tunnel is active, like a
+    /// wg-quick configuration that has no interface while it's down. Without it, the first
+    /// inactive interface is. The block is hidden when there is nothing to bring up.
+    #[must_use]
+    pub fn new(ctx: &BlockContext, tunnel: Option<&str>) -> Self {
+        Self {
+            tunnel: tunnel.map(ToOwned::to_owned),
+            last: Arc::new(Mutex::new(None)),
+            ctx: ctx.clone(),
+        }
+    }
+
+    /// Returns the tunnel to bring up when none is active, if there is one
+    fn idle_tunnel(&self, tunnels: &[Tunnel]) -> Option<Tunnel> {
+        let Some(name) = &self.tunnel else {
+            return tunnels.first().cloned();
+        };
+        if let Some(tunnel) = tunnels.iter().find(|tunnel| tunnel.name == *name) {
+            return Some(tunnel.clone());
+        }
+        // /etc/wireguard is usually only readable by root, so only a missing file counts
+        let config = std::fs::metadata(format!("/etc/wireguard/{name}.conf"));
+        if config.is_err_and(|e| e.kind() != ErrorKind::PermissionDenied) {
+            return None;
+        }
+        Some(Tunnel {
+            name: name.clone(),
+            up: false,
+            networkd: false,
+            handshake: Handshake::Unknown,
+        })
+    }
+
+    /// Returns the cached tunnels, polling them again if they are too old
+    fn tunnels(&self) -> Vec<Tunnel> {
+        let mut last = self.last.lock().unwrap();
+        if let Some((time, tunnels)) = &*last {
+            if time.elapsed() < Duration::from_secs(POLL_INTERVAL) {
+                return tunnels.clone();
+            }
+        }
+        let tunnels = read_tunnels();
+        *last = Some((Instant::now(), tunnels.clone()));
+        tunnels
+    }
+}
+
+/// Lists all `WireGuard` interfaces with their handshakes
+fn read_tunnels() -> Vec<Tunnel> {
+    let Ok(dir) = std::fs::read_dir("/sys/class/net") else {
+        return vec![];
+    };
+    let mut netlink = GenericNetlink::connect().ok().and_then(|mut socket| {
+        let family = socket.family("wireguard").ok()?;
+        Some((socket, family))
+    });
+    let mut tunnels: Vec<_> = dir
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let uevent = std::fs::read_to_string(path.join("uevent")).ok()?;
+            if !uevent.lines().any(|line| line == "DEVTYPE=wireguard") {
+                return None;
+            }
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Tunnels without peers report "unknown"
+            let up = std::fs::read_to_string(path.join("operstate"))
+                .is_ok_and(|state| state.trim() != "down");
+            let index = std::fs::read_to_string(path.join("ifindex")).ok()?;
+            let networkd =
+                std::fs::read_to_string(format!("/run/systemd/netif/links/{}", index.trim()))
+                    .is_ok_and(|link| link.lines().any(|line| line.starts_with("NETWORK_FILE=")));
+            let handshake = netlink
+                .as_mut()
+                .map_or(Handshake::Unknown, |(socket, family)| {
+                    read_handshake(socket, *family, &name)
+                });
+            Some(Tunnel {
+                name,
+                up,
+                networkd,
+                handshake,
+            })
+        })
+        .collect();
+    tunnels.sort_by(|a, b| a.name.cmp(&b.name));
+    tunnels
+}
+
+/// Reads the most recent handshake of all peers of the interface
+fn read_handshake(socket: &mut GenericNetlink, family: u16, interface: &str) -> Handshake {
+    let mut name = interface.as_bytes().to_vec();
+    name.push(0);
+    let Ok(replies) = socket.request(
+        family,
+        WG_CMD_GET_DEVICE,
+        true,
+        &[(WGDEVICE_A_IFNAME, &name)],
+    ) else {
+        return Handshake::Unknown;
+    };
+    // Peers are nested twice, once for the list and once for each peer
+    let latest = replies
+        .iter()
+        .flat_map(|reply| attributes(reply))
+        .filter(|(kind, _)| *kind == WGDEVICE_A_PEERS)
+        .flat_map(|(_, peers)| attributes(peers))
+        .flat_map(|(_, peer)| attributes(peer))
+        .filter(|(kind, _)| *kind == WGPEER_A_LAST_HANDSHAKE_TIME)
+        .filter_map(|(_, time)| {
+            // A timespec with 64 bit seconds and nanoseconds
+            let secs = u64::from_ne_bytes(time.get(..8)?.try_into().ok()?);
+            (secs > 0).then(|| SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+        })
+        .max();
+    latest.map_or(Handshake::Never, Handshake::At)
+}
+
+/// Brings the tunnel up or down, asking for authorization through polkit
+fn set_up(tunnel: &Tunnel, up: bool) -> Result<(), String> {
+    let status = if tunnel.networkd {
+        Command::new("networkctl")
+            .args([if up { "up" } else { "down" }, &tunnel.name])
+            .status()
+    } else {
+        Command::new("systemctl")
+            .args([
+                if up { "start" } else { "stop" },
+                &format!("wg-quick@{}", tunnel.name),
+            ])
+            .status()
+    }
+    .map_err(|e| e.to_string())?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(status.to_string())
+    }
+}
+
+/// Formats how long ago something happened
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    if secs >= 60 * 60 {
+        format!("{}h", secs / 60 / 60)
+    } else if secs >= 60 {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{secs}s")
+    }
+}
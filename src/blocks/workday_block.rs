@@ -0,0 +1,148 @@
+//! Shows how much of the workday is done as a small progress bar
+
+use super::bus::BusEvent;
+use super::color::Color;
+use super::steady_clock::SteadyClock;
+use super::{Block, BlockContext, I3Block, I3Event};
+use chrono::{Datelike as _, Local, NaiveTime, TimeDelta, Weekday};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// How many characters the bar takes up
+const BAR_WIDTH: u8 = 5;
+
+/// Partially filled characters, by eighths
+const PARTIAL: [char; 8] = [' ', '▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+/// What counts as done
+#[derive(Clone, Copy)]
+pub enum WorkdaySource {
+    /// The time since the start of the workday, on weekdays
+    Clock,
+    /// The time tracked today, as published by the timewarrior block
+    Tracked,
+}
+
+pub struct WorkdayBlock {
+    start: NaiveTime,
+    end: NaiveTime,
+    source: WorkdaySource,
+    /// Latest time tracked today
+    tracked: Arc<RwLock<Option<Duration>>>,
+    /// Keeps the progress from jumping when the wall clock is stepped
+    clock: SteadyClock,
+}
+
+impl Block for WorkdayBlock {
+    fn render(&self) -> Option<I3Block> {
+        let length = self.end.signed_duration_since(self.start);
+        if length <= TimeDelta::zero() {
+            return None;
+        }
+        let now = self.clock.now().with_timezone(&Local);
+        let (done, tooltip) = match self.source {
+            WorkdaySource::Clock => {
+                if matches!(now.weekday(), Weekday::Sat | Weekday::Sun) {
+                    return None;
+                }
+                let done = now
+                    .time()
+                    .signed_duration_since(self.start)
+                    .clamp(TimeDelta::zero(), length);
+                let tooltip = format!(
+                    "{} left until {}",
+                    format_delta(length - done),
+                    self.end.format("%H:%M")
+                );
+                (done, tooltip)
+            }
+            WorkdaySource::Tracked => {
+                let tracked = (*self.tracked.read().unwrap())?;
+                let done = TimeDelta::from_std(tracked).ok()?.min(length);
+                let tooltip = format!(
+                    "{} left of {}",
+                    format_delta(length - done),
+                    format_delta(length)
+                );
+                (done, tooltip)
+            }
+        };
+
+        #[allow(clippy::cast_precision_loss)] // Who cares
+        let progress = done.num_seconds() as f64 / length.num_seconds() as f64;
+        Some(I3Block {
+            full_text: format!("⏳▕{}▏{:.0}%", bar(progress), progress * 100.0),
+            color: if done >= length {
+                Some(Color::good())
+            } else {
+                None
+            },
+            tooltip: Some(tooltip),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, _: &I3Event) {}
+
+    fn resume(&self) {
+        self.clock.reset();
+    }
+
+    fn clock_changed(&self) {
+        self.clock.reset();
+    }
+}
+
+impl WorkdayBlock {
+    /// Creates the block for the workday from `start` to `end`, like 09:00 to 17:30
+    #[must_use]
+    pub fn new(
+        ctx: &BlockContext,
+        start: NaiveTime,
+        end: NaiveTime,
+        source: WorkdaySource,
+    ) -> Self {
+        let tracked = Arc::new(RwLock::new(None));
+
+        if matches!(source, WorkdaySource::Tracked) {
+            let tracked2 = Arc::clone(&tracked);
+            let events = ctx.subscribe();
+            let ctx = ctx.clone();
+            std::thread::spawn(move || {
+                for event in events {
+                    let BusEvent::TrackedToday(duration) = event else {
+                        continue;
+                    };
+                    *tracked2.write().unwrap() = Some(duration);
+                    ctx.request_update();
+                }
+            });
+        }
+
+        Self {
+            start,
+            end,
+            source,
+            tracked,
+            clock: SteadyClock::default(),
+        }
+    }
+}
+
+/// Draws the progress between 0 and 1 with a resolution of eighths of a character
+fn bar(progress: f64) -> String {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)] // Clamped
+    let eighths = (progress.clamp(0.0, 1.0) * f64::from(BAR_WIDTH * 8)).round() as usize;
+    let width = usize::from(BAR_WIDTH);
+    let mut bar = "█".repeat(eighths / 8);
+    if eighths / 8 < width {
+        bar.push(PARTIAL[eighths % 8]);
+        bar.push_str(&" ".repeat(width - eighths / 8 - 1));
+    }
+    bar
+}
+
+fn format_delta(delta: TimeDelta) -> String {
+    let mins = delta.num_minutes();
+    format!("{}:{:02}", mins / 60, mins % 60)
+}
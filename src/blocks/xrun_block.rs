@@ -0,0 +1,136 @@
+//! Shows the quantum and sample rate of `PipeWire` and how many xruns happened
+
+use super::color::Color;
+use super::{Block, BlockContext, I3Block, I3Event};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// How often to ask `PipeWire` for its state in seconds
+const POLL_INTERVAL: u64 = 5;
+
+pub struct XrunBlock {
+    state: Arc<RwLock<Option<GraphState>>>,
+    /// Xrun count at the last reset
+    baseline: AtomicU64,
+}
+
+#[derive(Clone, Copy)]
+struct GraphState {
+    /// Buffer size in samples
+    quantum: u32,
+    rate: u32,
+    /// Xruns since the block was started
+    xruns: u64,
+}
+
+impl Block for XrunBlock {
+    fn render(&self) -> Option<I3Block> {
+        let state = (*self.state.read().unwrap())?;
+        let xruns = state
+            .xruns
+            .saturating_sub(self.baseline.load(Ordering::Relaxed));
+        let latency = f64::from(state.quantum) / f64::from(state.rate) * 1000.0;
+        Some(I3Block {
+            full_text: format!(
+                "🎚{}/{:.1}k {xruns}xr",
+                state.quantum,
+                f64::from(state.rate) / 1000.0
+            ),
+            color: if xruns > 0 {
+                Some(Color::critical())
+            } else {
+                None
+            },
+            tooltip: Some(format!(
+                "{latency:.1}ms latency\n{xruns} xruns since the last reset"
+            )),
+            ..Default::default()
+        })
+    }
+
+    fn click(&self, evt: &I3Event) {
+        if evt.button == 1 {
+            if let Some(state) = *self.state.read().unwrap() {
+                self.baseline.store(state.xruns, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+impl XrunBlock {
+    #[must_use]
+    pub fn new(ctx: &BlockContext) -> Self {
+        let state = Arc::new(RwLock::new(None));
+
+        let state2 = Arc::clone(&state);
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            // Error counts of the nodes, which disappear with their nodes
+            let mut errors = None::<HashMap<u32, u64>>;
+            let mut xruns = 0;
+            loop {
+                let Ok(output) = std::process::Command::new("pw-top")
+                    .args(["-b", "-n", "1"])
+                    .output()
+                else {
+                    // No PipeWire installed
+                    return;
+                };
+                let output = String::from_utf8_lossy(&output.stdout);
+
+                let mut driver = None;
+                let mut new_errors = HashMap::new();
+                for node in output.lines().filter_map(parse_node) {
+                    if driver.is_none() && node.running && node.quantum > 0 && node.rate > 0 {
+                        driver = Some((node.quantum, node.rate));
+                    }
+                    // Counters can only go up while the node exists. Errors from before the
+                    // block was started are not counted.
+                    if let Some(errors) = &errors {
+                        let last = errors.get(&node.id).copied().unwrap_or_default();
+                        xruns += node.errors.saturating_sub(last);
+                    }
+                    new_errors.insert(node.id, node.errors);
+                }
+                errors = Some(new_errors);
+
+                *state2.write().unwrap() = driver.map(|(quantum, rate)| GraphState {
+                    quantum,
+                    rate,
+                    xruns,
+                });
+                ctx.request_update();
+                std::thread::sleep(Duration::from_secs(POLL_INTERVAL));
+            }
+        });
+
+        Self {
+            state,
+            baseline: AtomicU64::new(0),
+        }
+    }
+}
+
+/// A line of `pw-top`
+struct Node {
+    id: u32,
+    running: bool,
+    quantum: u32,
+    rate: u32,
+    errors: u64,
+}
+
+/// Parses a line of `pw-top`, which has the columns S, ID, QUANT, RATE, WAIT, BUSY, W/Q, B/Q,
+/// ERR, FORMAT and NAME
+fn parse_node(line: &str) -> Option<Node> {
+    let columns: Vec<_> = line.split_whitespace().collect();
+    Some(Node {
+        running: *columns.first()? == "R",
+        id: columns.get(1)?.parse().ok()?,
+        quantum: columns.get(2)?.parse().ok()?,
+        rate: columns.get(3)?.parse().ok()?,
+        errors: columns.get(8)?.parse().ok()?,
+    })
+}
@@ -0,0 +1,43 @@
+//! Blocks for status bars speaking the i3bar protocol
+//!
+//! Every block implements [`blocks::Block`]. Blocks that update in the background take a
+//! [`blocks::BlockContext`] as the first argument of their constructor, all others implement
+//! [`Default`]. The bar renders the blocks, serializes the resulting [`blocks::I3Block`]s and
+//! passes clicks back as [`I3Event`]s.
+//!
+//! The `Block` trait, `BlockContext`, `I3Block` and `I3Event` follow semantic versioning.
+//! Options of the individual blocks may still change in minor releases.
+
+#[deny(clippy::pedantic)]
+// Only poisoned locks panic, which means another thread already panicked
+#[allow(clippy::missing_panics_doc)]
+pub mod blocks;
+
+/// An event received from I3
+#[derive(Debug, Default, serde::Deserialize)]
+pub struct I3Event {
+    /// Name of the clicked block, as set by the bar
+    pub name: Option<String>,
+    /// Instance of the clicked block, as set by the block
+    pub instance: Option<String>,
+    /// Mouse button that was pressed
+    pub button: u8,
+    /// Modifier keys held during the click, e.g. `Shift`
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+    /// Position of the click on the screen
+    #[serde(default)]
+    pub x: i32,
+    #[serde(default)]
+    pub y: i32,
+    /// Position of the click relative to the top left corner of the block
+    #[serde(default)]
+    pub relative_x: u32,
+    #[serde(default)]
+    pub relative_y: u32,
+    /// Size of the block in pixels
+    #[serde(default)]
+    pub width: u32,
+    #[serde(default)]
+    pub height: u32,
+}
@@ -1,68 +1,336 @@
-#[deny(clippy::pedantic)]
-mod blocks;
+mod profiler;
+mod recorder;
+mod supervisor;
 
-use blocks::Block;
-use std::io::BufRead as _;
-use std::sync::{mpsc, Arc};
-use std::time::Duration;
+use profiler::{Profiler, PROFILER_NAME};
+use recorder::Recorder;
+use statusbar_rs::blocks::diagnostics_block::report;
+use statusbar_rs::blocks::notify::{Notification, Urgency};
+use statusbar_rs::blocks::threshold::{Threshold, ThresholdNotifier};
+use statusbar_rs::blocks::{self, Block, BlockContext};
+use statusbar_rs::I3Event;
+use std::io::{BufRead as _, Write as _};
+use std::os::fd::{AsRawFd as _, FromRawFd as _, OwnedFd};
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::{mpsc, Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Show the tooltip of a block as a notification when it is left-clicked with this modifier held.
+/// Such clicks are not passed on to the block, so they don't also trigger its own action.
+const TOOLTIP_MODIFIER: Option<&str> = Some("Control");
+
+/// Use the accessibility profile with high contrast, text labels and larger blocks
+const ACCESSIBILITY: bool = false;
+
+#[cfg(feature = "profiler")]
+#[global_allocator]
+static ALLOCATOR: profiler::CountingAllocator = profiler::CountingAllocator;
+
+/// How often to check for clock jumps in seconds
+const CLOCK_POLL_INTERVAL: u64 = 5;
+
+/// How many seconds the wall clock may drift from the monotonic clock between two checks
+const CLOCK_JUMP_TOLERANCE: u64 = 2;
+
+/// Tooltips of the blocks and whether they contain markup, by block index
+type Tooltips = Arc<RwLock<Vec<Option<(String, bool)>>>>;
 
 /// Entrypoint
 fn main() {
+    let signals = match catch_termination_signals() {
+        Ok(signals) => signals,
+        Err(e) => {
+            eprintln!("Failed to handle termination signals: {e}");
+            return;
+        }
+    };
+    if std::env::args().any(|arg| arg == "--supervise") {
+        supervisor::supervise(signals);
+        return;
+    }
+    let mut args = std::env::args().skip_while(|arg| arg != "--replay");
+    if args.next().is_some() {
+        if let Some(path) = args.next() {
+            recorder::replay(&path, signals);
+        } else {
+            eprintln!("--replay needs the path of a recording");
+        }
+        return;
+    }
+
     // For cancellable sleep
-    let (send, recv) = mpsc::channel::<()>();
+    let (send, recv) = mpsc::channel();
     let sleep = Duration::from_secs(2);
 
+    // Wait for SIGINT/SIGTERM so the JSON stream can be closed properly
+    let shutdown = Arc::new(AtomicBool::new(false));
+    {
+        let shutdown = Arc::clone(&shutdown);
+        let send = send.clone();
+        std::thread::spawn(move || {
+            wait_for_termination(&signals);
+            shutdown.store(true, Ordering::Relaxed);
+            let _idc = send.send(Location::caller());
+        });
+    }
+
     // Build blocks
+    let ctx = BlockContext::new(send);
+    blocks::theme::follow(&ctx, blocks::theme::ThemeSource::Portal);
+    if ACCESSIBILITY {
+        blocks::accessibility::Accessibility::default().enable();
+    }
     let blocks: Vec<Arc<dyn Block + Sync + Send>> = vec![
-        Arc::new(blocks::volume_block::VolumeBlock::new(send.clone())),
-        Arc::new(blocks::battery_block::BatteryBlock::new(&send)),
+        Arc::<blocks::restarts_block::RestartsBlock>::default(),
+        Arc::new(blocks::diagnostics_block::DiagnosticsBlock::new(&ctx)),
+        Arc::<blocks::container_block::ContainerBlock>::default(),
+        Arc::new(blocks::lsm_block::new()),
+        Arc::new(blocks::location_block::LocationBlock::new(&ctx)),
+        Arc::new(blocks::call_block::CallBlock::new(&ctx)),
+        Arc::new(blocks::volume_block::VolumeBlock::new(&ctx)),
+        Arc::new(blocks::xrun_block::XrunBlock::new(&ctx)),
+        Arc::new(blocks::battery_block::BatteryBlock::new(
+            &ctx,
+            blocks::state_file("battery-history"),
+            blocks::battery_block::BatteryBackend::UPower,
+            blocks::battery_block::BatteryAggregation::Separate,
+            ThresholdNotifier::below(vec![
+                Threshold::new(15.0, Urgency::Normal, "Battery low", "{value} left"),
+                Threshold::new(10.0, Urgency::Critical, "Battery low", "{value} left"),
+            ]),
+        )),
         Arc::<blocks::default_route_block::DefaultRouteBlock>::default(),
-        Arc::new(blocks::dunst_block::DunstBlock::new(send)),
+        Arc::<blocks::wifi_block::WifiBlock>::default(),
+        Arc::<blocks::hotspot_block::HotspotBlock>::default(),
+        Arc::new(blocks::wireguard_block::WireGuardBlock::new(&ctx, None)),
+        Arc::new(blocks::modem_block::ModemBlock::new(&ctx)),
+        Arc::new(blocks::thunderbolt_block::ThunderboltBlock::new(&ctx)),
+        Arc::new(blocks::disk_block::DiskBlock::new(
+            &ctx,
+            vec![blocks::disk_block::Mount::root()],
+        )),
+        Arc::new(blocks::nix_block::new()),
+        Arc::new(blocks::download_block::DownloadBlock::new(
+            &ctx,
+            blocks::expand_path("%h/Downloads"),
+        )),
+        Arc::new(blocks::network_mount_block::NetworkMountBlock::new(
+            &ctx,
+            vec![],
+        )),
+        Arc::new(blocks::job_block::JobBlock::new(
+            &ctx,
+            blocks::runtime_file("jobs"),
+            true,
+        )),
+        Arc::new(blocks::mail_queue_block::MailQueueBlock::new(&ctx)),
+        Arc::new(blocks::input_method_block::InputMethodBlock::new(&ctx)),
+        Arc::new(blocks::dunst_block::DunstBlock::new(&ctx)),
+        Arc::new(blocks::energy_block::EnergyBlock::new(
+            None,
+            blocks::state_file("energy"),
+        )),
         Arc::<blocks::load_block::LoadBlock>::default(),
+        Arc::new(blocks::cpu_block::CpuBlock::new(&ctx)),
+        Arc::new(blocks::memory_block::MemoryBlock::new(&ctx)),
+        Arc::<blocks::vmstat_block::VmstatBlock>::default(),
         Arc::<blocks::temperature_block::TemperatureBlock>::default(),
-        Arc::<blocks::date_block::DateBlock>::default(),
+        Arc::<blocks::ambient_light_block::AmbientLightBlock>::default(),
+        Arc::new(blocks::switch_block::SwitchBlock::new(
+            &ctx,
+            blocks::switch_block::SwitchCommands::default(),
+        )),
+        Arc::<blocks::throttle_block::ThrottleBlock>::default(),
+        Arc::new(blocks::fan_block::FanBlock::new(&ctx)),
+        Arc::new(blocks::screen_time_block::ScreenTimeBlock::new(
+            &ctx,
+            blocks::state_file("screen-time"),
+        )),
+        Arc::new(blocks::timewarrior_block::TimewarriorBlock::new(&ctx)),
+        Arc::new(blocks::workday_block::WorkdayBlock::new(
+            &ctx,
+            chrono::NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            chrono::NaiveTime::from_hms_opt(17, 30, 0).unwrap(),
+            blocks::workday_block::WorkdaySource::Clock,
+        )),
+        Arc::new(blocks::break_block::BreakBlock::new(
+            &ctx,
+            Duration::from_secs(90 * 60),
+            Duration::from_secs(10 * 60),
+        )),
+        Arc::new(blocks::timer_block::TimerBlock::new(
+            &ctx,
+            Duration::from_secs(25 * 60),
+        )),
+        Arc::new(
+            blocks::config_file("holidays.ics")
+                .map(|path| blocks::date_block::DateBlock::new(&path, None))
+                .unwrap_or_default(),
+        ),
     ];
 
-    // Header block
-    println!(
-        "{}",
-        serde_json::json!({
-            "version": 1,
-            "stop_signal": 19,
-            "cont_signal": 18,
-            "click_events": true,
-        })
-    );
-
-    // Begin infinite JSON stream
-    println!("[");
+    print_header();
     let mut out = Vec::with_capacity(blocks.len());
 
     // Set up mouse event handler
+    let recorder = Recorder::new().map(Arc::new);
     let blocks2 = blocks.iter().map(Arc::clone).collect();
+    let tooltips: Tooltips = Arc::new(RwLock::new(vec![None; blocks.len()]));
+    let tooltips2 = Arc::clone(&tooltips);
+    let recorder2 = recorder.clone();
     std::thread::spawn(move || {
-        event_handler(blocks2);
+        event_handler(blocks2, &tooltips2, recorder2.as_deref());
     });
+    let blocks2: Vec<_> = blocks.iter().map(Arc::clone).collect();
+    {
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            if let Err(e) = resume_handler(&blocks2, &ctx) {
+                report(format!("Failed to watch for suspend: {e}"));
+            }
+        });
+    }
+    let blocks2: Vec<_> = blocks.iter().map(Arc::clone).collect();
+    std::thread::spawn(move || clock_watcher(&blocks2, &ctx));
 
-    // Loop forever over all blocks
-    loop {
+    // Loop over all blocks until we are told to stop
+    let mut profiler = Profiler::new(blocks.len());
+    let mut line = Vec::with_capacity(4096);
+    let mut last_line = Vec::with_capacity(4096);
+    while !shutdown.load(Ordering::Relaxed) {
+        let mut stdout = std::io::stdout().lock();
         for (index, block) in blocks.iter().enumerate() {
+            let output = if let Some(profiler) = &mut profiler {
+                profiler.render(index, block.as_ref())
+            } else {
+                block.render()
+            };
             // Allow skipping blocks
-            if let Some(mut output) = block.render() {
+            if let Some(mut output) = output {
                 output.name = index.to_string();
+                if let Some(accessibility) = blocks::accessibility::Accessibility::current() {
+                    accessibility.apply(&mut output);
+                }
+                output.add_markers();
+                tooltips.write().unwrap()[index] = output
+                    .tooltip
+                    .take()
+                    .map(|tooltip| (tooltip, output.tooltip_markup));
                 out.push(output);
+            } else {
+                tooltips.write().unwrap()[index] = None;
+            }
+        }
+        if let Some(profiler) = &mut profiler {
+            out.push(profiler.summary(&blocks));
+        }
+        // Output all blocks, but only wake up i3bar if something changed
+        line.clear();
+        serde_json::to_writer(&mut line, &out).unwrap();
+        line.extend_from_slice(b",\n");
+        if line != last_line {
+            if let Some(recorder) = &recorder {
+                recorder.output(&line[..line.len() - 2]);
             }
+            let _ = stdout.write_all(&line);
+            let _ = stdout.flush();
+            std::mem::swap(&mut line, &mut last_line);
         }
-        // Output all blocks
-        println!("{},", serde_json::to_string(&out).unwrap());
         // Reset and wait before restarting loop
+        drop(stdout);
         out.clear();
-        let _ = recv.recv_timeout(sleep);
+        if let Ok(location) = recv.recv_timeout(sleep) {
+            if let Some(profiler) = &mut profiler {
+                profiler.wakeup(location);
+            }
+        }
+    }
+
+    for block in &blocks {
+        block.shutdown();
+    }
+
+    // End the infinite JSON stream. All other threads only hold handles that
+    // are cleaned up by the kernel, so they can just die with the process.
+    let mut stdout = std::io::stdout().lock();
+    let _ = writeln!(stdout, "]");
+    let _ = stdout.flush();
+}
+
+/// Prints the header and begins the infinite JSON stream
+fn print_header() {
+    println!(
+        "{}",
+        serde_json::json!({
+            "version": 1,
+            "stop_signal": 19,
+            "cont_signal": 18,
+            "click_events": true,
+        })
+    );
+    println!("[");
+}
+
+/// Write end of the pipe that wakes up [`wait_for_termination`]
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// Wakes up [`wait_for_termination`]
+extern "C" fn on_termination_signal(_: libc::c_int) {
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    // SAFETY: write() is async-signal-safe and the buffer is valid for its length
+    unsafe {
+        libc::write(fd, [0u8].as_ptr().cast(), 1);
+    }
+}
+
+/// Makes SIGINT and SIGTERM wake up the returned pipe instead of killing the bar. Unlike
+/// blocking the signals, this isn't inherited by the programs the blocks spawn, since exec()
+/// resets handled signals to their default action.
+fn catch_termination_signals() -> std::io::Result<OwnedFd> {
+    let mut fds = [0; 2];
+    // SAFETY: The array has room for both fds
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // SAFETY: The fds were just opened and are owned by nobody else. The write end is never
+    // closed since the handler may run at any time.
+    let read = unsafe { OwnedFd::from_raw_fd(fds[0]) };
+    SIGNAL_PIPE.store(fds[1], Ordering::Relaxed);
+
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        // SAFETY: The action is fully initialized and the handler only does async-signal-safe
+        // things
+        let ret = unsafe {
+            let mut action = std::mem::zeroed::<libc::sigaction>();
+            action.sa_sigaction =
+                on_termination_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(signal, &action, std::ptr::null_mut())
+        };
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
     }
+    Ok(read)
+}
+
+/// Waits until SIGINT or SIGTERM arrives
+fn wait_for_termination(signals: &OwnedFd) {
+    let mut buf = [0u8; 1];
+    // SAFETY: The buffer is valid for its length
+    while unsafe { libc::read(signals.as_raw_fd(), buf.as_mut_ptr().cast(), 1) } < 0
+        && std::io::Error::last_os_error().kind() == std::io::ErrorKind::Interrupted
+    {}
 }
 
 /// Handles I3 mouse events
-fn event_handler(blocks: Vec<Arc<dyn Block + Sync + Send>>) {
+fn event_handler(
+    blocks: Vec<Arc<dyn Block + Sync + Send>>,
+    tooltips: &Tooltips,
+    recorder: Option<&Recorder>,
+) {
     let stdin = std::io::stdin();
     for line in stdin.lock().lines().map_while(Result::ok) {
         // Pretty much I3's "hello"
@@ -70,30 +338,102 @@ fn event_handler(blocks: Vec<Arc<dyn Block + Sync + Send>>) {
             continue;
         }
         // Handle the event
-        if let Ok(event) = serde_json::from_str::<I3Event>(line.strip_prefix(',').unwrap_or(&line))
-        {
+        let json = line.strip_prefix(',').unwrap_or(&line);
+        if let Ok(event) = serde_json::from_str::<I3Event>(json) {
+            if let Some(recorder) = recorder {
+                recorder.click(json);
+            }
             if let Some(ref name) = event.name {
+                if name == PROFILER_NAME {
+                    continue;
+                }
                 if let Ok(name) = name.parse::<usize>() {
                     if let Some(block) = blocks.get(name) {
-                        block.click(&event);
+                        let tooltip_click = event.button == 1
+                            && TOOLTIP_MODIFIER.is_some_and(|modifier| {
+                                event.modifiers.iter().any(|m| m == modifier)
+                            });
+                        if tooltip_click {
+                            show_tooltip(tooltips.read().unwrap()[name].as_ref());
+                        } else {
+                            block.click(&event);
+                        }
                     } else {
-                        eprintln!("Got event for invalid block from i3: {}", name);
+                        report(format!("Got event for invalid block from i3: {}", name));
                     }
                 } else {
-                    eprintln!("Received invalid block name from i3: {}", name);
+                    report(format!("Received invalid block name from i3: {}", name));
                 }
             } else {
-                eprintln!("Received event without name from i3");
+                report("Received event without name from i3".to_owned());
             }
         } else {
-            eprintln!("Received invalid JSON from i3: {}", line);
+            report(format!("Received invalid JSON from i3: {}", line));
+        }
+    }
+}
+
+/// Tells all blocks when the machine resumed from suspend
+fn resume_handler(blocks: &[Arc<dyn Block + Sync + Send>], ctx: &BlockContext) -> zbus::Result<()> {
+    let dbus_conn = zbus::blocking::Connection::system()?;
+    let proxy = zbus::blocking::Proxy::new(
+        &dbus_conn,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager",
+    )?;
+    for signal in proxy.receive_signal("PrepareForSleep")? {
+        // True before suspending, false after resuming
+        let suspending = signal.body().deserialize::<bool>()?;
+        if !suspending {
+            for block in blocks {
+                block.resume();
+            }
+            ctx.request_update();
+        }
+    }
+    Ok(())
+}
+
+/// Tells all blocks when the wall clock jumped, e.g. because NTP stepped it, or the timezone
+/// changed
+fn clock_watcher(blocks: &[Arc<dyn Block + Sync + Send>], ctx: &BlockContext) {
+    let offset = || chrono::Local::now().offset().local_minus_utc();
+    let mut last = (Instant::now(), SystemTime::now(), offset());
+    loop {
+        std::thread::sleep(Duration::from_secs(CLOCK_POLL_INTERVAL));
+        let now = (Instant::now(), SystemTime::now(), offset());
+
+        let monotonic = now.0 - last.0;
+        let wall = now.1.duration_since(last.1).unwrap_or_default();
+        let jumped = wall.abs_diff(monotonic) > Duration::from_secs(CLOCK_JUMP_TOLERANCE);
+        if jumped || now.2 != last.2 {
+            for block in blocks {
+                block.clock_changed();
+            }
+            ctx.request_update();
         }
+        last = now;
     }
 }
 
-/// An event received from I3
-#[derive(Debug, Default, serde::Deserialize)]
-pub struct I3Event {
-    name: Option<String>,
-    button: u8,
+/// Shows a tooltip as a transient notification
+fn show_tooltip(tooltip: Option<&(String, bool)>) {
+    let Some((tooltip, markup)) = tooltip else {
+        return;
+    };
+    let (summary, body) = tooltip.split_once('\n').unwrap_or((tooltip, ""));
+    // Notification daemons interpret the body as markup
+    Notification {
+        summary: summary.to_owned(),
+        body: if *markup {
+            body.to_owned()
+        } else {
+            blocks::escape_markup(body)
+        },
+        urgency: Urgency::Low,
+        transient: true,
+        ..Default::default()
+    }
+    .show();
 }
@@ -0,0 +1,144 @@
+//! Opt-in instrumentation of the blocks, enabled by setting `STATUSBAR_PROFILE`
+//!
+//! Allocations are only counted when built with the `profiler` feature, since counting them
+//! slows down every allocation.
+
+use statusbar_rs::blocks::{Block, I3Block};
+#[cfg(feature = "profiler")]
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::panic::Location;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How often to print the full report in seconds
+const REPORT_INTERVAL: u64 = 60;
+
+/// Name of the block showing the slowest block
+pub const PROFILER_NAME: &str = "profiler";
+
+thread_local! {
+    /// Allocations made by the current thread
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/// Allocator that counts allocations per thread
+#[cfg(feature = "profiler")]
+pub struct CountingAllocator;
+
+// SAFETY: Everything is passed on to the system allocator unchanged
+#[cfg(feature = "profiler")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _idc = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let _idc = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+/// Statistics of a single block
+#[derive(Clone, Default)]
+struct Stats {
+    renders: u64,
+    total: Duration,
+    max: Duration,
+    allocations: u64,
+}
+
+/// Collects statistics of all blocks
+pub struct Profiler {
+    stats: Vec<Stats>,
+    /// Update requests from background threads by the source file that requested them, which
+    /// is usually the file of the block
+    wakeups: BTreeMap<&'static str, u64>,
+    since: Instant,
+    last_report: Instant,
+}
+
+impl Profiler {
+    /// Creates the profiler if it is enabled
+    pub fn new(blocks: usize) -> Option<Self> {
+        std::env::var_os("STATUSBAR_PROFILE")?;
+        Some(Self {
+            stats: vec![Stats::default(); blocks],
+            wakeups: BTreeMap::new(),
+            since: Instant::now(),
+            last_report: Instant::now(),
+        })
+    }
+
+    /// Renders a block and records how long it took and how many allocations it made
+    pub fn render(&mut self, index: usize, block: &dyn Block) -> Option<I3Block> {
+        let allocations = ALLOCATIONS.with(Cell::get);
+        let start = Instant::now();
+        let ret = block.render();
+        let elapsed = start.elapsed();
+
+        let stats = &mut self.stats[index];
+        stats.renders += 1;
+        stats.total += elapsed;
+        stats.max = stats.max.max(elapsed);
+        stats.allocations += ALLOCATIONS.with(Cell::get) - allocations;
+        ret
+    }
+
+    /// Records that a block asked for an update
+    pub fn wakeup(&mut self, location: &'static Location<'static>) {
+        *self.wakeups.entry(location.file()).or_default() += 1;
+    }
+
+    /// Returns a block showing the slowest block, and prints a full report from time to time
+    pub fn summary(&mut self, blocks: &[Arc<dyn Block + Sync + Send>]) -> I3Block {
+        let average =
+            |stats: &Stats| stats.total / u32::try_from(stats.renders.max(1)).unwrap_or(1);
+
+        if self.last_report.elapsed().as_secs() >= REPORT_INTERVAL {
+            self.last_report = Instant::now();
+            eprintln!(
+                "{} update requests in {}s",
+                self.wakeups.values().sum::<u64>(),
+                self.since.elapsed().as_secs()
+            );
+            let mut wakeups: Vec<_> = self.wakeups.iter().collect();
+            wakeups.sort_by(|a, b| b.1.cmp(a.1));
+            for (file, count) in wakeups {
+                eprintln!("{count:>8} requests  {file}");
+            }
+            for (block, stats) in blocks.iter().zip(&self.stats) {
+                let allocations = if cfg!(feature = "profiler") {
+                    (stats.allocations / stats.renders.max(1)).to_string()
+                } else {
+                    "-".to_owned()
+                };
+                eprintln!(
+                    "{:>10.2?} avg {:>10.2?} max {allocations:>8} allocs/render  {}",
+                    average(stats),
+                    stats.max,
+                    block.name()
+                );
+            }
+        }
+
+        let slowest = blocks
+            .iter()
+            .zip(&self.stats)
+            .max_by_key(|(_, stats)| average(stats));
+        I3Block {
+            full_text: slowest.map_or_else(String::new, |(block, stats)| {
+                let name = block.name().rsplit("::").next().unwrap_or_default();
+                format!("🐢{name} {:.1?}", average(stats))
+            }),
+            name: PROFILER_NAME.to_owned(),
+            ..Default::default()
+        }
+    }
+}
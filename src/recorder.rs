@@ -0,0 +1,115 @@
+//! Records the output and the click events to a file, enabled by setting `STATUSBAR_RECORD` to
+//! its path, and plays a recording back when passing `--replay <path>`
+
+use std::fs::File;
+use std::io::{BufRead as _, BufReader, Write as _};
+use std::os::fd::OwnedFd;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A single line of a recording
+#[derive(serde::Deserialize)]
+struct Entry {
+    /// Milliseconds since the recording started
+    time: u64,
+    /// Blocks sent to i3bar
+    output: Option<serde_json::Value>,
+    /// Event received from i3bar
+    click: Option<serde_json::Value>,
+}
+
+/// Writes everything the bar sends and receives to a file, one JSON object per line
+pub struct Recorder {
+    file: Mutex<File>,
+    since: Instant,
+}
+
+impl Recorder {
+    /// Creates the recorder if it is enabled
+    pub fn new() -> Option<Self> {
+        let path = std::env::var_os("STATUSBAR_RECORD")?;
+        match File::create(&path) {
+            Ok(file) => Some(Self {
+                file: Mutex::new(file),
+                since: Instant::now(),
+            }),
+            Err(e) => {
+                eprintln!("Failed to create {}: {e}", path.to_string_lossy());
+                None
+            }
+        }
+    }
+
+    /// Records the serialized blocks that were sent to i3bar
+    pub fn output(&self, json: &[u8]) {
+        self.write("output", json);
+    }
+
+    /// Records a click event as received from i3bar
+    pub fn click(&self, json: &str) {
+        self.write("click", json.as_bytes());
+    }
+
+    fn write(&self, kind: &str, json: &[u8]) {
+        let time = self.since.elapsed().as_millis();
+        let mut line = format!("{{\"time\":{time},\"{kind}\":").into_bytes();
+        line.extend_from_slice(json);
+        line.extend_from_slice(b"}\n");
+        // A single write keeps the lines of both threads apart
+        let _idc = self.file.lock().unwrap().write_all(&line);
+    }
+}
+
+/// Sends the recorded output to i3bar again with the original timing. Clicks are printed to
+/// stderr when they happened so they can be matched up with the output. The last output stays
+/// until we are told to stop.
+pub fn replay(path: &str, signals: OwnedFd) {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("Failed to open {path}: {e}");
+            return;
+        }
+    };
+
+    std::thread::spawn(move || {
+        crate::wait_for_termination(&signals);
+        let mut stdout = std::io::stdout().lock();
+        let _idc = writeln!(stdout, "]");
+        let _idc = stdout.flush();
+        std::process::exit(0);
+    });
+
+    crate::print_header();
+    let start = Instant::now();
+    for (number, line) in BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .enumerate()
+    {
+        let entry = match serde_json::from_str::<Entry>(&line) {
+            Ok(entry) => entry,
+            Err(e) => {
+                eprintln!("Skipping line {} of {path}: {e}", number + 1);
+                continue;
+            }
+        };
+        std::thread::sleep(Duration::from_millis(entry.time).saturating_sub(start.elapsed()));
+        if let Some(output) = entry.output {
+            let mut stdout = std::io::stdout().lock();
+            let _idc = writeln!(stdout, "{output},");
+            let _idc = stdout.flush();
+        }
+        if let Some(click) = entry.click {
+            eprintln!(
+                "{:>10.3}s click {}",
+                Duration::from_millis(entry.time).as_secs_f64(),
+                click
+            );
+        }
+    }
+    eprintln!("End of the recording");
+    loop {
+        std::thread::park();
+    }
+}
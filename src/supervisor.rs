@@ -0,0 +1,106 @@
+//! Restarts the bar when it crashes, enabled by passing `--supervise`
+
+use statusbar_rs::blocks::restarts_block::RESTARTS_VAR;
+use std::io::{BufRead as _, BufReader, Write as _};
+use std::os::fd::OwnedFd;
+use std::process::{ChildStdin, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Longest wait before restarting in seconds
+const MAX_BACKOFF: u64 = 60;
+
+/// After running this long in seconds, a crash is not counted as crashing in a loop
+const STABLE_AFTER: u64 = 300;
+
+/// Runs the bar as a child process and restarts it with exponential backoff whenever it exits
+/// unexpectedly. Click events are passed on to the current child.
+pub fn supervise(signals: OwnedFd) {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!("Failed to find the bar executable: {e}");
+            return;
+        }
+    };
+
+    // Stop the child when we are told to stop, it closes the JSON stream itself
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let child_pid = Arc::new(Mutex::new(None::<libc::pid_t>));
+    {
+        let shutdown = Arc::clone(&shutdown);
+        let child_pid = Arc::clone(&child_pid);
+        std::thread::spawn(move || {
+            crate::wait_for_termination(&signals);
+            shutdown.store(true, Ordering::Relaxed);
+            if let Some(pid) = *child_pid.lock().unwrap() {
+                // SAFETY: kill() has no memory safety preconditions
+                unsafe {
+                    libc::kill(pid, libc::SIGTERM);
+                }
+            }
+        });
+    }
+
+    // Pass click events on
+    let child_stdin = Arc::new(Mutex::new(None::<ChildStdin>));
+    {
+        let child_stdin = Arc::clone(&child_stdin);
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines().map_while(Result::ok) {
+                if let Some(stdin) = &mut *child_stdin.lock().unwrap() {
+                    let _idc = writeln!(stdin, "{line}");
+                }
+            }
+        });
+    }
+
+    let mut restarts = 0u32;
+    let mut backoff = 1;
+    loop {
+        let started = Instant::now();
+        let mut child = match Command::new(&exe)
+            .env(RESTARTS_VAR, restarts.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                eprintln!("Failed to start the bar: {e}");
+                return;
+            }
+        };
+        *child_pid.lock().unwrap() = libc::pid_t::try_from(child.id()).ok();
+        *child_stdin.lock().unwrap() = child.stdin.take();
+
+        // The header and the opening bracket were already sent by the first child
+        if let Some(stdout) = child.stdout.take() {
+            let skip = if restarts == 0 { 0 } else { 2 };
+            for line in BufReader::new(stdout)
+                .lines()
+                .map_while(Result::ok)
+                .skip(skip)
+            {
+                let mut out = std::io::stdout().lock();
+                let _idc = writeln!(out, "{line}");
+                let _idc = out.flush();
+            }
+        }
+
+        let status = child.wait();
+        *child_pid.lock().unwrap() = None;
+        if shutdown.load(Ordering::Relaxed) || status.is_ok_and(|status| status.success()) {
+            return;
+        }
+
+        restarts += 1;
+        if started.elapsed() >= Duration::from_secs(STABLE_AFTER) {
+            backoff = 1;
+        }
+        eprintln!("The bar crashed, restarting in {backoff}s");
+        std::thread::sleep(Duration::from_secs(backoff));
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}